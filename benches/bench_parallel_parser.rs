@@ -3,7 +3,9 @@ use std::fs;
 
 extern crate rust_obj_parser;
 
-use rust_obj_parser::parser;
+use rust_obj_parser::parser::{self, fused};
+
+mod common;
 
 fn bench_threaded_vs_non_threaded(c: &mut Criterion) {
     let content = fs::read_to_string("al.obj").unwrap();
@@ -13,5 +15,40 @@ fn bench_threaded_vs_non_threaded(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, bench_threaded_vs_non_threaded);
+fn bench_synthetic(c: &mut Criterion) {
+    let content = common::synthetic_obj(1000);
+
+    let mut group = c.benchmark_group("synthetic");
+    group.sample_size(10);
+    group.throughput(criterion::Throughput::Bytes(content.len() as u64));
+    group.bench_function("parallel parser", |b| {
+        b.iter(|| parser::parse_obj_threaded(content.clone()))
+    });
+    group.finish();
+}
+
+fn bench_fused_vs_three_pass(c: &mut Criterion) {
+    for (name, content) in [
+        ("al.obj", fs::read_to_string("al.obj").unwrap()),
+        ("synthetic", common::synthetic_obj(1000)),
+    ] {
+        let mut group = c.benchmark_group(format!("pipeline {}", name));
+        group.sample_size(10);
+        group.throughput(criterion::Throughput::Bytes(content.len() as u64));
+        group.bench_function("three pass", |b| {
+            b.iter(|| parser::parse_obj_threaded(content.clone()))
+        });
+        group.bench_function("fused", |b| {
+            b.iter(|| fused::parse_obj_fused(content.clone()))
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(
+    benches,
+    bench_threaded_vs_non_threaded,
+    bench_synthetic,
+    bench_fused_vs_three_pass
+);
 criterion_main!(benches);
@@ -0,0 +1,28 @@
+/// A `side` by `side` grid of quads on a wavy surface, written like typical exporter
+/// output with six decimals. `synthetic_obj(1000)` is about 60 MB.
+pub fn synthetic_obj(side: usize) -> String {
+    let mut obj_file = String::with_capacity(side * side * 64);
+
+    for row in 0..side {
+        for column in 0..side {
+            let (x, z) = (column as f32 * 0.01, row as f32 * 0.01);
+            let y = (x * 3.0).sin() * (z * 2.0).cos();
+            obj_file.push_str(&format!("v {:.6} {:.6} {:.6}\n", x, y, -z));
+        }
+    }
+
+    for row in 1..side {
+        for column in 1..side {
+            let corner = row * side + column + 1;
+            obj_file.push_str(&format!(
+                "f {} {} {} {}\n",
+                corner - side - 1,
+                corner - 1,
+                corner,
+                corner - side
+            ));
+        }
+    }
+
+    obj_file
+}
@@ -6,6 +6,10 @@ use std::thread;
 
 extern crate rust_obj_parser;
 
+use rust_obj_parser::parser::number;
+
+mod common;
+
 const NUM_CORES: usize = 4;
 
 fn partion_cases(c: &mut Criterion) {
@@ -93,7 +97,7 @@ fn partion_cases(c: &mut Criterion) {
                 messages.push(message);
             }
 
-            messages.sort_by(|a, b| a.id.cmp(&b.id));
+            messages.sort_by_key(|message| message.id);
 
             let mut index_str: Vec<Vec<usize>> = Vec::with_capacity(NUM_CORES);
             let mut vertex_str: Vec<Vec<usize>> = Vec::with_capacity(NUM_CORES);
@@ -117,7 +121,69 @@ fn partion_cases(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, partion_cases);
+/// The numbers of every `v` and the indices of every `f` line, the input
+/// `parse_vertex` and `add_vertex` see after partitioning.
+fn partitioned_numbers(obj_file: &str) -> (Vec<&str>, Vec<&str>) {
+    let (index_str, vertex_str) = obj_file
+        .lines()
+        .filter(|line| line.starts_with("v ") || line.starts_with("f "))
+        .partition::<Vec<_>, _>(|line| line.starts_with('f'));
+
+    (numbers(vertex_str), numbers(index_str))
+}
+
+fn numbers(lines: Vec<&str>) -> Vec<&str> {
+    lines
+        .into_iter()
+        .flat_map(|line| line.split_whitespace().skip(1))
+        .collect()
+}
+
+fn number_cases(c: &mut Criterion) {
+    let al = fs::read_to_string("al.obj").unwrap();
+    let synthetic = common::synthetic_obj(300);
+
+    for (name, obj_file) in [("al.obj", &al), ("synthetic", &synthetic)] {
+        let (floats, indices) = partitioned_numbers(obj_file);
+
+        let mut group = c.benchmark_group(format!("Partitioned numbers {}", name));
+        group.bench_function("f32 std", |b| {
+            b.iter(|| {
+                floats
+                    .iter()
+                    .map(|num| num.parse::<f32>().unwrap())
+                    .sum::<f32>()
+            })
+        });
+        group.bench_function("f32 number::parse_f32", |b| {
+            b.iter(|| {
+                floats
+                    .iter()
+                    .map(|num| number::parse_f32(num).unwrap())
+                    .sum::<f32>()
+            })
+        });
+        group.bench_function("usize std", |b| {
+            b.iter(|| {
+                indices
+                    .iter()
+                    .map(|index| index.parse::<usize>().unwrap())
+                    .sum::<usize>()
+            })
+        });
+        group.bench_function("usize number::parse_usize", |b| {
+            b.iter(|| {
+                indices
+                    .iter()
+                    .map(|index| number::parse_usize(index).unwrap())
+                    .sum::<usize>()
+            })
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, partion_cases, number_cases);
 criterion_main!(benches);
 
 fn chunk_and_combine(string: Vec<&str>) -> Vec<String> {
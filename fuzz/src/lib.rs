@@ -0,0 +1,141 @@
+//! The checks behind the fuzz targets. They only depend on `rust_obj_parser`, so
+//! `tests/fuzz_regressions.rs` runs the same checks on the inputs in
+//! `fuzz/regressions`, which are minimised crashes of the targets.
+//!
+//! Run a target with `cargo +nightly fuzz run obj` from the repository root, and
+//! copy what `cargo fuzz tmin` makes of a crash into `regressions/<target>/`.
+
+use rust_obj_parser::mtl;
+use rust_obj_parser::obj::{self, writer};
+use rust_obj_parser::parser::{self, fused, sequential, Groups, Origin, ParseOptions};
+use rust_obj_parser::triangulate::Triangulation;
+
+/// Up to twice the default number of workers, to vary how chunks are scheduled.
+pub const MAX_THREADS: usize = 8;
+
+/// Every OBJ front-end returns rather than panics, and the threaded parser gives
+/// the same result, error or groups, whatever its number of threads. When the
+/// threaded and sequential parsers both succeed, they agree.
+pub fn check_obj(data: &[u8]) {
+    let _ = parser::parse_obj_bytes(data.to_vec());
+
+    let obj_file = match std::str::from_utf8(data) {
+        Ok(obj_file) => obj_file,
+        Err(_) => return,
+    };
+
+    let _ = fused::parse_obj_fused(obj_file.to_string());
+
+    let origin = ParseOptions {
+        origin: Some(Origin::FirstVertex),
+        ..ParseOptions::default()
+    };
+    let _ = parser::parse_obj_with_options_as::<f64>(obj_file, &origin);
+    let sequential = sequential::parse_obj_reader(obj_file.as_bytes(), Triangulation::Fan)
+        .map(|groups| bits(&groups));
+
+    for triangulation in [Triangulation::Fan, Triangulation::EarClipping] {
+        let parse = |threads| {
+            let options = ParseOptions {
+                triangulation,
+                threads,
+                ..ParseOptions::default()
+            };
+
+            parser::parse_obj_with_options(obj_file, &options)
+                .map(|obj_data| bits(&obj_data.groups))
+                .map_err(|error| error.to_string())
+        };
+
+        let expected = parse(1);
+
+        for threads in 2..=MAX_THREADS {
+            assert_eq!(parse(threads), expected, "{} threads", threads);
+        }
+
+        if let (Triangulation::Fan, Ok(sequential), Ok(expected)) =
+            (triangulation, &sequential, &expected)
+        {
+            assert_eq!(sequential, expected);
+        }
+    }
+}
+
+/// `mtl::parse` returns rather than panics, and its materials survive the writer,
+/// up to the values `-o`, `-s` and `-t` default to.
+pub fn check_mtl(data: &[u8]) {
+    let mtl_file = match std::str::from_utf8(data) {
+        Ok(mtl_file) => mtl_file,
+        Err(_) => return,
+    };
+
+    let materials = match mtl::parse(mtl_file) {
+        Ok(materials) => materials,
+        Err(_) => return,
+    };
+
+    let written = mtl::writer::to_string(&materials, &mtl::writer::WriteOptions::default());
+    let reparsed = mtl::parse(&written).expect("written MTL parses");
+
+    assert_eq!(
+        debug(&with_defaults(reparsed)),
+        debug(&with_defaults(materials))
+    );
+}
+
+/// A scene read by `obj::parse` survives the writer, in both index modes.
+pub fn check_obj_round_trip(data: &[u8]) {
+    let obj_file = match std::str::from_utf8(data) {
+        Ok(obj_file) => obj_file,
+        Err(_) => return,
+    };
+
+    let scene = match obj::parse(obj_file) {
+        Ok(scene) => scene,
+        Err(_) => return,
+    };
+
+    for index_mode in [writer::IndexMode::Absolute, writer::IndexMode::Relative] {
+        let options = writer::WriteOptions {
+            index_mode,
+            ..writer::WriteOptions::default()
+        };
+
+        let written = writer::to_string(&scene, &options);
+        let reparsed = obj::parse(&written).expect("written OBJ parses");
+
+        assert_eq!(debug(&reparsed), debug(&scene));
+    }
+}
+
+/// Pads `-o`, `-s` and `-t` to three values, as the spec defaults them.
+fn with_defaults(mut materials: Vec<mtl::Material>) -> Vec<mtl::Material> {
+    for map in materials.iter_mut().flat_map(|material| &mut material.maps) {
+        let options = &mut map.options;
+
+        for (values, default) in [
+            (&mut options.offset, 0.0),
+            (&mut options.scale, 1.0),
+            (&mut options.turbulence, 0.0),
+        ] {
+            if let Some(values) = values {
+                values.resize(3, default);
+            }
+        }
+    }
+
+    materials
+}
+
+/// NaN compares unequal to itself, its `Debug` output does not.
+fn debug<T: std::fmt::Debug>(value: &T) -> String {
+    format!("{:?}", value)
+}
+
+/// Positions as bits, so that NaN compares equal to itself.
+fn bits(groups: &Groups) -> Vec<Vec<u32>> {
+    groups
+        .iter()
+        .map(|group| group.position.iter().map(|num| num.to_bits()).collect())
+        .collect()
+}
@@ -0,0 +1,291 @@
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Seek};
+
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+use crate::mtl::{self, Material};
+use crate::obj::{self, ObjError, Scene};
+use crate::parser::limits::{Limit, LimitExceeded, Limits};
+
+#[derive(Debug)]
+pub enum ArchiveError {
+    Zip(ZipError),
+    Io(io::Error),
+    Obj {
+        path: String,
+        error: ObjError,
+    },
+    /// An entry decompresses to more than `Limits::max_input_bytes`.
+    Limit {
+        path: String,
+        error: LimitExceeded,
+    },
+}
+
+impl fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArchiveError::Zip(error) => write!(f, "invalid zip archive: {}", error),
+            ArchiveError::Io(error) => write!(f, "{}", error),
+            ArchiveError::Obj { path, error } => write!(f, "{}: {}", path, error),
+            ArchiveError::Limit { path, error } => write!(f, "{}: {}", path, error),
+        }
+    }
+}
+
+impl Error for ArchiveError {}
+
+impl From<ZipError> for ArchiveError {
+    fn from(error: ZipError) -> Self {
+        ArchiveError::Zip(error)
+    }
+}
+
+impl From<io::Error> for ArchiveError {
+    fn from(error: io::Error) -> Self {
+        ArchiveError::Io(error)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReferenceKind {
+    MaterialLibrary,
+    Texture,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BrokenReason {
+    /// No entry matches, not even ignoring case.
+    Missing,
+    /// The entry exists but is not a valid MTL file.
+    Invalid(String),
+}
+
+/// A `mtllib` or texture map that could not be loaded. `from` is the entry that
+/// references it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BrokenReference {
+    pub kind: ReferenceKind,
+    pub reference: String,
+    pub from: String,
+    pub reason: BrokenReason,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaterialLibrary {
+    /// As written after `mtllib`.
+    pub reference: String,
+    /// The archive entry it resolved to.
+    pub path: String,
+    pub materials: Vec<Material>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Texture {
+    pub material: String,
+    pub reference: String,
+    pub path: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArchiveObj {
+    pub path: String,
+    pub scene: Scene,
+    pub material_libraries: Vec<MaterialLibrary>,
+    pub textures: Vec<Texture>,
+    pub broken_references: Vec<BrokenReference>,
+}
+
+/// OBJ assets packed in a zip file, with `mtllib` and texture paths resolved
+/// against the archive's entries.
+pub struct Archive<R> {
+    zip: ZipArchive<R>,
+    entries: Vec<String>,
+    limits: Limits,
+}
+
+impl<R: Read + Seek> Archive<R> {
+    pub fn new(reader: R) -> Result<Self, ArchiveError> {
+        Self::with_limits(reader, Limits::default())
+    }
+
+    /// An archive whose entries are read up to `Limits::max_input_bytes` each. OBJ
+    /// entries are parsed by `obj::parse`, which the other limits do not apply to.
+    pub fn with_limits(reader: R, limits: Limits) -> Result<Self, ArchiveError> {
+        let zip = ZipArchive::new(reader)?;
+
+        let mut entries: Vec<String> = zip
+            .file_names()
+            .filter(|name| !name.ends_with('/'))
+            .map(str::to_string)
+            .collect();
+        entries.sort();
+
+        Ok(Archive {
+            zip,
+            entries,
+            limits,
+        })
+    }
+
+    /// File entries, sorted by name.
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    pub fn obj_entries(&self) -> impl Iterator<Item = &str> {
+        self.entries
+            .iter()
+            .filter(|name| name.to_ascii_lowercase().ends_with(".obj"))
+            .map(String::as_str)
+    }
+
+    /// Resolves `reference` relative to the directory of the entry `from`. Backslashes
+    /// are separators, and if no entry matches exactly, one matching ignoring ASCII
+    /// case is used.
+    pub fn resolve(&self, from: &str, reference: &str) -> Option<&str> {
+        let directory = match from.rfind('/') {
+            Some(end) => &from[..end],
+            None => "",
+        };
+
+        let path = normalize(&format!("{}/{}", directory, reference.replace('\\', "/")))?;
+
+        self.entries
+            .iter()
+            .find(|entry| **entry == path)
+            .or_else(|| {
+                self.entries
+                    .iter()
+                    .find(|entry| entry.eq_ignore_ascii_case(&path))
+            })
+            .map(String::as_str)
+    }
+
+    /// The decompressed entry `path`, failing once it grows past the input limit.
+    pub fn read(&mut self, path: &str) -> Result<Vec<u8>, ArchiveError> {
+        let max = self.limits.max_input_bytes;
+
+        // The size in the entry's header is not checked until the entry is read, so
+        // it is not used to reserve memory
+        let entry = self.zip.by_name(path)?;
+        let mut bytes = vec![];
+        entry
+            .take(u64::try_from(max).unwrap_or(u64::MAX).saturating_add(1))
+            .read_to_end(&mut bytes)?;
+
+        self.limits
+            .check(Limit::InputBytes, bytes.len())
+            .map_err(|error| ArchiveError::Limit {
+                path: path.to_string(),
+                error,
+            })?;
+
+        Ok(bytes)
+    }
+
+    /// Parses every OBJ entry in the archive.
+    pub fn load_all(&mut self) -> Result<Vec<ArchiveObj>, ArchiveError> {
+        let paths: Vec<String> = self.obj_entries().map(str::to_string).collect();
+
+        paths.iter().map(|path| self.load_obj(path)).collect()
+    }
+
+    /// Parses the OBJ entry `path` with `obj::parse` and loads the material libraries
+    /// it references. Texture maps are resolved relative to their MTL file first, then
+    /// relative to the OBJ file. References that cannot be loaded are collected in
+    /// `ArchiveObj::broken_references` rather than failing the load.
+    pub fn load_obj(&mut self, path: &str) -> Result<ArchiveObj, ArchiveError> {
+        let obj_file = self.read(path)?;
+        let scene =
+            obj::parse(&String::from_utf8_lossy(&obj_file)).map_err(|error| ArchiveError::Obj {
+                path: path.to_string(),
+                error,
+            })?;
+
+        let mut asset = ArchiveObj {
+            path: path.to_string(),
+            scene,
+            material_libraries: vec![],
+            textures: vec![],
+            broken_references: vec![],
+        };
+
+        for reference in asset.scene.material_libraries.clone() {
+            let broken = |reason| BrokenReference {
+                kind: ReferenceKind::MaterialLibrary,
+                reference: reference.clone(),
+                from: path.to_string(),
+                reason,
+            };
+
+            let library_path = match self.resolve(path, &reference) {
+                Some(library_path) => library_path.to_string(),
+                None => {
+                    asset.broken_references.push(broken(BrokenReason::Missing));
+                    continue;
+                }
+            };
+
+            let mtl_file = self.read(&library_path)?;
+            let materials = match mtl::parse(&String::from_utf8_lossy(&mtl_file)) {
+                Ok(materials) => materials,
+                Err(error) => {
+                    let reason = BrokenReason::Invalid(error.to_string());
+                    asset.broken_references.push(broken(reason));
+                    continue;
+                }
+            };
+
+            for material in &materials {
+                for map in &material.maps {
+                    let resolved = self
+                        .resolve(&library_path, &map.path)
+                        .or_else(|| self.resolve(path, &map.path));
+
+                    match resolved {
+                        Some(texture_path) => asset.textures.push(Texture {
+                            material: material.name.clone(),
+                            reference: map.path.clone(),
+                            path: texture_path.to_string(),
+                        }),
+                        None => asset.broken_references.push(BrokenReference {
+                            kind: ReferenceKind::Texture,
+                            reference: map.path.clone(),
+                            from: library_path.clone(),
+                            reason: BrokenReason::Missing,
+                        }),
+                    }
+                }
+            }
+
+            asset.material_libraries.push(MaterialLibrary {
+                reference,
+                path: library_path,
+                materials,
+            });
+        }
+
+        Ok(asset)
+    }
+}
+
+/// Resolves `.` and `..` in a `/` separated path, `None` if it leaves the archive.
+fn normalize(path: &str) -> Option<String> {
+    let mut components = vec![];
+
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop()?;
+            }
+            component => components.push(component),
+        }
+    }
+
+    Some(components.join("/"))
+}
@@ -0,0 +1,205 @@
+//! Loading for async services, behind the `async` feature. Files are read with
+//! `tokio::fs`, parsing runs on tokio's blocking pool, and the material libraries
+//! are read and parsed concurrently.
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use tokio::task::{JoinError, JoinSet};
+
+use crate::mtl::{self, Material, MtlError};
+use crate::obj::ObjError;
+use crate::parser::compression::Compression;
+use crate::parser::{fused, Groups};
+
+#[derive(Debug)]
+pub enum AsyncLoadError {
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    Obj {
+        path: PathBuf,
+        error: ObjError,
+    },
+    Mtl {
+        path: PathBuf,
+        error: MtlError,
+    },
+    /// A blocking task panicked.
+    Join(JoinError),
+}
+
+impl fmt::Display for AsyncLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AsyncLoadError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            AsyncLoadError::Obj { path, error } => write!(f, "{}: {}", path.display(), error),
+            AsyncLoadError::Mtl { path, error } => write!(f, "{}: {}", path.display(), error),
+            AsyncLoadError::Join(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for AsyncLoadError {}
+
+impl From<JoinError> for AsyncLoadError {
+    fn from(error: JoinError) -> Self {
+        AsyncLoadError::Join(error)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaterialLibrary {
+    /// As written after `mtllib`.
+    pub reference: String,
+    /// Relative to the directory of the OBJ file.
+    pub path: PathBuf,
+    pub materials: Vec<Material>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoadedObj {
+    pub groups: Groups,
+    /// In the order of the `mtllib` statements.
+    pub material_libraries: Vec<MaterialLibrary>,
+}
+
+/// Sets the flag when the load is dropped, so blocking work that has not started
+/// yet is skipped.
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Loads the OBJ file at `path`, which may be compressed like for
+/// `parser::parse_obj_file`, with `parser::fused`, and the material libraries it
+/// references. A missing or malformed material library fails the load.
+///
+/// Dropping the future cancels the load: pending reads are dropped and material
+/// libraries still being parsed are aborted. Tokio cannot interrupt blocking
+/// tasks, so an OBJ parse that has already started runs to completion in the
+/// background, but its result is discarded.
+///
+/// Nothing limits the size of the files or of the parse, so this is not meant
+/// for untrusted input.
+pub async fn load_obj_async<P: AsRef<Path>>(path: P) -> Result<LoadedObj, AsyncLoadError> {
+    let path = path.as_ref().to_path_buf();
+    let cancelled = CancelOnDrop(Arc::new(AtomicBool::new(false)));
+
+    let bytes = tokio::fs::read(&path)
+        .await
+        .map_err(|error| io_error(&path, error))?;
+
+    let flag = Arc::clone(&cancelled.0);
+    let obj_path = path.clone();
+    let parsed = tokio::task::spawn_blocking(move || -> Result<_, AsyncLoadError> {
+        if flag.load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+
+        let obj_file = decode(bytes).map_err(|error| io_error(&obj_path, error))?;
+        let references = material_library_references(&obj_file);
+
+        if flag.load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+
+        let groups = fused::parse_obj_fused(obj_file).map_err(|error| AsyncLoadError::Obj {
+            path: obj_path.clone(),
+            error,
+        })?;
+
+        Ok(Some((groups, references)))
+    });
+
+    let (groups, references) = match parsed.await?? {
+        Some(parsed) => parsed,
+        None => unreachable!("the parse is only skipped once the load has been dropped"),
+    };
+
+    let directory = path.parent().unwrap_or_else(|| Path::new("")).to_path_buf();
+    let mut libraries = JoinSet::new();
+
+    for (index, reference) in references.into_iter().enumerate() {
+        let path = directory.join(&reference);
+        libraries.spawn(async move {
+            let mtl_file = tokio::fs::read_to_string(&path)
+                .await
+                .map_err(|error| io_error(&path, error))?;
+
+            let materials = tokio::task::spawn_blocking(move || mtl::parse(&mtl_file)).await?;
+
+            match materials {
+                Ok(materials) => Ok((
+                    index,
+                    MaterialLibrary {
+                        reference,
+                        path,
+                        materials,
+                    },
+                )),
+                Err(error) => Err(AsyncLoadError::Mtl { path, error }),
+            }
+        });
+    }
+
+    let mut material_libraries = Vec::with_capacity(libraries.len());
+
+    // Returning early drops the set, which aborts the other libraries
+    while let Some(library) = libraries.join_next().await {
+        material_libraries.push(library??);
+    }
+
+    material_libraries.sort_by_key(|(index, _)| *index);
+
+    Ok(LoadedObj {
+        groups,
+        material_libraries: material_libraries
+            .into_iter()
+            .map(|(_, library)| library)
+            .collect(),
+    })
+}
+
+fn io_error(path: &Path, error: io::Error) -> AsyncLoadError {
+    AsyncLoadError::Io {
+        path: path.to_path_buf(),
+        error,
+    }
+}
+
+fn decode(bytes: Vec<u8>) -> io::Result<String> {
+    let bytes = match Compression::detect(&bytes) {
+        Compression::None => bytes,
+        compression => {
+            let mut decoded = vec![];
+            compression.decoder(&bytes[..])?.read_to_end(&mut decoded)?;
+            decoded
+        }
+    };
+
+    String::from_utf8(bytes).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+fn material_library_references(obj_file: &str) -> Vec<String> {
+    obj_file
+        .lines()
+        .filter(|line| line.starts_with("mtllib"))
+        .flat_map(|line| {
+            let mut parts = line.split_whitespace();
+
+            match parts.next() {
+                Some("mtllib") => parts.map(String::from).collect(),
+                _ => vec![],
+            }
+        })
+        .collect()
+}
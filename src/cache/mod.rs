@@ -0,0 +1,335 @@
+use std::borrow::Cow;
+use std::convert::{TryFrom, TryInto};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::parser::limits::{Limit, Limits};
+use crate::parser::{self, Groups, ParseOptions, VertexData};
+
+pub const MAGIC: [u8; 8] = *b"ROPCACHE";
+pub const VERSION: u32 = 1;
+
+const HEADER_LEN: usize = 64;
+const NO_MTIME: u32 = u32::MAX;
+
+/// What the cache was built from. A cache is fresh for a source with the same size
+/// and either the same modification time or, if only that changed, the same hash.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SourceInfo {
+    pub size: u64,
+    /// Time since the Unix epoch, `None` if the platform does not report it.
+    pub modified: Option<Duration>,
+    pub hash: u64,
+}
+
+impl SourceInfo {
+    pub fn new(source: &[u8], modified: Option<SystemTime>) -> Self {
+        SourceInfo {
+            size: source.len() as u64,
+            modified: modified.and_then(|time| time.duration_since(UNIX_EPOCH).ok()),
+            hash: fnv1a(source),
+        }
+    }
+
+    /// Whether a cache built from `self` can stand in for the file at `path`. The
+    /// file is only read when its size matches but its modification time does not.
+    pub fn is_fresh_for<P: AsRef<Path>>(&self, path: P) -> io::Result<bool> {
+        let metadata = fs::metadata(&path)?;
+
+        if metadata.len() != self.size {
+            return Ok(false);
+        }
+
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok());
+
+        if modified.is_some() && modified == self.modified {
+            return Ok(true);
+        }
+
+        Ok(fnv1a(&fs::read(path)?) == self.hash)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CacheError {
+    InvalidMagic,
+    UnsupportedVersion(u32),
+    Truncated,
+    ChecksumMismatch,
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CacheError::InvalidMagic => write!(f, "not a rust_obj_parser cache"),
+            CacheError::UnsupportedVersion(version) => {
+                write!(f, "unsupported cache version {}", version)
+            }
+            CacheError::Truncated => write!(f, "cache is truncated"),
+            CacheError::ChecksumMismatch => write!(f, "cache checksum does not match"),
+        }
+    }
+}
+
+impl Error for CacheError {}
+
+/// A group borrowed from the cache bytes where possible. The arrays are copied
+/// only on big endian targets or when the bytes are not 4 byte aligned.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CachedGroup<'a> {
+    pub position: Cow<'a, [f32]>,
+    pub texcoord: Cow<'a, [f32]>,
+    pub normal: Cow<'a, [f32]>,
+}
+
+impl CachedGroup<'_> {
+    pub fn to_vertex_data(&self) -> VertexData {
+        VertexData {
+            position: self.position.to_vec(),
+            texcoord: self.texcoord.to_vec(),
+            normal: self.normal.to_vec(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cache<'a> {
+    pub source: SourceInfo,
+    pub groups: Vec<CachedGroup<'a>>,
+}
+
+impl Cache<'_> {
+    pub fn to_groups(&self) -> Groups {
+        self.groups
+            .iter()
+            .map(CachedGroup::to_vertex_data)
+            .collect()
+    }
+}
+
+/// Writes `groups` as a cache.
+///
+/// The layout is a 64 byte header (magic, version, source size, modification time
+/// and hash, group count, payload checksum), then the position, texcoord and normal
+/// lengths of every group as `u64`, then the arrays themselves. Everything is little
+/// endian and every array starts 4 byte aligned.
+pub fn write<W: Write>(
+    groups: &[VertexData],
+    source: &SourceInfo,
+    mut writer: W,
+) -> io::Result<()> {
+    let mut payload = Vec::with_capacity(
+        groups.len() * 24
+            + groups
+                .iter()
+                .map(|group| 4 * (group.position.len() + group.texcoord.len() + group.normal.len()))
+                .sum::<usize>(),
+    );
+
+    for group in groups {
+        for array in [&group.position, &group.texcoord, &group.normal] {
+            payload.extend_from_slice(&(array.len() as u64).to_le_bytes());
+        }
+    }
+
+    for group in groups {
+        for array in [&group.position, &group.texcoord, &group.normal] {
+            for num in array.iter() {
+                payload.extend_from_slice(&num.to_le_bytes());
+            }
+        }
+    }
+
+    let (seconds, nanos) = match source.modified {
+        Some(modified) => (modified.as_secs(), modified.subsec_nanos()),
+        None => (0, NO_MTIME),
+    };
+
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    header.extend_from_slice(&MAGIC);
+    header.extend_from_slice(&VERSION.to_le_bytes());
+    header.extend_from_slice(&nanos.to_le_bytes());
+    header.extend_from_slice(&source.size.to_le_bytes());
+    header.extend_from_slice(&seconds.to_le_bytes());
+    header.extend_from_slice(&source.hash.to_le_bytes());
+    header.extend_from_slice(&(groups.len() as u64).to_le_bytes());
+    header.extend_from_slice(&fnv1a(&payload).to_le_bytes());
+    header.resize(HEADER_LEN, 0);
+
+    writer.write_all(&header)?;
+    writer.write_all(&payload)?;
+    writer.flush()
+}
+
+/// Loads a cache written by `write`, borrowing the float arrays from `bytes`.
+pub fn load(bytes: &[u8]) -> Result<Cache<'_>, CacheError> {
+    if bytes.len() < HEADER_LEN {
+        return Err(match bytes.get(..MAGIC.len()) {
+            Some(magic) if magic != MAGIC => CacheError::InvalidMagic,
+            _ => CacheError::Truncated,
+        });
+    }
+
+    let (header, payload) = bytes.split_at(HEADER_LEN);
+
+    if header[..8] != MAGIC {
+        return Err(CacheError::InvalidMagic);
+    }
+
+    let u32_at = |offset: usize| u32::from_le_bytes(header[offset..offset + 4].try_into().unwrap());
+    let u64_at = |offset: usize| u64::from_le_bytes(header[offset..offset + 8].try_into().unwrap());
+
+    let version = u32_at(8);
+
+    if version != VERSION {
+        return Err(CacheError::UnsupportedVersion(version));
+    }
+
+    if fnv1a(payload) != u64_at(48) {
+        return Err(CacheError::ChecksumMismatch);
+    }
+
+    let nanos = u32_at(12);
+    let source = SourceInfo {
+        size: u64_at(16),
+        // NO_MTIME and any other invalid nanosecond count mean there is no time.
+        modified: if nanos < 1_000_000_000 {
+            Some(Duration::new(u64_at(24), nanos))
+        } else {
+            None
+        },
+        hash: u64_at(32),
+    };
+
+    let group_count = usize::try_from(u64_at(40)).map_err(|_| CacheError::Truncated)?;
+    let table_len = group_count.checked_mul(24).ok_or(CacheError::Truncated)?;
+
+    if payload.len() < table_len {
+        return Err(CacheError::Truncated);
+    }
+
+    let (table, mut data) = payload.split_at(table_len);
+    let mut lengths = table
+        .chunks_exact(8)
+        .map(|len| u64::from_le_bytes(len.try_into().unwrap()));
+
+    let mut next_array = || {
+        let len = lengths
+            .next()
+            .and_then(|len| usize::try_from(len).ok())
+            .and_then(|len| len.checked_mul(4))
+            .filter(|&len| len <= data.len())
+            .ok_or(CacheError::Truncated)?;
+
+        let (array, rest) = data.split_at(len);
+        data = rest;
+
+        Ok(floats(array))
+    };
+
+    let groups = (0..group_count)
+        .map(|_| {
+            Ok(CachedGroup {
+                position: next_array()?,
+                texcoord: next_array()?,
+                normal: next_array()?,
+            })
+        })
+        .collect::<Result<_, CacheError>>()?;
+
+    Ok(Cache { source, groups })
+}
+
+fn floats(bytes: &[u8]) -> Cow<'_, [f32]> {
+    if cfg!(target_endian = "little") {
+        // Every bit pattern is a valid f32, so reinterpreting aligned bytes is sound.
+        let (prefix, floats, suffix) = unsafe { bytes.align_to::<f32>() };
+
+        if prefix.is_empty() && suffix.is_empty() {
+            return Cow::Borrowed(floats);
+        }
+    }
+
+    Cow::Owned(
+        bytes
+            .chunks_exact(4)
+            .map(|num| f32::from_le_bytes(num.try_into().unwrap()))
+            .collect(),
+    )
+}
+
+/// Loads the groups of `obj_path` from the cache at `cache_path` if it is fresh,
+/// otherwise parses the OBJ and rewrites the cache. A missing, corrupt or outdated
+/// cache is not an error, failing to write one is.
+pub fn load_or_parse<P, Q>(obj_path: P, cache_path: Q) -> Result<Groups, Box<dyn Error>>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    load_or_parse_limited(obj_path, cache_path, &Limits::default())
+}
+
+/// `load_or_parse`, parsing with `parser::parse_obj_bytes_with_options` under
+/// `limits`. A fresh cache is loaded as is, so it should not be shared between
+/// callers with different limits.
+pub fn load_or_parse_limited<P, Q>(
+    obj_path: P,
+    cache_path: Q,
+    limits: &Limits,
+) -> Result<Groups, Box<dyn Error>>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let obj_path = obj_path.as_ref();
+    let cache_path = cache_path.as_ref();
+
+    if let Ok(bytes) = fs::read(cache_path) {
+        if let Ok(cache) = load(&bytes) {
+            if cache.source.is_fresh_for(obj_path)? {
+                return Ok(cache.to_groups());
+            }
+        }
+    }
+
+    let metadata = fs::metadata(obj_path)?;
+    // Compressed files only grow, so the size on disk can be rejected up front
+    limits.check(
+        Limit::InputBytes,
+        usize::try_from(metadata.len()).unwrap_or(usize::MAX),
+    )?;
+
+    let obj_file = fs::read(obj_path)?;
+    let source = SourceInfo::new(&obj_file, metadata.modified().ok());
+
+    let options = ParseOptions {
+        limits: *limits,
+        ..ParseOptions::default()
+    };
+    let groups = parser::parse_obj_bytes_with_options(&obj_file, &options)?.groups;
+
+    // Write next to the cache and rename, so readers never see a partial file.
+    let partial = cache_path.with_extension("partial");
+    write(
+        &groups,
+        &source,
+        io::BufWriter::new(fs::File::create(&partial)?),
+    )?;
+    fs::rename(&partial, cache_path)?;
+
+    Ok(groups)
+}
+
+/// 64 bit FNV-1a, used for both the source hash and the payload checksum.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
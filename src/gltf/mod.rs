@@ -0,0 +1,490 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::math;
+use crate::mtl::{Color, MapKind, Material};
+use crate::obj::{Face, Scene};
+use crate::parser::VertexData;
+
+const ARRAY_BUFFER: u32 = 34962;
+const FLOAT: u32 = 5126;
+
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const GLB_JSON_CHUNK: u32 = 0x4E4F_534A;
+const GLB_BIN_CHUNK: u32 = 0x004E_4942;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GltfError {
+    /// glTF accessors cannot hold NaN or infinity. `attribute` is `POSITION` or
+    /// `TEXCOORD_0`, non-finite normals are replaced instead.
+    NonFinite {
+        mesh: String,
+        attribute: &'static str,
+    },
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GltfError::NonFinite { mesh, attribute } => {
+                write!(f, "mesh `{}` has a non-finite {} value", mesh, attribute)
+            }
+        }
+    }
+}
+
+impl Error for GltfError {}
+
+/// A glTF 2.0 document with a single binary buffer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gltf {
+    json: Value,
+    bin: Vec<u8>,
+}
+
+impl Gltf {
+    /// The JSON document with the buffer stored at `bin_uri`.
+    pub fn to_json(&self, bin_uri: &str) -> String {
+        let mut json = self.json.clone();
+
+        if let Some(buffer) = json.pointer_mut("/buffers/0") {
+            buffer["uri"] = json!(encode_uri(bin_uri));
+        }
+
+        serde_json::to_string_pretty(&json).expect("the document is valid JSON")
+    }
+
+    pub fn bin(&self) -> &[u8] {
+        &self.bin
+    }
+
+    /// Writes `path` and a `.bin` file with the same stem next to it.
+    pub fn write_gltf<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let bin_path = path.with_extension("bin");
+        let bin_uri = bin_path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid file name"))?;
+
+        fs::write(path, self.to_json(bin_uri))?;
+        fs::write(&bin_path, &self.bin)
+    }
+
+    /// The binary GLB container with the JSON and the buffer embedded.
+    pub fn to_glb(&self) -> Vec<u8> {
+        let mut json = serde_json::to_vec(&self.json).expect("the document is valid JSON");
+        pad(&mut json, b' ');
+
+        let mut bin = self.bin.clone();
+        pad(&mut bin, 0);
+
+        let length = 12 + 8 + json.len() + 8 + bin.len();
+        let mut glb = Vec::with_capacity(length);
+
+        for word in [GLB_MAGIC, 2, length as u32] {
+            glb.extend_from_slice(&word.to_le_bytes());
+        }
+
+        for (chunk_type, chunk) in [(GLB_JSON_CHUNK, &json), (GLB_BIN_CHUNK, &bin)] {
+            glb.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+            glb.extend_from_slice(&chunk_type.to_le_bytes());
+            glb.extend_from_slice(chunk);
+        }
+
+        glb
+    }
+
+    pub fn write_glb<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_glb())
+    }
+}
+
+/// Percent-encodes everything but unreserved characters and `/`.
+fn encode_uri(path: &str) -> String {
+    let mut uri = String::with_capacity(path.len());
+
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    uri
+}
+
+fn pad(data: &mut Vec<u8>, byte: u8) {
+    let padding = (4 - data.len() % 4) % 4;
+    data.resize(data.len() + padding, byte);
+}
+
+type Primitive<'a> = (Option<&'a str>, Vec<&'a Face>);
+
+/// Exports `scene` with one mesh per object and group combination and one
+/// primitive per material used in it. Materials are looked up by name in
+/// `materials` and converted with `pbr_from_mtl`. Fails if a position or texcoord
+/// in the scene is NaN or infinite.
+pub fn export(scene: &Scene, materials: &[Material]) -> Result<Gltf, GltfError> {
+    let mut meshes: Vec<(String, Vec<Primitive<'_>>)> = vec![];
+
+    for face in &scene.faces {
+        let state = &scene.states[face.state];
+        let name = match (&state.object, state.groups.is_empty()) {
+            (Some(object), true) => object.clone(),
+            (Some(object), false) => format!("{}/{}", object, state.groups.join(" ")),
+            (None, _) => state.groups.join(" "),
+        };
+
+        let primitives = match meshes.iter_mut().find(|(mesh, _)| *mesh == name) {
+            Some((_, primitives)) => primitives,
+            None => {
+                meshes.push((name, vec![]));
+                &mut meshes.last_mut().unwrap().1
+            }
+        };
+
+        let material = state.material.as_deref();
+
+        match primitives.iter_mut().find(|(used, _)| *used == material) {
+            Some((_, faces)) => faces.push(face),
+            None => primitives.push((material, vec![face])),
+        }
+    }
+
+    let mut builder = Builder::default();
+
+    for (name, primitives) in meshes {
+        let primitives = primitives
+            .into_iter()
+            .map(|(material, faces)| {
+                let material = material.map(|name| {
+                    materials
+                        .iter()
+                        .find(|material| material.name == name)
+                        .cloned()
+                        .unwrap_or_else(|| Material {
+                            name: name.to_string(),
+                            ..Material::default()
+                        })
+                });
+
+                (scene.triangle_soup(faces.iter().copied()), material)
+            })
+            .collect();
+
+        builder.add_mesh(&name, primitives)?;
+    }
+
+    Ok(builder.finish())
+}
+
+/// Exports the triangle soups of `parser::parse_obj_threaded` as one mesh each,
+/// without materials. Fails like `export` on non-finite values.
+pub fn export_groups(groups: &[VertexData]) -> Result<Gltf, GltfError> {
+    let mut builder = Builder::default();
+
+    for (i, group) in groups.iter().enumerate() {
+        builder.add_mesh(&format!("group{}", i), vec![(group.clone(), None)])?;
+    }
+
+    Ok(builder.finish())
+}
+
+/// Maps an MTL material to `pbrMetallicRoughness`:
+///
+/// - `baseColorFactor` is `Kd` with `d` as alpha, `map_Kd` is the base color texture
+/// - `metallicFactor` and `roughnessFactor` are `Pm` and `Pr` when present. Otherwise
+///   the material is dielectric and the roughness is `sqrt(2 / (Ns + 2))`, the usual
+///   Blinn-Phong exponent to GGX roughness approximation, or 1 without `Ns`
+/// - `emissiveFactor` is `Ke` clamped to `[0, 1]`
+/// - `norm` becomes the normal texture. `bump` maps are height maps and are dropped
+/// - `alphaMode` is `BLEND` when `d` is below 1
+///
+/// Only RGB colours are used, `xyz` and `spectral` colours fall back to the defaults.
+pub fn pbr_from_mtl(material: &Material) -> PbrMaterial {
+    let rgb = |color: &Option<Color>| match color {
+        Some(Color::Rgb(rgb)) => Some(rgb.map(|c| c.clamp(0.0, 1.0))),
+        _ => None,
+    };
+
+    let alpha = material
+        .dissolve
+        .map_or(1.0, |dissolve| dissolve.factor.clamp(0.0, 1.0));
+    let [r, g, b] = rgb(&material.diffuse).unwrap_or([1.0; 3]);
+
+    let roughness = match (material.roughness, material.shininess) {
+        (Some(roughness), _) => roughness,
+        (None, Some(shininess)) => (2.0 / (shininess.max(0.0) + 2.0)).sqrt(),
+        (None, None) => 1.0,
+    };
+
+    let texture = |kind| {
+        material
+            .maps
+            .iter()
+            .find(|map| map.kind == kind)
+            .map(|map| map.path.replace('\\', "/"))
+    };
+
+    PbrMaterial {
+        name: material.name.clone(),
+        base_color: [r, g, b, alpha],
+        metallic: material.metallic.unwrap_or(0.0).clamp(0.0, 1.0),
+        roughness: roughness.clamp(0.0, 1.0),
+        emissive: rgb(&material.emissive).unwrap_or([0.0; 3]),
+        base_color_texture: texture(MapKind::Diffuse),
+        normal_texture: texture(MapKind::Normal),
+        blend: alpha < 1.0,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PbrMaterial {
+    pub name: String,
+    pub base_color: [f32; 4],
+    pub metallic: f32,
+    pub roughness: f32,
+    pub emissive: [f32; 3],
+    pub base_color_texture: Option<String>,
+    pub normal_texture: Option<String>,
+    pub blend: bool,
+}
+
+#[derive(Default)]
+struct Builder {
+    bin: Vec<u8>,
+    buffer_views: Vec<Value>,
+    accessors: Vec<Value>,
+    meshes: Vec<Value>,
+    materials: Vec<(String, Value)>,
+    textures: Vec<String>,
+}
+
+impl Builder {
+    fn add_mesh(
+        &mut self,
+        name: &str,
+        primitives: Vec<(VertexData, Option<Material>)>,
+    ) -> Result<(), GltfError> {
+        let primitives: Vec<_> = primitives
+            .into_iter()
+            .filter(|(soup, _)| !soup.position.is_empty())
+            .map(|(soup, material)| self.add_primitive(name, soup, material))
+            .collect::<Result<_, _>>()?;
+
+        if !primitives.is_empty() {
+            self.meshes.push(json!({
+                "name": name,
+                "primitives": primitives,
+            }));
+        }
+
+        Ok(())
+    }
+
+    fn add_primitive(
+        &mut self,
+        mesh: &str,
+        mut soup: VertexData,
+        material: Option<Material>,
+    ) -> Result<Value, GltfError> {
+        let count = soup.position.len() / 3;
+        let has_texcoord = soup.texcoord.len() == count * 2;
+
+        for (attribute, data, used) in [
+            ("POSITION", &soup.position, true),
+            ("TEXCOORD_0", &soup.texcoord, has_texcoord),
+        ] {
+            if used && !data.iter().all(|num| num.is_finite()) {
+                return Err(GltfError::NonFinite {
+                    mesh: mesh.to_string(),
+                    attribute,
+                });
+            }
+        }
+
+        let mut attributes = json!({
+            "POSITION": self.add_accessor(&soup.position, 3, true),
+        });
+
+        if soup.normal.len() == soup.position.len() {
+            fix_normals(&mut soup);
+            attributes["NORMAL"] = json!(self.add_accessor(&soup.normal, 3, false));
+        }
+
+        if has_texcoord {
+            // OBJ has the texture origin at the bottom left, glTF at the top left
+            for v in soup.texcoord.iter_mut().skip(1).step_by(2) {
+                *v = 1.0 - *v;
+            }
+            attributes["TEXCOORD_0"] = json!(self.add_accessor(&soup.texcoord, 2, false));
+        }
+
+        let mut primitive = json!({ "attributes": attributes, "mode": 4 });
+
+        if let Some(material) = material {
+            primitive["material"] = json!(self.add_material(&material));
+        }
+
+        Ok(primitive)
+    }
+
+    fn add_accessor(&mut self, data: &[f32], components: usize, bounds: bool) -> usize {
+        let offset = self.bin.len();
+
+        for num in data {
+            self.bin.extend_from_slice(&num.to_le_bytes());
+        }
+
+        self.buffer_views.push(json!({
+            "buffer": 0,
+            "byteOffset": offset,
+            "byteLength": data.len() * 4,
+            "target": ARRAY_BUFFER,
+        }));
+
+        let mut accessor = json!({
+            "bufferView": self.buffer_views.len() - 1,
+            "componentType": FLOAT,
+            "count": data.len() / components,
+            "type": if components == 3 { "VEC3" } else { "VEC2" },
+        });
+
+        if bounds {
+            let mut min = vec![f32::INFINITY; components];
+            let mut max = vec![f32::NEG_INFINITY; components];
+
+            for vertex in data.chunks(components) {
+                for (i, &num) in vertex.iter().enumerate() {
+                    min[i] = min[i].min(num);
+                    max[i] = max[i].max(num);
+                }
+            }
+
+            accessor["min"] = json!(min);
+            accessor["max"] = json!(max);
+        }
+
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    fn add_material(&mut self, material: &Material) -> usize {
+        if let Some(index) = self
+            .materials
+            .iter()
+            .position(|(name, _)| *name == material.name)
+        {
+            return index;
+        }
+
+        let pbr = pbr_from_mtl(material);
+
+        let mut metallic_roughness = json!({
+            "baseColorFactor": pbr.base_color,
+            "metallicFactor": pbr.metallic,
+            "roughnessFactor": pbr.roughness,
+        });
+
+        if let Some(texture) = &pbr.base_color_texture {
+            metallic_roughness["baseColorTexture"] = json!({ "index": self.add_texture(texture) });
+        }
+
+        let mut gltf_material = json!({
+            "name": pbr.name,
+            "pbrMetallicRoughness": metallic_roughness,
+            "emissiveFactor": pbr.emissive,
+        });
+
+        if let Some(texture) = &pbr.normal_texture {
+            gltf_material["normalTexture"] = json!({ "index": self.add_texture(texture) });
+        }
+
+        if pbr.blend {
+            gltf_material["alphaMode"] = json!("BLEND");
+        }
+
+        self.materials.push((material.name.clone(), gltf_material));
+        self.materials.len() - 1
+    }
+
+    fn add_texture(&mut self, path: &str) -> usize {
+        match self.textures.iter().position(|texture| texture == path) {
+            Some(index) => index,
+            None => {
+                self.textures.push(path.to_string());
+                self.textures.len() - 1
+            }
+        }
+    }
+
+    fn finish(self) -> Gltf {
+        let mut json = json!({
+            "asset": {
+                "version": "2.0",
+                "generator": concat!("rust_obj_parser ", env!("CARGO_PKG_VERSION")),
+            },
+        });
+
+        if !self.meshes.is_empty() {
+            let nodes: Vec<_> = (0..self.meshes.len())
+                .map(|mesh| json!({ "mesh": mesh }))
+                .collect();
+
+            json["scene"] = json!(0);
+            json["scenes"] = json!([{ "nodes": (0..nodes.len()).collect::<Vec<_>>() }]);
+            json["nodes"] = json!(nodes);
+            json["meshes"] = json!(self.meshes);
+            json["accessors"] = json!(self.accessors);
+            json["bufferViews"] = json!(self.buffer_views);
+            json["buffers"] = json!([{ "byteLength": self.bin.len() }]);
+        }
+
+        if !self.materials.is_empty() {
+            let materials: Vec<_> = self
+                .materials
+                .into_iter()
+                .map(|(_, material)| material)
+                .collect();
+            json["materials"] = json!(materials);
+        }
+
+        if !self.textures.is_empty() {
+            json["samplers"] = json!([{}]);
+            json["images"] = json!(self
+                .textures
+                .iter()
+                .map(|path| json!({ "uri": encode_uri(path) }))
+                .collect::<Vec<_>>());
+            json["textures"] = json!((0..self.textures.len())
+                .map(|image| json!({ "sampler": 0, "source": image }))
+                .collect::<Vec<_>>());
+        }
+
+        Gltf {
+            json,
+            bin: self.bin,
+        }
+    }
+}
+
+/// glTF requires unit normals. Normals that cannot be normalized are replaced by
+/// the face normal, or +Z for degenerate triangles.
+fn fix_normals(soup: &mut VertexData) {
+    for (triangle, normals) in soup.position.chunks(9).zip(soup.normal.chunks_mut(9)) {
+        let face_normal = math::triangle_normal(triangle).unwrap_or([0.0, 0.0, 1.0]);
+
+        for normal in normals.chunks_mut(3) {
+            let fixed = math::normalize([normal[0], normal[1], normal[2]]).unwrap_or(face_normal);
+            normal.copy_from_slice(&fixed);
+        }
+    }
+}
@@ -6,9 +6,21 @@ extern crate glium;
 
 use std::env::Args;
 use std::error::Error;
-use std::fs;
 
+pub mod archive;
+#[cfg(feature = "async")]
+pub mod asynchronous;
+pub mod cache;
+pub mod gltf;
+mod math;
+pub mod mesh;
+pub mod mtl;
+pub mod obj;
+pub mod off;
 pub mod parser;
+pub mod ply;
+pub mod stl;
+pub mod triangulate;
 
 pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
     run_gl(config.filename)?;
@@ -37,15 +49,14 @@ fn run_gl(filename: String) -> Result<(), Box<dyn Error>> {
 
     implement_vertex!(Vertex, position);
 
-    let content = fs::read_to_string(filename)?;
-    let object = parser::parse_obj_threaded(content).unwrap();
+    let object = parser::parse_obj_file(filename)?;
 
     // let positions = object.position;
 
     // let shape = positions
     //     .chunks(3)
     //     .map(|position| Vertex {
-    //         position: [position[0] as f32, position[1] as f32, position[2] as f32],
+    //         position: [position[0], position[1], position[2]],
     //     })
     //     .collect::<Vec<_>>();
 
@@ -60,7 +71,7 @@ fn run_gl(filename: String) -> Result<(), Box<dyn Error>> {
             let shape: Vec<_> = positions
                 .chunks(3)
                 .map(|position| Vertex {
-                    position: [position[0] as f32, position[1] as f32, position[2] as f32],
+                    position: [position[0], position[1], position[2]],
                 })
                 .collect();
             glium::VertexBuffer::new(&display, &shape).unwrap()
@@ -118,7 +129,7 @@ fn run_gl(filename: String) -> Result<(), Box<dyn Error>> {
 
         // we update `t`
         t += 0.02;
-        t %= 6.28;
+        t %= std::f32::consts::TAU;
 
         let mut target = display.draw();
         target.clear_color(0.0, 0.0, 1.0, 1.0);
@@ -140,7 +151,7 @@ fn run_gl(filename: String) -> Result<(), Box<dyn Error>> {
             target
                 .draw(
                     vertex_buffer,
-                    &indices,
+                    indices,
                     &program,
                     &uniforms,
                     &Default::default(),
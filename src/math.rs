@@ -0,0 +1,29 @@
+pub(crate) fn sub(a: &[f32], b: &[f32]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub(crate) fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+pub(crate) fn normalize(v: [f32; 3]) -> Option<[f32; 3]> {
+    let length = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+
+    if length.is_finite() && length > f32::EPSILON {
+        Some(v.map(|c| c / length))
+    } else {
+        None
+    }
+}
+
+/// The unit normal of a triangle given as 9 floats, `None` if it is degenerate.
+pub(crate) fn triangle_normal(triangle: &[f32]) -> Option<[f32; 3]> {
+    normalize(cross(
+        sub(&triangle[3..6], &triangle[0..3]),
+        sub(&triangle[6..9], &triangle[0..3]),
+    ))
+}
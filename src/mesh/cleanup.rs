@@ -0,0 +1,303 @@
+//! Cleans up the indexed faces of a `Scene`: welds vertices that are close
+//! together, removes degenerate and duplicate faces and drops the `v`, `vt` and
+//! `vn` entries no face uses.
+
+use std::collections::{HashMap, HashSet};
+
+use super::{area_and_volume, longest_edge_squared};
+use crate::obj::Scene;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CleanupOptions {
+    /// Positions, texcoords and normals at most this far apart are welded, each to
+    /// the first earlier one in range. Zero only welds identical values.
+    pub epsilon: f32,
+    /// Only weld positions with the same colour and attributes, whose corners use
+    /// the same texcoords and normals. This keeps UV seams and hard edges split.
+    pub respect_attributes: bool,
+    /// Removes faces with fewer than three corners once repeated ones are dropped,
+    /// or with zero area up to rounding.
+    pub remove_degenerate_faces: bool,
+    /// Removes faces with the same positions as an earlier face, in any order.
+    pub remove_duplicate_faces: bool,
+    /// Drops entries no face uses and renumbers the others, keeping their order.
+    pub remove_unused: bool,
+}
+
+impl Default for CleanupOptions {
+    fn default() -> Self {
+        CleanupOptions {
+            epsilon: 1e-6,
+            respect_attributes: false,
+            remove_degenerate_faces: true,
+            remove_duplicate_faces: true,
+            remove_unused: true,
+        }
+    }
+}
+
+/// What `clean` changed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CleanupReport {
+    pub welded_positions: usize,
+    pub welded_texcoords: usize,
+    pub welded_normals: usize,
+    pub degenerate_faces: usize,
+    pub duplicate_faces: usize,
+    /// Entries dropped by `remove_unused`, welded ones included.
+    pub removed_positions: usize,
+    pub removed_texcoords: usize,
+    pub removed_normals: usize,
+}
+
+/// Cleans up `scene` in place. Faces keep their order and state.
+pub fn clean(scene: &mut Scene, options: &CleanupOptions) -> CleanupReport {
+    let mut report = CleanupReport::default();
+
+    let texcoords = weld(&scene.texcoord, 2, options.epsilon, |_, _| true);
+    let normals = weld(&scene.normal, 3, options.epsilon, |_, _| true);
+    report.welded_texcoords = count_welded(&texcoords);
+    report.welded_normals = count_welded(&normals);
+
+    for vertex in scene.faces.iter_mut().flat_map(|face| &mut face.vertices) {
+        vertex.texcoord = vertex.texcoord.map(|texcoord| texcoords[texcoord]);
+        vertex.normal = vertex.normal.map(|normal| normals[normal]);
+    }
+
+    let positions = if options.respect_attributes {
+        let mut corners = vec![vec![]; scene.position_count()];
+
+        for vertex in scene.faces.iter().flat_map(|face| &face.vertices) {
+            corners[vertex.position].push((vertex.texcoord, vertex.normal));
+        }
+
+        for corners in &mut corners {
+            corners.sort_unstable();
+            corners.dedup();
+        }
+
+        let scene = &*scene;
+        let same_color = |a: usize, b: usize| {
+            scene.color.is_empty() || scene.color[a * 3..a * 3 + 3] == scene.color[b * 3..b * 3 + 3]
+        };
+        let same_attributes = |a: usize, b: usize| {
+            scene
+                .attributes
+                .iter()
+                .all(|attribute| attribute.values[a] == attribute.values[b])
+        };
+
+        weld(&scene.position, 3, options.epsilon, |a, b| {
+            corners[a] == corners[b] && same_color(a, b) && same_attributes(a, b)
+        })
+    } else {
+        weld(&scene.position, 3, options.epsilon, |_, _| true)
+    };
+
+    report.welded_positions = count_welded(&positions);
+
+    for vertex in scene.faces.iter_mut().flat_map(|face| &mut face.vertices) {
+        vertex.position = positions[vertex.position];
+    }
+
+    if options.remove_degenerate_faces {
+        let position = &scene.position;
+        let point = |vertex: usize| [0, 1, 2].map(|i| f64::from(position[vertex * 3 + i]));
+
+        scene.faces.retain_mut(|face| {
+            // Welded corners follow each other, and so do the ends of the loop
+            face.vertices.dedup_by_key(|vertex| vertex.position);
+            while face.vertices.len() > 1
+                && face.vertices.first().map(|vertex| vertex.position)
+                    == face.vertices.last().map(|vertex| vertex.position)
+            {
+                face.vertices.pop();
+            }
+
+            let points: Vec<_> = face.vertices.iter().map(|v| point(v.position)).collect();
+            let finite = points.iter().flatten().all(|num| num.is_finite());
+
+            let degenerate = points.len() < 3
+                || finite
+                    && area_and_volume(&points).0 <= f64::EPSILON * longest_edge_squared(&points);
+
+            if degenerate {
+                report.degenerate_faces += 1;
+            }
+
+            !degenerate
+        });
+    }
+
+    if options.remove_duplicate_faces {
+        let mut seen = HashSet::new();
+
+        scene.faces.retain(|face| {
+            let mut key: Vec<_> = face.vertices.iter().map(|vertex| vertex.position).collect();
+            key.sort_unstable();
+
+            let first = seen.insert(key);
+
+            if !first {
+                report.duplicate_faces += 1;
+            }
+
+            first
+        });
+    }
+
+    if options.remove_unused {
+        let mut used = [
+            vec![false; scene.position_count()],
+            vec![false; scene.texcoord_count()],
+            vec![false; scene.normal_count()],
+        ];
+
+        for vertex in scene.faces.iter().flat_map(|face| &face.vertices) {
+            used[0][vertex.position] = true;
+
+            if let Some(texcoord) = vertex.texcoord {
+                used[1][texcoord] = true;
+            }
+
+            if let Some(normal) = vertex.normal {
+                used[2][normal] = true;
+            }
+        }
+
+        let [positions, texcoords, normals] = &used;
+
+        let new_positions = compact(&mut scene.position, 3, positions);
+        let new_texcoords = compact(&mut scene.texcoord, 2, texcoords);
+        let new_normals = compact(&mut scene.normal, 3, normals);
+
+        if !scene.color.is_empty() {
+            compact(&mut scene.color, 3, positions);
+        }
+
+        for attribute in &mut scene.attributes {
+            compact_values(&mut attribute.values, positions);
+        }
+
+        for vertex in scene.faces.iter_mut().flat_map(|face| &mut face.vertices) {
+            vertex.position = new_positions[vertex.position];
+            vertex.texcoord = vertex.texcoord.map(|texcoord| new_texcoords[texcoord]);
+            vertex.normal = vertex.normal.map(|normal| new_normals[normal]);
+        }
+
+        report.removed_positions = unused(positions);
+        report.removed_texcoords = unused(texcoords);
+        report.removed_normals = unused(normals);
+    }
+
+    report
+}
+
+/// Maps every value of `dims` components to the first earlier one within
+/// `epsilon` that `compatible` accepts, or to itself. Values are hashed into cells
+/// of `epsilon` size, so only the neighbouring cells need to be searched.
+/// Non-finite values, or all with a zero `epsilon`, are only welded when equal.
+fn weld<C>(values: &[f32], dims: usize, epsilon: f32, compatible: C) -> Vec<usize>
+where
+    C: Fn(usize, usize) -> bool,
+{
+    let value = |i: usize| &values[i * dims..i * dims + dims];
+
+    let mut welded = Vec::with_capacity(values.len() / dims);
+    let mut cells: HashMap<[i64; 3], Vec<usize>> = HashMap::new();
+    let mut exact: HashMap<[u32; 3], Vec<usize>> = HashMap::new();
+
+    for i in 0..values.len() / dims {
+        let point = value(i);
+
+        let (cell, near) = if epsilon > 0.0 && point.iter().all(|num| num.is_finite()) {
+            let mut cell = [0; 3];
+
+            for (axis, num) in point.iter().enumerate() {
+                cell[axis] = (num / epsilon).floor() as i64;
+            }
+
+            let near: Vec<_> = neighbours(cell, dims)
+                .filter_map(|cell| cells.get(&cell))
+                .flatten()
+                .copied()
+                .filter(|&j| distance_squared(value(j), point) <= epsilon * epsilon)
+                .collect();
+
+            (cells.entry(cell).or_default(), near)
+        } else {
+            // + 0.0 turns -0.0 into 0.0 so both weld together
+            let mut key = [0; 3];
+
+            for (axis, num) in point.iter().enumerate() {
+                key[axis] = (num + 0.0).to_bits();
+            }
+
+            let entry = exact.entry(key).or_default();
+            let near = entry.clone();
+
+            (entry, near)
+        };
+
+        match near.into_iter().filter(|&j| compatible(j, i)).min() {
+            Some(j) => welded.push(j),
+            None => {
+                cell.push(i);
+                welded.push(i);
+            }
+        }
+    }
+
+    welded
+}
+
+/// The cell and the ones around it in the first `dims` axes.
+fn neighbours(cell: [i64; 3], dims: usize) -> impl Iterator<Item = [i64; 3]> {
+    let offsets = move |axis: usize| if axis < dims { -1..=1 } else { 0..=0 };
+
+    offsets(0).flat_map(move |x| {
+        offsets(1).flat_map(move |y| {
+            offsets(2).map(move |z| {
+                [
+                    cell[0].saturating_add(x),
+                    cell[1].saturating_add(y),
+                    cell[2].saturating_add(z),
+                ]
+            })
+        })
+    })
+}
+
+fn distance_squared(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum()
+}
+
+fn count_welded(welded: &[usize]) -> usize {
+    welded.iter().enumerate().filter(|&(i, &j)| i != j).count()
+}
+
+fn unused(used: &[bool]) -> usize {
+    used.iter().filter(|&&used| !used).count()
+}
+
+/// Keeps the values of `dims` components that are `used` and returns where each
+/// one went. Dropped values map to `usize::MAX`.
+fn compact(values: &mut Vec<f32>, dims: usize, used: &[bool]) -> Vec<usize> {
+    let mut new_index = vec![usize::MAX; used.len()];
+    let mut kept = 0;
+
+    for (i, _) in used.iter().enumerate().filter(|(_, &used)| used) {
+        values.copy_within(i * dims..i * dims + dims, kept * dims);
+        new_index[i] = kept;
+        kept += 1;
+    }
+
+    values.truncate(kept * dims);
+
+    new_index
+}
+
+fn compact_values(values: &mut Vec<f64>, used: &[bool]) {
+    let mut used = used.iter();
+    values.retain(|_| *used.next().unwrap_or(&false));
+}
@@ -0,0 +1,895 @@
+//! A half-edge mesh over the polygons of a `Scene`, for algorithms that need
+//! adjacency. Every edge is a pair of half-edges, one per side, and a half-edge
+//! on the boundary has no face. Navigating from a half-edge to its twin, next,
+//! face or origin is O(1), and so is every step of the iterators around vertices
+//! and faces. Vertices, faces and half-edges are indices that stay valid across
+//! edits. Removed elements leave holes until `to_scene`.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::iter;
+
+use crate::math;
+use crate::obj::{Face, FaceState, FaceVertex, Scene, VertexAttribute};
+use crate::parser::VertexData;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HalfEdgeMesh {
+    position: Vec<f32>,
+    texcoord: Vec<f32>,
+    normal: Vec<f32>,
+    /// Per position, like `Scene::color`.
+    color: Vec<f32>,
+    attributes: Vec<VertexAttribute>,
+    material_libraries: Vec<String>,
+    states: Vec<FaceState>,
+    vertices: Vec<Vertex>,
+    faces: Vec<FaceRecord>,
+    /// The two halves of an edge are `2 * e` and `2 * e + 1`.
+    half_edges: Vec<HalfEdge>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Vertex {
+    /// An outgoing half-edge, the boundary one if the vertex is on the boundary.
+    /// `None` for vertices no face uses.
+    half_edge: Option<usize>,
+    removed: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct FaceRecord {
+    half_edge: usize,
+    state: usize,
+    removed: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct HalfEdge {
+    origin: usize,
+    face: Option<usize>,
+    next: usize,
+    prev: usize,
+    /// The texcoord and normal of the face's corner at `origin`.
+    texcoord: Option<usize>,
+    normal: Option<usize>,
+    removed: bool,
+}
+
+/// Why a `Scene` has no half-edge representation.
+#[derive(Debug, PartialEq, Eq)]
+pub enum HalfEdgeError {
+    /// A face with fewer than three distinct positions.
+    DegenerateFace { face: usize },
+    /// An edge used twice in the same direction, either by more than two faces or
+    /// by two faces with opposite winding.
+    NonManifoldEdge { from: usize, to: usize },
+    /// A vertex whose faces form more than one fan.
+    NonManifoldVertex { vertex: usize },
+    /// A face corner referring to a position, texcoord or normal the scene does
+    /// not have.
+    IndexOutOfRange {
+        face: usize,
+        attribute: &'static str,
+        index: usize,
+    },
+}
+
+impl fmt::Display for HalfEdgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HalfEdgeError::DegenerateFace { face } => {
+                write!(f, "face {} has fewer than 3 distinct vertices", face)
+            }
+            HalfEdgeError::NonManifoldEdge { from, to } => {
+                write!(f, "edge {} to {} is not manifold", from, to)
+            }
+            HalfEdgeError::NonManifoldVertex { vertex } => {
+                write!(f, "vertex {} is not manifold", vertex)
+            }
+            HalfEdgeError::IndexOutOfRange {
+                face,
+                attribute,
+                index,
+            } => write!(f, "face {} uses missing {} {}", face, attribute, index),
+        }
+    }
+}
+
+impl Error for HalfEdgeError {}
+
+/// Why an edit was refused. The mesh is left unchanged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditError {
+    /// Flipping needs a face on both sides.
+    BoundaryEdge,
+    /// Flipping needs both faces to be triangles.
+    NotTriangles,
+    /// The flipped edge already exists elsewhere.
+    ExistingEdge,
+    /// Collapsing would make the mesh non-manifold or degenerate.
+    NonManifold,
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            EditError::BoundaryEdge => "boundary edges cannot be flipped",
+            EditError::NotTriangles => "only edges between two triangles can be flipped",
+            EditError::ExistingEdge => "the flipped edge already exists",
+            EditError::NonManifold => "collapsing the edge would make the mesh non-manifold",
+        };
+
+        write!(f, "{}", reason)
+    }
+}
+
+impl Error for EditError {}
+
+impl HalfEdgeMesh {
+    /// Builds the mesh from the position indices of `scene`'s faces, keeping the
+    /// texcoord and normal indices of every corner. Vertices are the scene's
+    /// positions, in order, and faces its faces.
+    pub fn from_scene(scene: &Scene) -> Result<Self, HalfEdgeError> {
+        let vertex_count = scene.position_count();
+
+        for (id, face) in scene.faces.iter().enumerate() {
+            for corner in &face.vertices {
+                let indices = [
+                    ("position", Some(corner.position), vertex_count),
+                    ("texcoord", corner.texcoord, scene.texcoord_count()),
+                    ("normal", corner.normal, scene.normal_count()),
+                ];
+
+                for (attribute, index, count) in indices {
+                    if let Some(index) = index.filter(|&index| index >= count) {
+                        return Err(HalfEdgeError::IndexOutOfRange {
+                            face: id,
+                            attribute,
+                            index,
+                        });
+                    }
+                }
+            }
+        }
+
+        let mut mesh = HalfEdgeMesh {
+            position: scene.position.clone(),
+            texcoord: scene.texcoord.clone(),
+            normal: scene.normal.clone(),
+            color: scene.color.clone(),
+            attributes: scene.attributes.clone(),
+            material_libraries: scene.material_libraries.clone(),
+            states: scene.states.clone(),
+            vertices: vec![
+                Vertex {
+                    half_edge: None,
+                    removed: false,
+                };
+                vertex_count
+            ],
+            faces: Vec::with_capacity(scene.faces.len()),
+            half_edges: vec![],
+        };
+
+        let mut directed = HashMap::new();
+
+        for (id, face) in scene.faces.iter().enumerate() {
+            let corners = &face.vertices;
+            let mut distinct: Vec<_> = corners.iter().map(|vertex| vertex.position).collect();
+            distinct.sort_unstable();
+            distinct.dedup();
+
+            if distinct.len() < 3 || distinct.len() < corners.len() {
+                return Err(HalfEdgeError::DegenerateFace { face: id });
+            }
+
+            let mut loop_edges = Vec::with_capacity(corners.len());
+
+            for (i, corner) in corners.iter().enumerate() {
+                let (from, to) = (corner.position, corners[(i + 1) % corners.len()].position);
+
+                let half_edge = match directed.get(&(to, from)) {
+                    Some(&twin) => twin ^ 1,
+                    None => mesh.add_edge(from, to).0,
+                };
+
+                if mesh.half_edges[half_edge].face.is_some() || directed.contains_key(&(from, to)) {
+                    return Err(HalfEdgeError::NonManifoldEdge { from, to });
+                }
+
+                directed.insert((from, to), half_edge);
+
+                let record = &mut mesh.half_edges[half_edge];
+                record.face = Some(id);
+                record.texcoord = corner.texcoord;
+                record.normal = corner.normal;
+                loop_edges.push(half_edge);
+            }
+
+            for (i, &half_edge) in loop_edges.iter().enumerate() {
+                mesh.link(half_edge, loop_edges[(i + 1) % loop_edges.len()]);
+            }
+
+            mesh.faces.push(FaceRecord {
+                half_edge: loop_edges[0],
+                state: face.state,
+                removed: false,
+            });
+        }
+
+        // Boundary half-edges follow each other around holes, which needs a single
+        // one leaving each boundary vertex
+        let mut boundary_out = vec![None; vertex_count];
+
+        for (half_edge, record) in mesh.half_edges.iter().enumerate() {
+            if record.face.is_none() {
+                let out = &mut boundary_out[record.origin];
+
+                if out.is_some() {
+                    return Err(HalfEdgeError::NonManifoldVertex {
+                        vertex: record.origin,
+                    });
+                }
+
+                *out = Some(half_edge);
+            }
+        }
+
+        let mut outgoing = vec![0; vertex_count];
+
+        for half_edge in 0..mesh.half_edges.len() {
+            let origin = mesh.half_edges[half_edge].origin;
+            outgoing[origin] += 1;
+
+            if mesh.half_edges[half_edge].face.is_none() {
+                let next = boundary_out[mesh.destination(half_edge)]
+                    .expect("a boundary half-edge ends where another starts");
+                mesh.link(half_edge, next);
+            }
+
+            let vertex = &mut mesh.vertices[origin];
+            vertex.half_edge = boundary_out[origin]
+                .or(vertex.half_edge)
+                .or(Some(half_edge));
+        }
+
+        // Faces around a vertex with several fans are not all reachable from one
+        for (vertex, &count) in outgoing.iter().enumerate() {
+            if mesh.outgoing(vertex).take(count + 1).count() != count {
+                return Err(HalfEdgeError::NonManifoldVertex { vertex });
+            }
+        }
+
+        Ok(mesh)
+    }
+
+    /// The scene the mesh was built from, with the edits applied. Removed vertices
+    /// and faces are dropped and the other vertices keep their order. Texcoords and
+    /// normals are kept as they are, including ones no face uses anymore.
+    pub fn to_scene(&self) -> Scene {
+        let mut remap = vec![usize::MAX; self.vertices.len()];
+        let mut scene = Scene {
+            material_libraries: self.material_libraries.clone(),
+            texcoord: self.texcoord.clone(),
+            normal: self.normal.clone(),
+            states: self.states.clone(),
+            ..Scene::default()
+        };
+
+        let kept = || self.vertices().map(|vertex| vertex * 3);
+
+        for (new, vertex) in self.vertices().enumerate() {
+            remap[vertex] = new;
+        }
+
+        scene.position = kept()
+            .flat_map(|i| self.position[i..i + 3].iter().copied())
+            .collect();
+
+        if !self.color.is_empty() {
+            scene.color = kept()
+                .flat_map(|i| self.color[i..i + 3].iter().copied())
+                .collect();
+        }
+
+        scene.attributes = self
+            .attributes
+            .iter()
+            .map(|attribute| VertexAttribute {
+                name: attribute.name.clone(),
+                values: self.vertices().map(|v| attribute.values[v]).collect(),
+            })
+            .collect();
+
+        scene.faces = self
+            .faces()
+            .map(|face| Face {
+                vertices: self
+                    .face_half_edges(face)
+                    .map(|half_edge| {
+                        let record = &self.half_edges[half_edge];
+
+                        FaceVertex {
+                            position: remap[record.origin],
+                            texcoord: record.texcoord,
+                            normal: record.normal,
+                        }
+                    })
+                    .collect(),
+                state: self.faces[face].state,
+            })
+            .collect();
+
+        scene
+    }
+
+    /// The faces as a triangle soup, like `Scene::triangle_soup`.
+    pub fn triangle_soup(&self) -> VertexData {
+        let scene = self.to_scene();
+        scene.triangle_soup(&scene.faces)
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertices().count()
+    }
+
+    pub fn face_count(&self) -> usize {
+        self.faces().count()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.half_edges.iter().filter(|edge| !edge.removed).count() / 2
+    }
+
+    /// The vertices that have not been removed.
+    pub fn vertices(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.vertices.len()).filter(move |&vertex| !self.vertices[vertex].removed)
+    }
+
+    /// The faces that have not been removed.
+    pub fn faces(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.faces.len()).filter(move |&face| !self.faces[face].removed)
+    }
+
+    /// One half-edge of each edge, the first of its pair.
+    pub fn edges(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.half_edges.len())
+            .step_by(2)
+            .filter(move |&half_edge| !self.half_edges[half_edge].removed)
+    }
+
+    pub fn position(&self, vertex: usize) -> [f32; 3] {
+        [0, 1, 2].map(|i| self.position[vertex * 3 + i])
+    }
+
+    pub fn set_position(&mut self, vertex: usize, position: [f32; 3]) {
+        self.position[vertex * 3..vertex * 3 + 3].copy_from_slice(&position);
+    }
+
+    /// An outgoing half-edge, on the boundary if the vertex is.
+    pub fn vertex_half_edge(&self, vertex: usize) -> Option<usize> {
+        self.vertices[vertex].half_edge
+    }
+
+    pub fn face_half_edge(&self, face: usize) -> usize {
+        self.faces[face].half_edge
+    }
+
+    pub fn origin(&self, half_edge: usize) -> usize {
+        self.half_edges[half_edge].origin
+    }
+
+    pub fn destination(&self, half_edge: usize) -> usize {
+        self.origin(self.twin(half_edge))
+    }
+
+    pub fn twin(&self, half_edge: usize) -> usize {
+        half_edge ^ 1
+    }
+
+    pub fn next(&self, half_edge: usize) -> usize {
+        self.half_edges[half_edge].next
+    }
+
+    pub fn prev(&self, half_edge: usize) -> usize {
+        self.half_edges[half_edge].prev
+    }
+
+    /// `None` on the boundary.
+    pub fn face(&self, half_edge: usize) -> Option<usize> {
+        self.half_edges[half_edge].face
+    }
+
+    /// The texcoord and normal indices of the corner at the half-edge's origin.
+    pub fn corner(&self, half_edge: usize) -> (Option<usize>, Option<usize>) {
+        let record = &self.half_edges[half_edge];
+        (record.texcoord, record.normal)
+    }
+
+    pub fn is_boundary_half_edge(&self, half_edge: usize) -> bool {
+        self.face(half_edge).is_none()
+    }
+
+    /// Whether either side of the edge has no face.
+    pub fn is_boundary_edge(&self, half_edge: usize) -> bool {
+        self.is_boundary_half_edge(half_edge) || self.is_boundary_half_edge(self.twin(half_edge))
+    }
+
+    /// Vertices no face uses are on the boundary too.
+    pub fn is_boundary_vertex(&self, vertex: usize) -> bool {
+        self.vertex_half_edge(vertex)
+            .is_none_or(|half_edge| self.is_boundary_half_edge(half_edge))
+    }
+
+    pub fn is_boundary_face(&self, face: usize) -> bool {
+        self.face_half_edges(face)
+            .any(|half_edge| self.is_boundary_half_edge(self.twin(half_edge)))
+    }
+
+    /// The half-edges leaving `vertex`, turning around it from its boundary one.
+    pub fn outgoing(&self, vertex: usize) -> impl Iterator<Item = usize> + '_ {
+        let start = self.vertex_half_edge(vertex);
+
+        iter::successors(start, move |&half_edge| {
+            Some(self.twin(self.prev(half_edge))).filter(|&next| Some(next) != start)
+        })
+    }
+
+    /// The vertices sharing an edge with `vertex`.
+    pub fn one_ring(&self, vertex: usize) -> impl Iterator<Item = usize> + '_ {
+        self.outgoing(vertex)
+            .map(move |half_edge| self.destination(half_edge))
+    }
+
+    /// The faces around `vertex`.
+    pub fn vertex_faces(&self, vertex: usize) -> impl Iterator<Item = usize> + '_ {
+        self.outgoing(vertex)
+            .filter_map(move |half_edge| self.face(half_edge))
+    }
+
+    /// The half-edges of `face`, in its winding order.
+    pub fn face_half_edges(&self, face: usize) -> impl Iterator<Item = usize> + '_ {
+        let start = self.faces[face].half_edge;
+
+        iter::successors(Some(start), move |&half_edge| {
+            Some(self.next(half_edge)).filter(|&next| next != start)
+        })
+    }
+
+    pub fn face_vertices(&self, face: usize) -> impl Iterator<Item = usize> + '_ {
+        self.face_half_edges(face)
+            .map(move |half_edge| self.origin(half_edge))
+    }
+
+    /// The faces sharing an edge with `face`.
+    pub fn face_neighbours(&self, face: usize) -> impl Iterator<Item = usize> + '_ {
+        self.face_half_edges(face)
+            .filter_map(move |half_edge| self.face(self.twin(half_edge)))
+    }
+
+    /// The half-edge from `from` to `to`, if they share an edge.
+    pub fn find_half_edge(&self, from: usize, to: usize) -> Option<usize> {
+        self.outgoing(from)
+            .find(|&half_edge| self.destination(half_edge) == to)
+    }
+
+    /// Replaces the edge between two triangles by the one between their opposite
+    /// corners, which keeps their winding.
+    pub fn flip_edge(&mut self, half_edge: usize) -> Result<(), EditError> {
+        let twin = self.twin(half_edge);
+
+        let (face, twin_face) = match (self.face(half_edge), self.face(twin)) {
+            (Some(face), Some(twin_face)) => (face, twin_face),
+            _ => return Err(EditError::BoundaryEdge),
+        };
+
+        if self.face_len(face) != 3 || self.face_len(twin_face) != 3 {
+            return Err(EditError::NotTriangles);
+        }
+
+        // a -> b -> c and b -> a -> d become a -> d -> c and b -> c -> d
+        let (a, b) = (self.origin(half_edge), self.origin(twin));
+        let (b_c, c_a) = (self.next(half_edge), self.prev(half_edge));
+        let (a_d, d_b) = (self.next(twin), self.prev(twin));
+        let (c, d) = (self.origin(c_a), self.origin(d_b));
+
+        if c == d || self.find_half_edge(c, d).is_some() {
+            return Err(EditError::ExistingEdge);
+        }
+
+        let (d_corner, c_corner) = (self.corner(d_b), self.corner(c_a));
+
+        self.half_edges[half_edge].origin = d;
+        self.set_corner(half_edge, d_corner);
+        self.half_edges[twin].origin = c;
+        self.set_corner(twin, c_corner);
+
+        for (edges, face) in [([a_d, half_edge, c_a], face), ([b_c, twin, d_b], twin_face)] {
+            for i in 0..3 {
+                self.link(edges[i], edges[(i + 1) % 3]);
+                self.half_edges[edges[i]].face = Some(face);
+            }
+
+            self.faces[face].half_edge = edges[0];
+        }
+
+        if self.vertices[a].half_edge == Some(half_edge) {
+            self.vertices[a].half_edge = Some(a_d);
+        }
+
+        if self.vertices[b].half_edge == Some(twin) {
+            self.vertices[b].half_edge = Some(b_c);
+        }
+
+        Ok(())
+    }
+
+    /// Inserts a vertex at `t` along the edge, from its origin to its destination,
+    /// and returns it. Adjacent triangles are split in two so that triangle meshes
+    /// stay triangle meshes, other faces get an extra corner. Texcoords, normals,
+    /// colours and attributes of the new vertex are interpolated.
+    pub fn split_edge(&mut self, half_edge: usize, t: f32) -> usize {
+        let twin = self.twin(half_edge);
+        let (a, b) = (self.origin(half_edge), self.origin(twin));
+
+        let vertex = self.vertices.len();
+        let position = lerp(&self.position(a), &self.position(b), t);
+        self.position.extend_from_slice(&position);
+
+        if !self.color.is_empty() {
+            let color = lerp(
+                &self.color[a * 3..a * 3 + 3],
+                &self.color[b * 3..b * 3 + 3],
+                t,
+            );
+            self.color.extend_from_slice(&color);
+        }
+
+        for attribute in &mut self.attributes {
+            let (from, to) = (attribute.values[a], attribute.values[b]);
+            attribute.values.push(from + (to - from) * f64::from(t));
+        }
+
+        // The new corners in both faces, shared when the faces share the corners
+        // at both ends, i.e. the edge is not a seam
+        let mut corners = HashMap::new();
+        let mut new_corner = |mesh: &mut Self, from: usize, to: usize| {
+            let (from, to) = (mesh.corner(from), mesh.corner(to));
+
+            *corners
+                .entry((from, to))
+                .or_insert_with(|| mesh.interpolate_corner(from, to, t))
+        };
+
+        // a -> b becomes a -> m -> b, and b -> a becomes b -> m -> a
+        let (m_b, b_m) = self.add_edge(vertex, b);
+        let after = self.next(half_edge);
+        let before = self.prev(twin);
+
+        let corner = match self.face(half_edge) {
+            Some(_) => new_corner(self, half_edge, after),
+            None => (None, None),
+        };
+        let twin_next = self.next(twin);
+        let twin_corner = match self.face(twin) {
+            Some(_) => new_corner(self, twin_next, twin),
+            None => (None, None),
+        };
+        let b_corner = self.corner(twin);
+
+        self.half_edges[m_b].face = self.face(half_edge);
+        self.set_corner(m_b, corner);
+        self.half_edges[b_m].face = self.face(twin);
+        self.set_corner(b_m, b_corner);
+        self.half_edges[twin].origin = vertex;
+        self.set_corner(twin, twin_corner);
+
+        self.link(m_b, after);
+        self.link(half_edge, m_b);
+        self.link(before, b_m);
+        self.link(b_m, twin);
+
+        if self.vertices[b].half_edge == Some(twin) {
+            self.vertices[b].half_edge = Some(b_m);
+        }
+
+        let boundary = if self.is_boundary_half_edge(twin) {
+            twin
+        } else {
+            m_b
+        };
+
+        self.vertices.push(Vertex {
+            half_edge: Some(boundary),
+            removed: false,
+        });
+
+        // Former triangles are quads with the new vertex at `from`
+        for from in [m_b, twin] {
+            if let Some(face) = self.face(from) {
+                if self.face_len(face) == 4 {
+                    self.split_face(from, self.next(self.next(from)));
+                }
+            }
+        }
+
+        vertex
+    }
+
+    /// Merges the destination of the edge into its origin, which keeps its
+    /// position, and returns the origin. Triangles on the edge disappear, other
+    /// faces lose a corner. Refused when the result would not be a manifold mesh.
+    pub fn collapse_edge(&mut self, half_edge: usize) -> Result<usize, EditError> {
+        let twin = self.twin(half_edge);
+        let (a, b) = (self.origin(half_edge), self.origin(twin));
+
+        // Joining two boundaries through the inside would pinch the surface
+        if !self.is_boundary_edge(half_edge)
+            && self.is_boundary_vertex(a)
+            && self.is_boundary_vertex(b)
+        {
+            return Err(EditError::NonManifold);
+        }
+
+        let mut opposite = vec![];
+
+        for side in [half_edge, twin] {
+            if let Some(face) = self.face(side) {
+                if self.face_len(face) == 3 {
+                    let (next, prev) = (self.next(side), self.prev(side));
+
+                    if self.is_boundary_half_edge(self.twin(next))
+                        && self.is_boundary_half_edge(self.twin(prev))
+                    {
+                        return Err(EditError::NonManifold);
+                    }
+
+                    opposite.push(self.origin(prev));
+                }
+            }
+        }
+
+        // The link condition: the only vertices next to both ends are the opposite
+        // corners of the triangles that disappear
+        let a_ring: Vec<_> = self.one_ring(a).collect();
+
+        if self
+            .one_ring(b)
+            .any(|vertex| a_ring.contains(&vertex) && !opposite.contains(&vertex))
+        {
+            return Err(EditError::NonManifold);
+        }
+
+        if let [c, d] = opposite[..] {
+            if c == d || self.find_half_edge(c, d).is_some() {
+                return Err(EditError::NonManifold);
+            }
+        }
+
+        // A face with both ends away from the edge would get a repeated corner
+        let sides = [self.face(half_edge), self.face(twin)];
+        let a_faces: Vec<_> = self.vertex_faces(a).collect();
+
+        if self
+            .vertex_faces(b)
+            .any(|face| a_faces.contains(&face) && !sides.contains(&Some(face)))
+        {
+            return Err(EditError::NonManifold);
+        }
+
+        let b_outgoing: Vec<_> = self.outgoing(b).collect();
+        let a_outgoing: Vec<_> = self.outgoing(a).collect();
+        let mut fix = vec![a];
+
+        for side in [half_edge, twin] {
+            let face = self.face(side);
+
+            if face.is_some_and(|face| self.face_len(face) == 3) {
+                let (next, prev) = (self.next(side), self.prev(side));
+
+                // Of the triangle's two other edges, the one away from `b` is kept.
+                // Its half in the triangle takes the place of the other edge's half
+                // in the face across, which runs the same way
+                let (kept, removed) = if side == half_edge {
+                    (prev, next)
+                } else {
+                    (next, prev)
+                };
+
+                let across = self.twin(removed);
+                let (across_prev, across_next) = (self.prev(across), self.next(across));
+                let across_face = self.face(across);
+
+                self.half_edges[kept].face = across_face;
+                let corner = self.corner(across);
+                self.set_corner(kept, corner);
+                self.link(across_prev, kept);
+                self.link(kept, across_next);
+
+                if let Some(across_face) = across_face {
+                    if self.faces[across_face].half_edge == across {
+                        self.faces[across_face].half_edge = kept;
+                    }
+                }
+
+                let opposite_out = if self.origin(kept) == a {
+                    self.twin(kept)
+                } else {
+                    kept
+                };
+                let opposite = self.origin(opposite_out);
+                self.vertices[opposite].half_edge = Some(opposite_out);
+                fix.push(opposite);
+
+                self.remove_edge(removed);
+                self.faces[face.unwrap()].removed = true;
+            } else {
+                let (prev, next) = (self.prev(side), self.next(side));
+                self.link(prev, next);
+
+                if side == half_edge && face.is_some() {
+                    let corner = self.corner(side);
+                    self.set_corner(next, corner);
+                }
+
+                if let Some(face) = face {
+                    if self.faces[face].half_edge == side {
+                        self.faces[face].half_edge = next;
+                    }
+                }
+            }
+        }
+
+        self.remove_edge(half_edge);
+
+        for &out in &b_outgoing {
+            if !self.half_edges[out].removed {
+                self.half_edges[out].origin = a;
+            }
+        }
+
+        self.vertices[b].removed = true;
+        self.vertices[b].half_edge = None;
+        self.vertices[a].half_edge = a_outgoing
+            .iter()
+            .chain(&b_outgoing)
+            .copied()
+            .find(|&out| !self.half_edges[out].removed);
+
+        for vertex in fix {
+            self.prefer_boundary(vertex);
+        }
+
+        Ok(a)
+    }
+
+    fn face_len(&self, face: usize) -> usize {
+        self.face_half_edges(face).count()
+    }
+
+    fn link(&mut self, half_edge: usize, next: usize) {
+        self.half_edges[half_edge].next = next;
+        self.half_edges[next].prev = half_edge;
+    }
+
+    fn set_corner(&mut self, half_edge: usize, (texcoord, normal): (Option<usize>, Option<usize>)) {
+        self.half_edges[half_edge].texcoord = texcoord;
+        self.half_edges[half_edge].normal = normal;
+    }
+
+    /// A new edge pair, both halves on the boundary and linked to themselves.
+    fn add_edge(&mut self, from: usize, to: usize) -> (usize, usize) {
+        let half_edge = self.half_edges.len();
+
+        for (origin, own) in [(from, half_edge), (to, half_edge + 1)] {
+            self.half_edges.push(HalfEdge {
+                origin,
+                face: None,
+                next: own,
+                prev: own,
+                texcoord: None,
+                normal: None,
+                removed: false,
+            });
+        }
+
+        (half_edge, half_edge + 1)
+    }
+
+    fn remove_edge(&mut self, half_edge: usize) {
+        self.half_edges[half_edge].removed = true;
+        self.half_edges[half_edge ^ 1].removed = true;
+    }
+
+    /// Connects the origins of two half-edges of the same face. The part from
+    /// `to` back around to `from` becomes a new face.
+    fn split_face(&mut self, from: usize, to: usize) {
+        let face = self.face(from).expect("only faces are split");
+        let (from_prev, to_prev) = (self.prev(from), self.prev(to));
+        let (forward, backward) = self.add_edge(self.origin(from), self.origin(to));
+
+        let (from_corner, to_corner) = (self.corner(from), self.corner(to));
+        self.set_corner(forward, from_corner);
+        self.set_corner(backward, to_corner);
+
+        self.link(to_prev, backward);
+        self.link(backward, from);
+        self.link(from_prev, forward);
+        self.link(forward, to);
+
+        let new_face = self.faces.len();
+        self.faces.push(FaceRecord {
+            half_edge: forward,
+            state: self.faces[face].state,
+            removed: false,
+        });
+
+        self.half_edges[backward].face = Some(face);
+        self.faces[face].half_edge = from;
+
+        let new_edges: Vec<_> = self.face_half_edges(new_face).collect();
+
+        for half_edge in new_edges {
+            self.half_edges[half_edge].face = Some(new_face);
+        }
+    }
+
+    fn interpolate_corner(
+        &mut self,
+        (from_texcoord, from_normal): (Option<usize>, Option<usize>),
+        (to_texcoord, to_normal): (Option<usize>, Option<usize>),
+        t: f32,
+    ) -> (Option<usize>, Option<usize>) {
+        let texcoord = match (from_texcoord, to_texcoord) {
+            (Some(from), Some(to)) => {
+                let texcoord = lerp(
+                    &self.texcoord[from * 2..from * 2 + 2],
+                    &self.texcoord[to * 2..to * 2 + 2],
+                    t,
+                );
+                self.texcoord.extend_from_slice(&texcoord[..2]);
+                Some(self.texcoord.len() / 2 - 1)
+            }
+            _ => None,
+        };
+
+        let normal = match (from_normal, to_normal) {
+            (Some(from), Some(to)) => {
+                let normal = lerp(
+                    &self.normal[from * 3..from * 3 + 3],
+                    &self.normal[to * 3..to * 3 + 3],
+                    t,
+                );
+                self.normal
+                    .extend_from_slice(&math::normalize(normal).unwrap_or(normal));
+                Some(self.normal.len() / 3 - 1)
+            }
+            _ => None,
+        };
+
+        (texcoord, normal)
+    }
+
+    fn prefer_boundary(&mut self, vertex: usize) {
+        let boundary = self
+            .outgoing(vertex)
+            .find(|&half_edge| self.is_boundary_half_edge(half_edge));
+
+        if boundary.is_some() {
+            self.vertices[vertex].half_edge = boundary;
+        }
+    }
+}
+
+/// Up to three components, the rest of the array is zero.
+fn lerp(from: &[f32], to: &[f32], t: f32) -> [f32; 3] {
+    let mut lerped = [0.0; 3];
+
+    for (i, (from, to)) in from.iter().zip(to).enumerate() {
+        lerped[i] = from + (to - from) * t;
+    }
+
+    lerped
+}
@@ -0,0 +1,472 @@
+//! Geometry on parsed meshes. `analyze` reports what a mesh is made of and what is
+//! wrong with it, on a triangle soup or on the faces of a `Scene`, and `cleanup`
+//! fixes the simpler problems. `tangent` generates tangents for normal mapping.
+
+use std::collections::{HashMap, HashSet};
+
+use serde_json::{json, Value};
+
+use crate::obj::Scene;
+use crate::parser::Group;
+use crate::stl;
+
+pub mod cleanup;
+pub mod half_edge;
+pub mod tangent;
+
+/// What `analyze` found. Faces are polygons, or the triangles of a soup.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeshReport {
+    /// Positions, those of a triangle soup welded when they are bit-identical.
+    pub vertices: usize,
+    pub faces: usize,
+    /// After fan triangulation.
+    pub triangles: usize,
+    /// Positions no face uses.
+    pub unused_vertices: usize,
+    /// Faces with fewer than three distinct vertices, or zero area up to rounding.
+    pub degenerate_faces: usize,
+    /// Faces of a `Scene` using a position it does not have. They count towards
+    /// `faces` but are left out of everything else.
+    pub invalid_faces: usize,
+    /// Faces with the same vertices as an earlier face, in any order.
+    pub duplicate_faces: usize,
+    /// Edges shared by more than two faces.
+    pub non_manifold_edges: usize,
+    /// Vertices whose faces form more than one fan, e.g. two cones touching at
+    /// their tips.
+    pub non_manifold_vertices: usize,
+    /// Edges used by a single face.
+    pub boundary_edges: usize,
+    /// Chains of boundary edges, followed through the faces around each vertex, so
+    /// two faces that only share a vertex are bounded by two loops. A closed mesh
+    /// has none.
+    pub boundary_loops: usize,
+    /// Faces connected through shared vertices.
+    pub connected_components: usize,
+    /// Edges shared by two faces that traverse it in the same direction, so one of
+    /// them is flipped.
+    pub inconsistent_winding: usize,
+    /// Positions with a NaN or infinite coordinate.
+    pub non_finite_vertices: usize,
+    /// Of the finite positions, `None` if there are none.
+    pub bounds: Option<Bounds>,
+    /// Faces with a non-finite vertex are left out of the area and the volume.
+    pub surface_area: f64,
+    /// Positive when the faces are wound counter-clockwise seen from outside. Only
+    /// meaningful for closed meshes.
+    pub signed_volume: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+impl MeshReport {
+    /// Every face is valid, every edge has two consistently wound faces and every
+    /// vertex one fan, so the faces enclose `signed_volume`.
+    pub fn is_closed_manifold(&self) -> bool {
+        self.invalid_faces == 0
+            && self.boundary_edges == 0
+            && self.non_manifold_edges == 0
+            && self.non_manifold_vertices == 0
+            && self.inconsistent_winding == 0
+    }
+
+    pub fn to_json(&self) -> Value {
+        // JSON has no NaN or infinity
+        let finite = |num: f64| {
+            if num.is_finite() {
+                json!(num)
+            } else {
+                Value::Null
+            }
+        };
+
+        json!({
+            "vertices": self.vertices,
+            "faces": self.faces,
+            "triangles": self.triangles,
+            "unused_vertices": self.unused_vertices,
+            "degenerate_faces": self.degenerate_faces,
+            "invalid_faces": self.invalid_faces,
+            "duplicate_faces": self.duplicate_faces,
+            "non_manifold_edges": self.non_manifold_edges,
+            "non_manifold_vertices": self.non_manifold_vertices,
+            "boundary_edges": self.boundary_edges,
+            "boundary_loops": self.boundary_loops,
+            "connected_components": self.connected_components,
+            "inconsistent_winding": self.inconsistent_winding,
+            "non_finite_vertices": self.non_finite_vertices,
+            "bounds": self.bounds.map(|bounds| json!({
+                "min": bounds.min,
+                "max": bounds.max,
+            })),
+            "surface_area": finite(self.surface_area),
+            "signed_volume": finite(self.signed_volume),
+            "closed_manifold": self.is_closed_manifold(),
+        })
+    }
+}
+
+/// Analyzes a triangle soup, such as a group of `parser::parse_obj_threaded`.
+/// Connectivity comes from welding bit-identical positions, as `stl::read_indexed`
+/// does, so texcoords and normals do not split vertices.
+pub fn analyze(group: &Group) -> MeshReport {
+    let mut position = vec![];
+    let mut welded = HashMap::new();
+
+    let faces: Vec<Vec<usize>> = group
+        .position
+        .chunks_exact(9)
+        .map(|triangle| {
+            triangle
+                .chunks_exact(3)
+                .map(|vertex| stl::weld(&mut position, &mut welded, vertex))
+                .collect()
+        })
+        .collect();
+
+    analyze_faces(&position, &faces)
+}
+
+/// Analyzes the polygons of `scene` by their position indices. Faces with an
+/// index out of range are only counted, as `invalid_faces`.
+pub fn analyze_scene(scene: &Scene) -> MeshReport {
+    let vertex_count = scene.position_count();
+
+    let (faces, invalid): (Vec<Vec<usize>>, _) = scene
+        .faces
+        .iter()
+        .map(|face| face.vertices.iter().map(|vertex| vertex.position).collect())
+        .partition(|face: &Vec<usize>| face.iter().all(|&vertex| vertex < vertex_count));
+
+    let mut report = analyze_faces(&scene.position, &faces);
+    report.faces += invalid.len();
+    report.invalid_faces = invalid.len();
+
+    report
+}
+
+/// Both kinds of input as polygons indexing into `position`, which they must be
+/// in range of.
+fn analyze_faces(position: &[f32], faces: &[Vec<usize>]) -> MeshReport {
+    let vertex_count = position.len() / 3;
+    let point = |vertex: usize| [0, 1, 2].map(|i| f64::from(position[vertex * 3 + i]));
+
+    let finite: Vec<bool> = position
+        .chunks_exact(3)
+        .map(|vertex| vertex.iter().all(|num| num.is_finite()))
+        .collect();
+
+    let mut report = MeshReport {
+        vertices: vertex_count,
+        faces: faces.len(),
+        triangles: 0,
+        unused_vertices: 0,
+        degenerate_faces: 0,
+        invalid_faces: 0,
+        duplicate_faces: 0,
+        non_manifold_edges: 0,
+        non_manifold_vertices: 0,
+        boundary_edges: 0,
+        boundary_loops: 0,
+        connected_components: 0,
+        inconsistent_winding: 0,
+        non_finite_vertices: finite.iter().filter(|&&finite| !finite).count(),
+        bounds: bounds(position, &finite),
+        surface_area: 0.0,
+        signed_volume: 0.0,
+    };
+
+    let mut used = vec![false; vertex_count];
+    let mut components = UnionFind::new(vertex_count);
+    let mut seen = HashSet::new();
+
+    // Each undirected edge, smaller vertex first, with the corners of its faces at
+    // both ends and whether the face runs from the smaller vertex to the larger
+    let mut edges: HashMap<(usize, usize), Vec<EdgeUse>> = HashMap::new();
+    let mut corners = vec![];
+
+    for face in faces {
+        report.triangles += face.len().saturating_sub(2);
+
+        for &vertex in face {
+            used[vertex] = true;
+            components.union(face[0], vertex);
+        }
+
+        let mut sorted = face.clone();
+        sorted.sort_unstable();
+
+        if !seen.insert(sorted.clone()) {
+            report.duplicate_faces += 1;
+        }
+
+        sorted.dedup();
+        let mut degenerate = sorted.len() < 3;
+
+        if face.iter().all(|&vertex| finite[vertex]) {
+            let points: Vec<_> = face.iter().map(|&vertex| point(vertex)).collect();
+            let (area, volume) = area_and_volume(&points);
+
+            degenerate |= area <= f64::EPSILON * longest_edge_squared(&points);
+            report.surface_area += area;
+            report.signed_volume += volume;
+        }
+
+        if degenerate {
+            report.degenerate_faces += 1;
+        }
+
+        // A vertex repeated in the face has one corner, at its first occurrence
+        let offset = corners.len();
+        let corner = |i: usize| offset + face.iter().position(|&v| v == face[i]).unwrap();
+        corners.extend_from_slice(face);
+
+        for i in 0..face.len() {
+            let next = (i + 1) % face.len();
+            let (a, b) = (face[i], face[next]);
+
+            if a == b {
+                continue;
+            }
+
+            let edge_use = if a < b {
+                EdgeUse {
+                    corners: (corner(i), corner(next)),
+                    forward: true,
+                }
+            } else {
+                EdgeUse {
+                    corners: (corner(next), corner(i)),
+                    forward: false,
+                }
+            };
+
+            edges
+                .entry((a.min(b), a.max(b)))
+                .or_default()
+                .push(edge_use);
+        }
+    }
+
+    let mut fans = UnionFind::new(corners.len());
+    let mut boundary = vec![];
+
+    for (&(a, b), uses) in &edges {
+        match uses.as_slice() {
+            [edge_use] => {
+                report.boundary_edges += 1;
+                boundary.push(edge_use.directed(a, b));
+            }
+            [first, second] if first.forward == second.forward => {
+                report.inconsistent_winding += 1;
+            }
+            [_, _] => {}
+            _ => report.non_manifold_edges += 1,
+        }
+
+        // Faces across an edge are part of the same fan around both its vertices
+        for edge_use in &uses[1..] {
+            fans.union(uses[0].corners.0, edge_use.corners.0);
+            fans.union(uses[0].corners.1, edge_use.corners.1);
+        }
+    }
+
+    let mut fan = vec![None; vertex_count];
+    let mut pinched = vec![false; vertex_count];
+
+    for (corner, &vertex) in corners.iter().enumerate() {
+        let root = fans.find(corner);
+
+        match fan[vertex] {
+            None => fan[vertex] = Some(root),
+            Some(first) if first != root => pinched[vertex] = true,
+            Some(_) => {}
+        }
+    }
+
+    report.signed_volume /= 6.0;
+    report.non_manifold_vertices = pinched.iter().filter(|&&pinched| pinched).count();
+    report.unused_vertices = used.iter().filter(|&&used| !used).count();
+    report.connected_components = components.count_roots((0..vertex_count).filter(|&v| used[v]));
+    report.boundary_loops = boundary_loops(&boundary, &mut fans);
+
+    report
+}
+
+struct EdgeUse {
+    /// The face's corners at the smaller and the larger vertex of the edge.
+    corners: (usize, usize),
+    forward: bool,
+}
+
+impl EdgeUse {
+    /// The edge from `a` to `b`, `a < b`, in the direction its face runs along it.
+    fn directed(&self, a: usize, b: usize) -> BoundaryEdge {
+        if self.forward {
+            BoundaryEdge {
+                from: (a, self.corners.0),
+                to: (b, self.corners.1),
+            }
+        } else {
+            BoundaryEdge {
+                from: (b, self.corners.1),
+                to: (a, self.corners.0),
+            }
+        }
+    }
+}
+
+/// An edge of a single face, as its vertices and the face's corners at them.
+struct BoundaryEdge {
+    from: (usize, usize),
+    to: (usize, usize),
+}
+
+/// Walks the boundary edges, leaving each vertex by an edge whose corner there is
+/// in the same fan as the corner the walk arrived at. Only where the winding is
+/// inconsistent is there no such edge, and then any edge leaving the vertex is
+/// taken.
+fn boundary_loops(boundary: &[BoundaryEdge], fans: &mut UnionFind) -> usize {
+    let mut leaving: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    let mut leaving_vertex: HashMap<usize, Vec<usize>> = HashMap::new();
+
+    for (i, edge) in boundary.iter().enumerate() {
+        let (vertex, corner) = edge.from;
+        leaving
+            .entry((vertex, fans.find(corner)))
+            .or_default()
+            .push(i);
+        leaving_vertex.entry(vertex).or_default().push(i);
+    }
+
+    let mut visited = vec![false; boundary.len()];
+    let mut loops = 0;
+
+    for start in 0..boundary.len() {
+        if visited[start] {
+            continue;
+        }
+
+        loops += 1;
+        let mut edge = start;
+
+        loop {
+            visited[edge] = true;
+
+            let (vertex, corner) = boundary[edge].to;
+            let edges = leaving
+                .get(&(vertex, fans.find(corner)))
+                .or_else(|| leaving_vertex.get(&vertex));
+
+            // Back at the start, the loop is closed
+            let next = edges.and_then(|edges| edges.iter().copied().find(|&next| !visited[next]));
+
+            match next {
+                Some(next) => edge = next,
+                None => break,
+            }
+        }
+    }
+
+    loops
+}
+
+fn bounds(position: &[f32], finite: &[bool]) -> Option<Bounds> {
+    position
+        .chunks_exact(3)
+        .zip(finite)
+        .filter(|(_, &finite)| finite)
+        .fold(None, |bounds, (vertex, _)| {
+            let Bounds { mut min, mut max } = bounds.unwrap_or(Bounds {
+                min: [f32::INFINITY; 3],
+                max: [f32::NEG_INFINITY; 3],
+            });
+
+            for i in 0..3 {
+                min[i] = min[i].min(vertex[i]);
+                max[i] = max[i].max(vertex[i]);
+            }
+
+            Some(Bounds { min, max })
+        })
+}
+
+/// The area of a polygon from its Newell normal, which is exact for planar
+/// polygons, convex or not, and six times the signed volume of the cone from the
+/// origin to its fan triangles.
+fn area_and_volume(points: &[[f64; 3]]) -> (f64, f64) {
+    let mut normal = [0.0; 3];
+    let mut volume = 0.0;
+
+    for (i, p) in points.iter().enumerate() {
+        let q = points[(i + 1) % points.len()];
+        let cross = cross(*p, q);
+
+        for axis in 0..3 {
+            normal[axis] += cross[axis];
+        }
+
+        if i > 0 && i + 1 < points.len() {
+            volume += dot(points[0], cross);
+        }
+    }
+
+    (dot(normal, normal).sqrt() / 2.0, volume)
+}
+
+fn longest_edge_squared(points: &[[f64; 3]]) -> f64 {
+    (0..points.len())
+        .map(|i| {
+            let (p, q) = (points[i], points[(i + 1) % points.len()]);
+            let edge = [q[0] - p[0], q[1] - p[1], q[2] - p[2]];
+            dot(edge, edge)
+        })
+        .fold(0.0, f64::max)
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+struct UnionFind {
+    parent: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(len: usize) -> Self {
+        UnionFind {
+            parent: (0..len).collect(),
+        }
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+
+        i
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        self.parent[a] = b;
+    }
+
+    fn count_roots(&mut self, elements: impl Iterator<Item = usize>) -> usize {
+        elements.map(|i| self.find(i)).collect::<HashSet<_>>().len()
+    }
+}
@@ -0,0 +1,598 @@
+//! Tangents for normal mapping, computed the way MikkTSpace does so they match
+//! what bakers such as Blender, Substance and xNormal assume. This follows the
+//! reference `mikktspace.c` with its default 180 degree angular threshold, step by
+//! step and in the same order. `tests/tangent.rs` compares it with
+//! `bevy_mikktspace`, a port of that file, on meshes with UV seams, mirrored
+//! texcoords and degenerate triangles.
+//!
+//! Corners with the same position, texcoord and normal are one vertex. Around
+//! every vertex, triangles connected through such vertices and with the same UV
+//! winding share a tangent, the angle weighted average of their own tangents
+//! projected onto the normal's plane. Degenerate triangles take the tangents of
+//! the first good triangle at the same vertex.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use crate::parser::Group;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TangentError {
+    /// Tangents follow the texcoords, so every vertex needs one.
+    MissingTexcoords,
+    MissingNormals,
+}
+
+impl fmt::Display for TangentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            TangentError::MissingTexcoords => "tangents need a texcoord for every vertex",
+            TangentError::MissingNormals => "tangents need a normal for every vertex",
+        };
+
+        write!(f, "{}", reason)
+    }
+}
+
+impl Error for TangentError {}
+
+/// A triangle mesh with one index per vertex, as renderers want it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TangentMesh {
+    pub position: Vec<f32>,
+    pub texcoord: Vec<f32>,
+    pub normal: Vec<f32>,
+    /// `x y z w` per vertex, `w` being 1 or -1 for the handedness of the bitangent
+    /// `w * cross(normal, tangent)`.
+    pub tangent: Vec<f32>,
+    /// Three per triangle.
+    pub indices: Vec<usize>,
+}
+
+/// The tangents of a triangle soup with texcoords and normals, such as
+/// `Scene::triangle_soup` makes, as `x y z w` per vertex. Vertices of an
+/// incomplete last triangle get the default `1 0 0 -1`.
+pub fn generate(group: &Group) -> Result<Vec<f32>, TangentError> {
+    let corners = group.position.len() / 3;
+
+    if group.texcoord.len() != corners * 2 {
+        return Err(TangentError::MissingTexcoords);
+    }
+
+    if group.normal.len() != corners * 3 {
+        return Err(TangentError::MissingNormals);
+    }
+
+    let spaces = Generator::new(group).run();
+
+    Ok(spaces
+        .iter()
+        .flat_map(|space| {
+            let sign = if space.orient_preserving { 1.0 } else { -1.0 };
+            [space.os[0], space.os[1], space.os[2], sign]
+        })
+        .collect())
+}
+
+/// `generate`, with corners that agree on every stream, the tangent included,
+/// welded into one vertex. Vertices on a UV seam or a mirrored UV border stay
+/// split even where their position, texcoord and normal are shared.
+pub fn generate_indexed(group: &Group) -> Result<TangentMesh, TangentError> {
+    let tangent = generate(group)?;
+    let corners = group.position.len() / 9 * 3;
+
+    let mut mesh = TangentMesh {
+        position: vec![],
+        texcoord: vec![],
+        normal: vec![],
+        tangent: vec![],
+        indices: Vec::with_capacity(corners),
+    };
+    let mut welded = HashMap::new();
+
+    for corner in 0..corners {
+        let streams = [
+            &group.position[corner * 3..corner * 3 + 3],
+            &group.texcoord[corner * 2..corner * 2 + 2],
+            &group.normal[corner * 3..corner * 3 + 3],
+            &tangent[corner * 4..corner * 4 + 4],
+        ];
+
+        // + 0.0 turns -0.0 into 0.0 so both weld together
+        let key: Vec<u32> = streams
+            .iter()
+            .flat_map(|stream| stream.iter().map(|num| (num + 0.0).to_bits()))
+            .collect();
+
+        let vertex = *welded.entry(key).or_insert_with(|| {
+            mesh.position.extend_from_slice(streams[0]);
+            mesh.texcoord.extend_from_slice(streams[1]);
+            mesh.normal.extend_from_slice(streams[2]);
+            mesh.tangent.extend_from_slice(streams[3]);
+            mesh.position.len() / 3 - 1
+        });
+
+        mesh.indices.push(vertex);
+    }
+
+    Ok(mesh)
+}
+
+type Vec3 = [f32; 3];
+
+#[derive(Clone, Copy)]
+struct Space {
+    os: Vec3,
+    orient_preserving: bool,
+}
+
+const DEFAULT_SPACE: Space = Space {
+    os: [1.0, 0.0, 0.0],
+    orient_preserving: false,
+};
+
+#[derive(Clone)]
+struct Triangle {
+    /// Across the edge from each corner to the next.
+    neighbours: [Option<usize>; 3],
+    /// The vertex group of each corner.
+    groups: [Option<usize>; 3],
+    os: Vec3,
+    ot: Vec3,
+    orient_preserving: bool,
+    /// Too small in UV space for a tangent of its own, so it joins any group.
+    any: bool,
+}
+
+/// The triangles around a vertex that share its tangent.
+struct VertexGroup {
+    vertex: usize,
+    orient_preserving: bool,
+    triangles: Vec<usize>,
+}
+
+struct Generator<'a> {
+    group: &'a Group,
+    /// Per corner, the first corner with the same position, texcoord and normal.
+    vertices: Vec<usize>,
+    triangles: Vec<Triangle>,
+    /// The triangles that are not degenerate, in order.
+    good: Vec<usize>,
+    groups: Vec<VertexGroup>,
+}
+
+impl<'a> Generator<'a> {
+    fn new(group: &'a Group) -> Self {
+        let corners = group.position.len() / 9 * 3;
+        let mut welded = HashMap::new();
+
+        let vertices = (0..corners)
+            .map(|corner| {
+                let key = [
+                    &group.position[corner * 3..corner * 3 + 3],
+                    &group.normal[corner * 3..corner * 3 + 3],
+                    &group.texcoord[corner * 2..corner * 2 + 2],
+                ]
+                .map(|stream| {
+                    stream
+                        .iter()
+                        .map(|num| (num + 0.0).to_bits())
+                        .collect::<Vec<_>>()
+                });
+
+                *welded.entry(key).or_insert(corner)
+            })
+            .collect();
+
+        let mut generator = Generator {
+            group,
+            vertices,
+            triangles: vec![],
+            good: vec![],
+            groups: vec![],
+        };
+
+        generator.good = (0..corners / 3)
+            .filter(|&t| {
+                let [p0, p1, p2] = [0, 1, 2].map(|i| generator.position(t * 3 + i));
+                p0 != p1 && p0 != p2 && p1 != p2
+            })
+            .collect();
+
+        generator
+    }
+
+    fn position(&self, corner: usize) -> Vec3 {
+        let position = &self.group.position[corner * 3..corner * 3 + 3];
+        [position[0], position[1], position[2]]
+    }
+
+    fn normal(&self, corner: usize) -> Vec3 {
+        let normal = &self.group.normal[corner * 3..corner * 3 + 3];
+        [normal[0], normal[1], normal[2]]
+    }
+
+    fn texcoord(&self, corner: usize) -> [f32; 2] {
+        [
+            self.group.texcoord[corner * 2],
+            self.group.texcoord[corner * 2 + 1],
+        ]
+    }
+
+    fn run(mut self) -> Vec<Space> {
+        self.init_triangles();
+        self.build_neighbours();
+        self.build_groups();
+
+        let mut spaces = vec![DEFAULT_SPACE; self.group.position.len() / 3];
+        self.generate_spaces(&mut spaces);
+        self.degenerate_epilogue(&mut spaces);
+
+        spaces
+    }
+
+    fn init_triangles(&mut self) {
+        let mut triangles = vec![
+            Triangle {
+                neighbours: [None; 3],
+                groups: [None; 3],
+                os: [0.0; 3],
+                ot: [0.0; 3],
+                orient_preserving: false,
+                any: true,
+            };
+            self.group.position.len() / 9
+        ];
+
+        for &t in &self.good {
+            let [v1, v2, v3] = [0, 1, 2].map(|i| self.position(t * 3 + i));
+            let [t1, t2, t3] = [0, 1, 2].map(|i| self.texcoord(t * 3 + i));
+
+            let t21x = t2[0] - t1[0];
+            let t21y = t2[1] - t1[1];
+            let t31x = t3[0] - t1[0];
+            let t31y = t3[1] - t1[1];
+            let d1 = sub(v2, v1);
+            let d2 = sub(v3, v1);
+
+            let signed_area = t21x * t31y - t21y * t31x;
+            let os = sub(scale(t31y, d1), scale(t21y, d2));
+            let ot = add(scale(-t31x, d1), scale(t21x, d2));
+
+            let triangle = &mut triangles[t];
+            triangle.orient_preserving = signed_area > 0.0;
+
+            if not_zero(signed_area) {
+                let abs_area = signed_area.abs();
+                let length_os = length(os);
+                let length_ot = length(ot);
+                let sign = if triangle.orient_preserving {
+                    1.0
+                } else {
+                    -1.0
+                };
+
+                if not_zero(length_os) {
+                    triangle.os = scale(sign / length_os, os);
+                }
+
+                if not_zero(length_ot) {
+                    triangle.ot = scale(sign / length_ot, ot);
+                }
+
+                if not_zero(length_os / abs_area) && not_zero(length_ot / abs_area) {
+                    triangle.any = false;
+                }
+            }
+        }
+
+        self.triangles = triangles;
+    }
+
+    /// Pairs each edge with the first unpaired one running the other way, by
+    /// triangle order.
+    fn build_neighbours(&mut self) {
+        let mut edges = Vec::with_capacity(self.good.len() * 3);
+
+        for &t in &self.good {
+            for i in 0..3 {
+                let (a, b) = (self.vertices[t * 3 + i], self.vertices[t * 3 + (i + 1) % 3]);
+                edges.push((a.min(b), a.max(b), t, i));
+            }
+        }
+
+        edges.sort_unstable();
+
+        for (k, &(min, max, t, i)) in edges.iter().enumerate() {
+            if self.triangles[t].neighbours[i].is_some() {
+                continue;
+            }
+
+            let from = self.vertices[t * 3 + i];
+
+            let other = edges[k + 1..]
+                .iter()
+                .take_while(|&&(other_min, other_max, _, _)| (other_min, other_max) == (min, max))
+                .find(|&&(_, _, other_t, other_i)| {
+                    self.vertices[other_t * 3 + (other_i + 1) % 3] == from
+                        && self.triangles[other_t].neighbours[other_i].is_none()
+                });
+
+            if let Some(&(_, _, other_t, other_i)) = other {
+                self.triangles[t].neighbours[i] = Some(other_t);
+                self.triangles[other_t].neighbours[other_i] = Some(t);
+            }
+        }
+    }
+
+    fn build_groups(&mut self) {
+        for index in 0..self.good.len() {
+            let t = self.good[index];
+
+            for i in 0..3 {
+                let triangle = &self.triangles[t];
+
+                if triangle.any || triangle.groups[i].is_some() {
+                    continue;
+                }
+
+                let group = self.groups.len();
+                let neighbours = [triangle.neighbours[i], triangle.neighbours[(i + 2) % 3]];
+
+                self.groups.push(VertexGroup {
+                    vertex: self.vertices[t * 3 + i],
+                    orient_preserving: triangle.orient_preserving,
+                    triangles: vec![t],
+                });
+                self.triangles[t].groups[i] = Some(group);
+
+                self.assign(neighbours, group);
+            }
+        }
+    }
+
+    /// Adds `neighbours` to `group` and carries on around the vertex, depth first
+    /// like the recursion of MikkTSpace, skipping triangles that already have a
+    /// group there or wind their UVs the other way. The stack is explicit because
+    /// a vertex can have any number of triangles.
+    fn assign(&mut self, neighbours: [Option<usize>; 2], group: usize) {
+        let vertex = self.groups[group].vertex;
+        let orient_preserving = self.groups[group].orient_preserving;
+        let mut stack: Vec<usize> = neighbours.iter().rev().flatten().copied().collect();
+
+        while let Some(t) = stack.pop() {
+            let i = self.corner_of(t, vertex);
+            let triangle = &mut self.triangles[t];
+
+            if triangle.groups[i].is_some() {
+                continue;
+            }
+
+            // The first group to reach a triangle without a tangent decides its
+            // winding, the only order dependency of MikkTSpace
+            if triangle.any && triangle.groups.iter().all(Option::is_none) {
+                triangle.orient_preserving = orient_preserving;
+            }
+
+            if triangle.orient_preserving != orient_preserving {
+                continue;
+            }
+
+            triangle.groups[i] = Some(group);
+            stack.extend(
+                [triangle.neighbours[(i + 2) % 3], triangle.neighbours[i]]
+                    .iter()
+                    .flatten(),
+            );
+            self.groups[group].triangles.push(t);
+        }
+    }
+
+    fn corner_of(&self, t: usize, vertex: usize) -> usize {
+        (0..3)
+            .find(|&i| self.vertices[t * 3 + i] == vertex)
+            .expect("the triangle has the vertex")
+    }
+
+    /// Every triangle of a group averages the tangents of the others, leaving out
+    /// only those with exactly opposite tangents. The group is summed once, and
+    /// the opposite ones, found through a grid of directions, are subtracted.
+    fn generate_spaces(&self, spaces: &mut [Space]) {
+        for group in &self.groups {
+            let mut members = group.triangles.clone();
+            members.sort_unstable();
+
+            // The corners of a vertex share their normal
+            let first = members[0];
+            let normal = self.normal(first * 3 + self.corner_of(first, group.vertex));
+            let projected: Vec<_> = members.iter().map(|&t| self.projected(t, normal)).collect();
+            let weighted: Vec<_> = members
+                .iter()
+                .zip(&projected)
+                .map(|(&t, &(os, _))| self.weighted(t, group.vertex, os))
+                .collect();
+
+            // Small groups, like most vertices, are cheaper to compare pairwise
+            let grids = (members.len() > SMALL_GROUP).then(|| {
+                let mut grids: [HashMap<[i32; 3], Vec<usize>>; 2] = Default::default();
+
+                for (member, &(os, ot)) in projected.iter().enumerate() {
+                    if self.triangles[members[member]].any {
+                        continue;
+                    }
+
+                    for (grid, v) in grids.iter_mut().zip([os, ot]) {
+                        if v != [0.0; 3] {
+                            grid.entry(cell(v)).or_default().push(member);
+                        }
+                    }
+                }
+
+                grids
+            });
+
+            // In the order of MikkTSpace, so that a group without opposite
+            // tangents gets the same bits
+            let sum = weighted.iter().fold([0.0; 3], |sum, &os| add(sum, os));
+            let mut opposite = vec![];
+
+            for (member, &t) in members.iter().enumerate() {
+                let (os, ot) = projected[member];
+                opposite.clear();
+
+                if !self.triangles[t].any {
+                    match &grids {
+                        Some(grids) => {
+                            for (grid, v) in grids.iter().zip([os, ot]) {
+                                if v != [0.0; 3] {
+                                    opposite.extend(nearby(grid, scale(-1.0, v)));
+                                }
+                            }
+                        }
+                        None => opposite.extend(
+                            (0..members.len()).filter(|&other| !self.triangles[members[other]].any),
+                        ),
+                    }
+
+                    opposite.retain(|&other| {
+                        let (other_os, other_ot) = projected[other];
+                        other != member && (dot(os, other_os) <= -1.0 || dot(ot, other_ot) <= -1.0)
+                    });
+                    opposite.sort_unstable();
+                    opposite.dedup();
+                }
+
+                let os = opposite
+                    .iter()
+                    .fold(sum, |sum, &other| sub(sum, weighted[other]));
+
+                let i = self.corner_of(t, group.vertex);
+                spaces[t * 3 + i] = Space {
+                    os: normalize_non_zero(os),
+                    orient_preserving: group.orient_preserving,
+                };
+            }
+        }
+    }
+
+    /// The tangent and bitangent of triangle `t` in the plane of `normal`.
+    fn projected(&self, t: usize, normal: Vec3) -> (Vec3, Vec3) {
+        let triangle = &self.triangles[t];
+
+        (
+            normalize_non_zero(project(triangle.os, normal)),
+            normalize_non_zero(project(triangle.ot, normal)),
+        )
+    }
+
+    /// The projected tangent `os` of `t`, weighted by its angle at `vertex`, or
+    /// zero for a triangle without a tangent.
+    fn weighted(&self, t: usize, vertex: usize, os: Vec3) -> Vec3 {
+        if self.triangles[t].any {
+            return [0.0; 3];
+        }
+
+        let i = self.corner_of(t, vertex);
+        let normal = self.normal(t * 3 + i);
+        let p0 = self.position(t * 3 + (i + 2) % 3);
+        let p1 = self.position(t * 3 + i);
+        let p2 = self.position(t * 3 + (i + 1) % 3);
+        let v1 = normalize_non_zero(project(sub(p0, p1), normal));
+        let v2 = normalize_non_zero(project(sub(p2, p1), normal));
+        let cos = dot(v1, v2).clamp(-1.0, 1.0);
+        let angle = f64::from(cos).acos() as f32;
+
+        scale(angle, os)
+    }
+
+    /// Degenerate triangles take the space of the first good corner of the same
+    /// vertex, if there is one.
+    fn degenerate_epilogue(&self, spaces: &mut [Space]) {
+        let mut first = HashMap::new();
+
+        for &t in &self.good {
+            for corner in t * 3..t * 3 + 3 {
+                first.entry(self.vertices[corner]).or_insert(corner);
+            }
+        }
+
+        for corner in 0..self.triangles.len() * 3 {
+            if self.good.binary_search(&(corner / 3)).is_ok() {
+                continue;
+            }
+
+            if let Some(&source) = first.get(&self.vertices[corner]) {
+                spaces[corner] = spaces[source];
+            }
+        }
+    }
+}
+
+/// Groups up to this size look for opposite tangents without a grid.
+const SMALL_GROUP: usize = 16;
+
+/// Two normalized vectors with a dot product of -1 are within this distance of
+/// each other after negating one.
+const OPPOSITE_DISTANCE: f32 = 1e-3;
+
+/// Cells of a direction grid, at least twice `OPPOSITE_DISTANCE` wide.
+const CELLS_PER_UNIT: f32 = 256.0;
+
+fn cell(v: Vec3) -> [i32; 3] {
+    v.map(|num| (num * CELLS_PER_UNIT).floor() as i32)
+}
+
+/// The entries of `grid` in the cells within `OPPOSITE_DISTANCE` of `v`, usually
+/// just its own.
+fn nearby<'a>(
+    grid: &'a HashMap<[i32; 3], Vec<usize>>,
+    v: Vec3,
+) -> impl Iterator<Item = usize> + 'a {
+    let low = cell(v.map(|num| num - OPPOSITE_DISTANCE));
+    let high = cell(v.map(|num| num + OPPOSITE_DISTANCE));
+
+    (low[0]..=high[0])
+        .flat_map(move |x| (low[1]..=high[1]).map(move |y| (x, y)))
+        .flat_map(move |(x, y)| (low[2]..=high[2]).map(move |z| [x, y, z]))
+        .filter_map(move |key| grid.get(&key))
+        .flatten()
+        .copied()
+}
+
+/// As `NotZero` in MikkTSpace.
+fn not_zero(num: f32) -> bool {
+    num.abs() > f32::MIN_POSITIVE
+}
+
+fn normalize_non_zero(v: Vec3) -> Vec3 {
+    if v.iter().any(|&num| not_zero(num)) {
+        scale(1.0 / length(v), v)
+    } else {
+        v
+    }
+}
+
+fn project(v: Vec3, normal: Vec3) -> Vec3 {
+    sub(v, scale(dot(normal, v), normal))
+}
+
+fn add(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn sub(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn scale(s: f32, v: Vec3) -> Vec3 {
+    [s * v[0], s * v[1], s * v[2]]
+}
+
+fn dot(a: Vec3, b: Vec3) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn length(v: Vec3) -> f32 {
+    dot(v, v).sqrt()
+}
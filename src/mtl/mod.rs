@@ -0,0 +1,408 @@
+use std::error::Error;
+use std::fmt;
+
+pub mod writer;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Material {
+    pub name: String,
+    /// `Ka`
+    pub ambient: Option<Color>,
+    /// `Kd`
+    pub diffuse: Option<Color>,
+    /// `Ks`
+    pub specular: Option<Color>,
+    /// `Ke`
+    pub emissive: Option<Color>,
+    /// `Tf`
+    pub transmission_filter: Option<Color>,
+    /// `Ns`
+    pub shininess: Option<f32>,
+    /// `d`, `Tr` is read as `1 - Tr`
+    pub dissolve: Option<Dissolve>,
+    /// `Ni`
+    pub optical_density: Option<f32>,
+    pub sharpness: Option<u32>,
+    /// `illum`
+    pub illumination_model: Option<u32>,
+    /// `Pr`
+    pub roughness: Option<f32>,
+    /// `Pm`
+    pub metallic: Option<f32>,
+    /// `Ps`
+    pub sheen: Option<f32>,
+    /// `Pc`
+    pub clearcoat_thickness: Option<f32>,
+    /// `Pcr`
+    pub clearcoat_roughness: Option<f32>,
+    /// `aniso`
+    pub anisotropy: Option<f32>,
+    /// `anisor`
+    pub anisotropy_rotation: Option<f32>,
+    pub maps: Vec<TextureMap>,
+    /// Statements this model does not know about, kept verbatim.
+    pub unknown: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Color {
+    Rgb([f32; 3]),
+    Xyz([f32; 3]),
+    Spectral { file: String, factor: f32 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Dissolve {
+    pub factor: f32,
+    pub halo: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MapKind {
+    Ambient,
+    Diffuse,
+    Specular,
+    Emissive,
+    Shininess,
+    Dissolve,
+    Bump,
+    Displacement,
+    Decal,
+    Reflection,
+    Roughness,
+    Metallic,
+    Sheen,
+    Normal,
+}
+
+impl MapKind {
+    pub fn keyword(self) -> &'static str {
+        match self {
+            MapKind::Ambient => "map_Ka",
+            MapKind::Diffuse => "map_Kd",
+            MapKind::Specular => "map_Ks",
+            MapKind::Emissive => "map_Ke",
+            MapKind::Shininess => "map_Ns",
+            MapKind::Dissolve => "map_d",
+            MapKind::Bump => "map_bump",
+            MapKind::Displacement => "disp",
+            MapKind::Decal => "decal",
+            MapKind::Reflection => "refl",
+            MapKind::Roughness => "map_Pr",
+            MapKind::Metallic => "map_Pm",
+            MapKind::Sheen => "map_Ps",
+            MapKind::Normal => "norm",
+        }
+    }
+
+    fn from_keyword(keyword: &str) -> Option<Self> {
+        let kind = match keyword {
+            "map_Ka" => MapKind::Ambient,
+            "map_Kd" => MapKind::Diffuse,
+            "map_Ks" => MapKind::Specular,
+            "map_Ke" => MapKind::Emissive,
+            "map_Ns" => MapKind::Shininess,
+            "map_d" => MapKind::Dissolve,
+            "map_bump" | "map_Bump" | "bump" => MapKind::Bump,
+            "disp" => MapKind::Displacement,
+            "decal" => MapKind::Decal,
+            "refl" => MapKind::Reflection,
+            "map_Pr" => MapKind::Roughness,
+            "map_Pm" => MapKind::Metallic,
+            "map_Ps" => MapKind::Sheen,
+            "norm" => MapKind::Normal,
+            _ => return None,
+        };
+
+        Some(kind)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextureMap {
+    pub kind: MapKind,
+    pub path: String,
+    pub options: TextureOptions,
+}
+
+/// The `-option` arguments of a texture map statement. `None` means the option
+/// was not given. `offset`, `scale` and `turbulence` hold one to three values.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextureOptions {
+    pub blend_u: Option<bool>,
+    pub blend_v: Option<bool>,
+    pub bump_multiplier: Option<f32>,
+    pub boost: Option<f32>,
+    pub color_correction: Option<bool>,
+    pub clamp: Option<bool>,
+    pub channel: Option<String>,
+    /// `-mm base gain`
+    pub range: Option<(f32, f32)>,
+    pub offset: Option<Vec<f32>>,
+    pub scale: Option<Vec<f32>>,
+    pub turbulence: Option<Vec<f32>>,
+    pub resolution: Option<u32>,
+    /// `-type`, only meaningful for `refl`
+    pub projection: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum MtlErrorKind {
+    InvalidNumber(String),
+    /// A texture option value other than `on` or `off`.
+    InvalidArgument(String),
+    MissingArgument,
+    StatementOutsideMaterial,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct MtlError {
+    pub line: usize,
+    pub kind: MtlErrorKind,
+}
+
+impl fmt::Display for MtlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+
+        match &self.kind {
+            MtlErrorKind::InvalidNumber(num) => write!(f, "invalid number `{}`", num),
+            MtlErrorKind::InvalidArgument(value) => write!(f, "invalid argument `{}`", value),
+            MtlErrorKind::MissingArgument => write!(f, "missing argument"),
+            MtlErrorKind::StatementOutsideMaterial => {
+                write!(f, "statement before the first newmtl")
+            }
+        }
+    }
+}
+
+impl Error for MtlError {}
+
+/// Parses the materials of `mtl_file`. Malformed statements are an `MtlError`,
+/// never a panic.
+pub fn parse(mtl_file: &str) -> Result<Vec<Material>, MtlError> {
+    let mut materials: Vec<Material> = vec![];
+
+    for (number, line) in mtl_file.lines().enumerate() {
+        let error = |kind| MtlError {
+            line: number + 1,
+            kind,
+        };
+
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+
+        if keyword == "newmtl" {
+            materials.push(Material {
+                name: rest.to_string(),
+                ..Material::default()
+            });
+            continue;
+        }
+
+        let material = materials
+            .last_mut()
+            .ok_or_else(|| error(MtlErrorKind::StatementOutsideMaterial))?;
+
+        parse_statement(material, keyword, rest, line).map_err(error)?;
+    }
+
+    Ok(materials)
+}
+
+fn parse_statement(
+    material: &mut Material,
+    keyword: &str,
+    rest: &str,
+    line: &str,
+) -> Result<(), MtlErrorKind> {
+    let mut parts = rest.split_whitespace();
+
+    match keyword {
+        "Ka" => material.ambient = Some(parse_color(parts)?),
+        "Kd" => material.diffuse = Some(parse_color(parts)?),
+        "Ks" => material.specular = Some(parse_color(parts)?),
+        "Ke" => material.emissive = Some(parse_color(parts)?),
+        "Tf" => material.transmission_filter = Some(parse_color(parts)?),
+        "Ns" => material.shininess = Some(parse_next(&mut parts)?),
+        "Ni" => material.optical_density = Some(parse_next(&mut parts)?),
+        "sharpness" => material.sharpness = Some(parse_next(&mut parts)?),
+        "illum" => material.illumination_model = Some(parse_next(&mut parts)?),
+        "Pr" => material.roughness = Some(parse_next(&mut parts)?),
+        "Pm" => material.metallic = Some(parse_next(&mut parts)?),
+        "Ps" => material.sheen = Some(parse_next(&mut parts)?),
+        "Pc" => material.clearcoat_thickness = Some(parse_next(&mut parts)?),
+        "Pcr" => material.clearcoat_roughness = Some(parse_next(&mut parts)?),
+        "aniso" => material.anisotropy = Some(parse_next(&mut parts)?),
+        "anisor" => material.anisotropy_rotation = Some(parse_next(&mut parts)?),
+        "d" => {
+            let mut halo = false;
+            let mut factor = parts.next().ok_or(MtlErrorKind::MissingArgument)?;
+
+            if factor == "-halo" {
+                halo = true;
+                factor = parts.next().ok_or(MtlErrorKind::MissingArgument)?;
+            }
+
+            material.dissolve = Some(Dissolve {
+                factor: parse_num(factor)?,
+                halo,
+            });
+        }
+        "Tr" => {
+            material.dissolve = Some(Dissolve {
+                factor: 1.0 - parse_next::<f32>(&mut parts)?,
+                halo: false,
+            });
+        }
+        _ => match MapKind::from_keyword(keyword) {
+            Some(kind) => material.maps.push(parse_map(kind, rest)?),
+            None => material.unknown.push(line.to_string()),
+        },
+    }
+
+    Ok(())
+}
+
+fn parse_num<T: std::str::FromStr>(num: &str) -> Result<T, MtlErrorKind> {
+    num.parse()
+        .map_err(|_| MtlErrorKind::InvalidNumber(num.to_string()))
+}
+
+fn parse_next<'a, T: std::str::FromStr>(
+    parts: &mut impl Iterator<Item = &'a str>,
+) -> Result<T, MtlErrorKind> {
+    parse_num(parts.next().ok_or(MtlErrorKind::MissingArgument)?)
+}
+
+/// `r [g b]`, `xyz x [y z]` or `spectral file.rfl [factor]`. A single value is
+/// used for all three components.
+fn parse_color<'a>(mut parts: impl Iterator<Item = &'a str>) -> Result<Color, MtlErrorKind> {
+    let first = parts.next().ok_or(MtlErrorKind::MissingArgument)?;
+
+    match first {
+        "spectral" => {
+            let file = parts.next().ok_or(MtlErrorKind::MissingArgument)?;
+            let factor = match parts.next() {
+                Some(factor) => parse_num(factor)?,
+                None => 1.0,
+            };
+
+            Ok(Color::Spectral {
+                file: file.to_string(),
+                factor,
+            })
+        }
+        "xyz" => {
+            let x = parts.next().ok_or(MtlErrorKind::MissingArgument)?;
+            Ok(Color::Xyz(parse_triple(x, parts)?))
+        }
+        r => Ok(Color::Rgb(parse_triple(r, parts)?)),
+    }
+}
+
+fn parse_triple<'a>(
+    first: &str,
+    mut parts: impl Iterator<Item = &'a str>,
+) -> Result<[f32; 3], MtlErrorKind> {
+    let first = parse_num(first)?;
+
+    match parts.next() {
+        Some(second) => Ok([first, parse_num(second)?, parse_next(&mut parts)?]),
+        None => Ok([first; 3]),
+    }
+}
+
+fn parse_map(kind: MapKind, rest: &str) -> Result<TextureMap, MtlErrorKind> {
+    let mut options = TextureOptions::default();
+    let mut words = Words {
+        words: rest.split_whitespace().collect(),
+        position: 0,
+    };
+
+    while let Some(option) = words.peek().filter(|word| word.starts_with('-')) {
+        words.position += 1;
+
+        match option {
+            "-blendu" => options.blend_u = Some(parse_on_off(words.next()?)?),
+            "-blendv" => options.blend_v = Some(parse_on_off(words.next()?)?),
+            "-cc" => options.color_correction = Some(parse_on_off(words.next()?)?),
+            "-clamp" => options.clamp = Some(parse_on_off(words.next()?)?),
+            "-bm" => options.bump_multiplier = Some(parse_num(words.next()?)?),
+            "-boost" => options.boost = Some(parse_num(words.next()?)?),
+            "-texres" => options.resolution = Some(parse_num(words.next()?)?),
+            "-imfchan" => options.channel = Some(words.next()?.to_string()),
+            "-type" => options.projection = Some(words.next()?.to_string()),
+            "-mm" => options.range = Some((parse_num(words.next()?)?, parse_num(words.next()?)?)),
+            "-o" | "-s" | "-t" => {
+                let mut values = vec![parse_num(words.next()?)?];
+
+                // Up to two more values, as long as they are numbers and not the path
+                while values.len() < 3 && words.remaining() > 1 {
+                    match words.peek().and_then(|num| num.parse().ok()) {
+                        Some(num) => {
+                            values.push(num);
+                            words.position += 1;
+                        }
+                        None => break,
+                    }
+                }
+
+                match option {
+                    "-o" => options.offset = Some(values),
+                    "-s" => options.scale = Some(values),
+                    _ => options.turbulence = Some(values),
+                }
+            }
+            _ => {
+                words.position -= 1;
+                break;
+            }
+        }
+    }
+
+    if words.remaining() == 0 {
+        return Err(MtlErrorKind::MissingArgument);
+    }
+
+    Ok(TextureMap {
+        kind,
+        path: words.words[words.position..].join(" "),
+        options,
+    })
+}
+
+struct Words<'a> {
+    words: Vec<&'a str>,
+    position: usize,
+}
+
+impl<'a> Words<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.words.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Result<&'a str, MtlErrorKind> {
+        let word = self.peek().ok_or(MtlErrorKind::MissingArgument)?;
+        self.position += 1;
+        Ok(word)
+    }
+
+    fn remaining(&self) -> usize {
+        self.words.len() - self.position
+    }
+}
+
+fn parse_on_off(value: &str) -> Result<bool, MtlErrorKind> {
+    match value {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(MtlErrorKind::InvalidArgument(value.to_string())),
+    }
+}
@@ -0,0 +1,309 @@
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+
+use super::{Color, Material, TextureMap, TextureOptions};
+use crate::obj::writer::{self, FloatFormat};
+
+/// Rewrites texture paths that were relative to `source_dir` so that they are
+/// relative to `output_dir`, where the `.mtl` file is going to be saved.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PathRewrite {
+    pub source_dir: PathBuf,
+    pub output_dir: PathBuf,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WriteOptions {
+    pub float_format: FloatFormat,
+    pub texture_paths: Option<PathRewrite>,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        WriteOptions {
+            float_format: FloatFormat::Shortest,
+            texture_paths: None,
+        }
+    }
+}
+
+/// Writes `materials` as MTL text. With `FloatFormat::Shortest`, parsing the
+/// output with `mtl::parse` gives back the materials, except that `-o`, `-s` and
+/// `-t` right before a texture path starting with a number get all three values.
+pub fn write<W: Write>(
+    materials: &[Material],
+    writer: W,
+    options: &WriteOptions,
+) -> io::Result<()> {
+    let mut writer = Writer {
+        writer: io::BufWriter::new(writer),
+        float_format: options.float_format,
+    };
+
+    for (i, material) in materials.iter().enumerate() {
+        if i > 0 {
+            writer.writer.write_all(b"\n")?;
+        }
+
+        writer.write_material(material, options.texture_paths.as_ref())?;
+    }
+
+    writer.writer.flush()
+}
+
+pub fn to_string(materials: &[Material], options: &WriteOptions) -> String {
+    let mut buffer = Vec::new();
+
+    write(materials, &mut buffer, options).expect("writing to a Vec cannot fail");
+
+    String::from_utf8(buffer).expect("the writer only emits UTF-8")
+}
+
+/// `path` relative to `rewrite.output_dir`, with `/` separators. Absolute paths
+/// are kept as they are. Both directories should be absolute, or relative to the
+/// same working directory.
+pub fn rewrite_path(path: &str, rewrite: &PathRewrite) -> String {
+    if Path::new(path).is_absolute() {
+        return path.to_string();
+    }
+
+    let target = normalize(&rewrite.source_dir.join(path.replace('\\', "/")));
+    let base = normalize(&rewrite.output_dir);
+
+    let common = target
+        .components()
+        .zip(base.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut relative = PathBuf::new();
+
+    for _ in base.components().skip(common) {
+        relative.push("..");
+    }
+
+    for component in target.components().skip(common) {
+        relative.push(component);
+    }
+
+    to_slashes(&relative)
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if normalized.file_name().is_some() => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+fn to_slashes(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+struct Writer<W: Write> {
+    writer: W,
+    float_format: FloatFormat,
+}
+
+impl<W: Write> Writer<W> {
+    fn write_material(
+        &mut self,
+        material: &Material,
+        texture_paths: Option<&PathRewrite>,
+    ) -> io::Result<()> {
+        writeln!(self.writer, "newmtl {}", material.name)?;
+
+        for (keyword, color) in [
+            ("Ka", &material.ambient),
+            ("Kd", &material.diffuse),
+            ("Ks", &material.specular),
+            ("Ke", &material.emissive),
+            ("Tf", &material.transmission_filter),
+        ] {
+            if let Some(color) = color {
+                self.write_color(keyword, color)?;
+            }
+        }
+
+        for (keyword, value) in [
+            ("Ns", material.shininess),
+            ("Ni", material.optical_density),
+            ("Pr", material.roughness),
+            ("Pm", material.metallic),
+            ("Ps", material.sheen),
+            ("Pc", material.clearcoat_thickness),
+            ("Pcr", material.clearcoat_roughness),
+            ("aniso", material.anisotropy),
+            ("anisor", material.anisotropy_rotation),
+        ] {
+            if let Some(value) = value {
+                write!(self.writer, "{} ", keyword)?;
+                self.write_float(value)?;
+                self.writer.write_all(b"\n")?;
+            }
+        }
+
+        if let Some(dissolve) = material.dissolve {
+            self.writer.write_all(b"d ")?;
+
+            if dissolve.halo {
+                self.writer.write_all(b"-halo ")?;
+            }
+
+            self.write_float(dissolve.factor)?;
+            self.writer.write_all(b"\n")?;
+        }
+
+        if let Some(sharpness) = material.sharpness {
+            writeln!(self.writer, "sharpness {}", sharpness)?;
+        }
+
+        if let Some(illum) = material.illumination_model {
+            writeln!(self.writer, "illum {}", illum)?;
+        }
+
+        for map in &material.maps {
+            self.write_map(map, texture_paths)?;
+        }
+
+        for line in &material.unknown {
+            writeln!(self.writer, "{}", line)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_float(&mut self, num: f32) -> io::Result<()> {
+        writer::write_float(&mut self.writer, num, self.float_format)
+    }
+
+    fn write_floats(&mut self, nums: &[f32]) -> io::Result<()> {
+        for (i, &num) in nums.iter().enumerate() {
+            if i > 0 {
+                self.writer.write_all(b" ")?;
+            }
+
+            self.write_float(num)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_color(&mut self, keyword: &str, color: &Color) -> io::Result<()> {
+        write!(self.writer, "{} ", keyword)?;
+
+        match color {
+            Color::Rgb(rgb) => self.write_floats(rgb)?,
+            Color::Xyz(xyz) => {
+                self.writer.write_all(b"xyz ")?;
+                self.write_floats(xyz)?;
+            }
+            Color::Spectral { file, factor } => {
+                write!(self.writer, "spectral {} ", file)?;
+                self.write_float(*factor)?;
+            }
+        }
+
+        self.writer.write_all(b"\n")
+    }
+
+    fn write_map(
+        &mut self,
+        map: &TextureMap,
+        texture_paths: Option<&PathRewrite>,
+    ) -> io::Result<()> {
+        let path = match texture_paths {
+            Some(rewrite) => rewrite_path(&map.path, rewrite),
+            None => map.path.clone(),
+        };
+
+        self.writer.write_all(map.kind.keyword().as_bytes())?;
+        self.write_options(&map.options, &path)?;
+
+        writeln!(self.writer, " {}", path)
+    }
+
+    fn write_options(&mut self, options: &TextureOptions, path: &str) -> io::Result<()> {
+        for (option, value) in [
+            ("-blendu", options.blend_u),
+            ("-blendv", options.blend_v),
+            ("-cc", options.color_correction),
+            ("-clamp", options.clamp),
+        ] {
+            if let Some(value) = value {
+                let value = if value { "on" } else { "off" };
+                write!(self.writer, " {} {}", option, value)?;
+            }
+        }
+
+        for (option, value) in [("-bm", options.bump_multiplier), ("-boost", options.boost)] {
+            if let Some(value) = value {
+                write!(self.writer, " {} ", option)?;
+                self.write_float(value)?;
+            }
+        }
+
+        if let Some((base, gain)) = options.range {
+            self.writer.write_all(b" -mm ")?;
+            self.write_floats(&[base, gain])?;
+        }
+
+        let vectors = [
+            ("-o", &options.offset, 0.0),
+            ("-s", &options.scale, 1.0),
+            ("-t", &options.turbulence, 0.0),
+        ];
+
+        // These take up to three values, so a path starting with a number right
+        // after one of them would be read as another value. All three values with
+        // the defaults of the spec leave no room for it.
+        let followed = options.resolution.is_some()
+            || options.channel.is_some()
+            || options.projection.is_some();
+        let path_is_number = path
+            .split_whitespace()
+            .next()
+            .is_some_and(|word| word.parse::<f32>().is_ok());
+        let last = vectors.iter().rposition(|(_, values, _)| values.is_some());
+
+        for (i, &(option, values, default)) in vectors.iter().enumerate() {
+            if let Some(values) = values {
+                write!(self.writer, " {} ", option)?;
+
+                if path_is_number && !followed && Some(i) == last && values.len() < 3 {
+                    let mut padded = values.clone();
+                    padded.resize(3, default);
+                    self.write_floats(&padded)?;
+                } else {
+                    self.write_floats(values)?;
+                }
+            }
+        }
+
+        if let Some(resolution) = options.resolution {
+            write!(self.writer, " -texres {}", resolution)?;
+        }
+
+        if let Some(channel) = &options.channel {
+            write!(self.writer, " -imfchan {}", channel)?;
+        }
+
+        if let Some(projection) = &options.projection {
+            write!(self.writer, " -type {}", projection)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,394 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use crate::parser::VertexData;
+use crate::triangulate::{triangulate, Triangulation};
+
+pub mod writer;
+
+/// An indexed OBJ scene. Unlike the triangle soups produced by
+/// `parser::parse_obj_threaded`, faces keep their polygons and index form.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Scene {
+    pub material_libraries: Vec<String>,
+    pub position: Vec<f32>,
+    pub texcoord: Vec<f32>,
+    pub normal: Vec<f32>,
+    /// RGB per position from `v x y z r g b`, empty if no vertex has a colour.
+    pub color: Vec<f32>,
+    /// Named per-position streams from formats such as PLY. OBJ cannot store them.
+    pub attributes: Vec<VertexAttribute>,
+    pub faces: Vec<Face>,
+    /// Runs of faces share a state, `Face::state` indexes into this.
+    pub states: Vec<FaceState>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VertexAttribute {
+    pub name: String,
+    /// One value per position.
+    pub values: Vec<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Face {
+    pub vertices: Vec<FaceVertex>,
+    pub state: usize,
+}
+
+/// Zero based indices. The presence of `texcoord` and `normal` is the index form,
+/// i.e. `v`, `v/vt`, `v//vn` or `v/vt/vn`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FaceVertex {
+    pub position: usize,
+    pub texcoord: Option<usize>,
+    pub normal: Option<usize>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FaceState {
+    pub object: Option<String>,
+    pub groups: Vec<String>,
+    pub material: Option<String>,
+    /// 0 means smoothing is off.
+    pub smoothing_group: u32,
+}
+
+impl Scene {
+    pub fn position_count(&self) -> usize {
+        self.position.len() / 3
+    }
+
+    pub fn texcoord_count(&self) -> usize {
+        self.texcoord.len() / 2
+    }
+
+    pub fn normal_count(&self) -> usize {
+        self.normal.len() / 3
+    }
+
+    /// Fan triangulates `faces` into a triangle soup, as `parser::parse_obj_threaded`
+    /// does. Texcoords and normals are only included if every vertex has them.
+    pub fn triangle_soup<'a, I>(&self, faces: I) -> VertexData
+    where
+        I: IntoIterator<Item = &'a Face>,
+        I::IntoIter: Clone,
+    {
+        self.triangle_soup_with(faces, Triangulation::Fan)
+    }
+
+    /// `triangle_soup` with a choice of how polygons are split into triangles.
+    pub fn triangle_soup_with<'a, I>(&self, faces: I, triangulation: Triangulation) -> VertexData
+    where
+        I: IntoIterator<Item = &'a Face>,
+        I::IntoIter: Clone,
+    {
+        let faces = faces.into_iter();
+
+        let vertices = || faces.clone().flat_map(|face| &face.vertices);
+        let has_texcoord = vertices().all(|vertex| vertex.texcoord.is_some());
+        let has_normal = vertices().all(|vertex| vertex.normal.is_some());
+
+        let triangles: usize = faces
+            .clone()
+            .map(|face| face.vertices.len().saturating_sub(2))
+            .sum();
+
+        let mut soup = VertexData {
+            position: Vec::with_capacity(triangles * 9),
+            texcoord: Vec::with_capacity(if has_texcoord { triangles * 6 } else { 0 }),
+            normal: Vec::with_capacity(if has_normal { triangles * 9 } else { 0 }),
+        };
+
+        let mut polygon = vec![];
+
+        for face in faces {
+            polygon.clear();
+            polygon.extend(face.vertices.iter().map(|vertex| {
+                let position = vertex.position * 3;
+                [0, 1, 2].map(|i| self.position[position + i])
+            }));
+
+            for triangle in triangulate(&polygon, triangulation) {
+                for vertex in triangle.map(|corner| &face.vertices[corner]) {
+                    let position = vertex.position * 3;
+                    soup.position
+                        .extend_from_slice(&self.position[position..position + 3]);
+
+                    if let (true, Some(texcoord)) = (has_texcoord, vertex.texcoord) {
+                        soup.texcoord
+                            .extend_from_slice(&self.texcoord[texcoord * 2..texcoord * 2 + 2]);
+                    }
+
+                    if let (true, Some(normal)) = (has_normal, vertex.normal) {
+                        soup.normal
+                            .extend_from_slice(&self.normal[normal * 3..normal * 3 + 3]);
+                    }
+                }
+            }
+        }
+
+        soup
+    }
+}
+
+/// Scene vertices for formats with one index per vertex, such as PLY and OFF. Each
+/// distinct position, texcoord and normal combination used by a face becomes a
+/// corner, unless the streams are already aligned with the positions.
+pub(crate) struct SingleIndexed {
+    pub corners: Vec<FaceVertex>,
+    pub faces: Vec<Vec<usize>>,
+    pub has_normal: bool,
+    pub has_texcoord: bool,
+    pub has_color: bool,
+}
+
+impl SingleIndexed {
+    pub fn new(scene: &Scene) -> Self {
+        let face_vertices = || scene.faces.iter().flat_map(|face| &face.vertices);
+
+        let has_normal = scene.normal_count() > 0 && face_vertices().all(|v| v.normal.is_some());
+        let has_texcoord =
+            scene.texcoord_count() > 0 && face_vertices().all(|v| v.texcoord.is_some());
+        let has_color = scene.color.len() == scene.position.len() && !scene.color.is_empty();
+
+        let aligned = face_vertices().all(|vertex| {
+            (!has_normal || vertex.normal == Some(vertex.position))
+                && (!has_texcoord || vertex.texcoord == Some(vertex.position))
+        }) && (!has_normal || scene.normal_count() == scene.position_count())
+            && (!has_texcoord || scene.texcoord_count() == scene.position_count());
+
+        let key = |vertex: &FaceVertex| FaceVertex {
+            position: vertex.position,
+            texcoord: vertex.texcoord.filter(|_| has_texcoord),
+            normal: vertex.normal.filter(|_| has_normal),
+        };
+
+        if aligned {
+            let corners = (0..scene.position_count())
+                .map(|position| FaceVertex {
+                    position,
+                    texcoord: if has_texcoord { Some(position) } else { None },
+                    normal: if has_normal { Some(position) } else { None },
+                })
+                .collect();
+
+            let faces = scene
+                .faces
+                .iter()
+                .map(|face| face.vertices.iter().map(|vertex| vertex.position).collect())
+                .collect();
+
+            return SingleIndexed {
+                corners,
+                faces,
+                has_normal,
+                has_texcoord,
+                has_color,
+            };
+        }
+
+        let mut corners = vec![];
+        let mut indices = HashMap::new();
+
+        let faces = scene
+            .faces
+            .iter()
+            .map(|face| {
+                face.vertices
+                    .iter()
+                    .map(|vertex| {
+                        *indices.entry(key(vertex)).or_insert_with(|| {
+                            corners.push(key(vertex));
+                            corners.len() - 1
+                        })
+                    })
+                    .collect()
+            })
+            .collect();
+
+        SingleIndexed {
+            corners,
+            faces,
+            has_normal,
+            has_texcoord,
+            has_color,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ObjErrorKind {
+    InvalidNumber(String),
+    InvalidIndex(String),
+    IndexOutOfRange(isize),
+    TooFewVertices,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ObjError {
+    pub line: usize,
+    pub kind: ObjErrorKind,
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+
+        match &self.kind {
+            ObjErrorKind::InvalidNumber(num) => write!(f, "invalid number `{}`", num),
+            ObjErrorKind::InvalidIndex(index) => write!(f, "invalid face vertex `{}`", index),
+            ObjErrorKind::IndexOutOfRange(index) => write!(f, "index {} is out of range", index),
+            ObjErrorKind::TooFewVertices => write!(f, "face has fewer than 3 vertices"),
+        }
+    }
+}
+
+impl Error for ObjError {}
+
+/// Parses `obj_file` into a `Scene` on the calling thread. Statements other than
+/// vertex data, faces, `o`, `g`, `usemtl`, `mtllib` and `s` are ignored.
+pub fn parse(obj_file: &str) -> Result<Scene, ObjError> {
+    let mut scene = Scene::default();
+    let mut state = FaceState::default();
+
+    for (number, line) in obj_file.lines().enumerate() {
+        let error = |kind| ObjError {
+            line: number + 1,
+            kind,
+        };
+
+        let mut parts = line.split_whitespace();
+
+        match parts.next() {
+            Some("v") => push_position(&mut scene, parts).map_err(error)?,
+            Some("vt") => push_floats(&mut scene.texcoord, parts, 2).map_err(error)?,
+            Some("vn") => push_floats(&mut scene.normal, parts, 3).map_err(error)?,
+            Some("f") => {
+                let vertices = parts
+                    .map(|vertex| parse_face_vertex(vertex, &scene))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(error)?;
+
+                if vertices.len() < 3 {
+                    return Err(error(ObjErrorKind::TooFewVertices));
+                }
+
+                if scene.states.last() != Some(&state) {
+                    scene.states.push(state.clone());
+                }
+
+                scene.faces.push(Face {
+                    vertices,
+                    state: scene.states.len() - 1,
+                });
+            }
+            Some("o") => state.object = parts.next().map(String::from),
+            Some("g") => state.groups = parts.map(String::from).collect(),
+            Some("usemtl") => state.material = parts.next().map(String::from),
+            Some("mtllib") => scene.material_libraries.extend(parts.map(String::from)),
+            Some("s") => {
+                state.smoothing_group = match parts.next() {
+                    None | Some("off") => 0,
+                    Some(group) => group
+                        .parse()
+                        .map_err(|_| error(ObjErrorKind::InvalidNumber(group.to_string())))?,
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(scene)
+}
+
+// `v x y z r g b` carries a colour. Vertices without one are white once any has one
+fn push_position<'a>(
+    scene: &mut Scene,
+    parts: impl Iterator<Item = &'a str>,
+) -> Result<(), ObjErrorKind> {
+    let parts: Vec<_> = parts.collect();
+
+    push_floats(&mut scene.position, parts.iter().copied(), 3)?;
+
+    if parts.len() == 6 {
+        scene.color.resize(scene.position.len() - 3, 1.0);
+        push_floats(&mut scene.color, parts[3..].iter().copied(), 3)?;
+    } else if !scene.color.is_empty() {
+        scene.color.extend_from_slice(&[1.0; 3]);
+    }
+
+    Ok(())
+}
+
+// Extra components are dropped and missing ones are zero, as in the threaded parser
+fn push_floats<'a>(
+    dst: &mut Vec<f32>,
+    mut parts: impl Iterator<Item = &'a str>,
+    components: usize,
+) -> Result<(), ObjErrorKind> {
+    for _ in 0..components {
+        let num = match parts.next() {
+            Some(num) => num
+                .parse()
+                .map_err(|_| ObjErrorKind::InvalidNumber(num.to_string()))?,
+            None => 0.0,
+        };
+
+        dst.push(num);
+    }
+
+    Ok(())
+}
+
+fn parse_face_vertex(vertex: &str, scene: &Scene) -> Result<FaceVertex, ObjErrorKind> {
+    let mut iter = vertex.split('/');
+    let invalid = || ObjErrorKind::InvalidIndex(vertex.to_string());
+
+    let position = iter.next().ok_or_else(invalid)?;
+    let position = resolve_index(position, scene.position_count())?.ok_or_else(invalid)?;
+
+    let texcoord = match iter.next() {
+        Some(texcoord) => resolve_index(texcoord, scene.texcoord_count())?,
+        None => None,
+    };
+
+    let normal = match iter.next() {
+        Some(normal) => Some(resolve_index(normal, scene.normal_count())?.ok_or_else(invalid)?),
+        None => None,
+    };
+
+    if iter.next().is_some() {
+        return Err(invalid());
+    }
+
+    Ok(FaceVertex {
+        position,
+        texcoord,
+        normal,
+    })
+}
+
+/// Resolves a one based, possibly negative (relative) index to a zero based one.
+/// An empty index, as in `1//2`, resolves to `None`.
+fn resolve_index(index: &str, count: usize) -> Result<Option<usize>, ObjErrorKind> {
+    if index.is_empty() {
+        return Ok(None);
+    }
+
+    let obj_index: isize = index
+        .parse()
+        .map_err(|_| ObjErrorKind::InvalidIndex(index.to_string()))?;
+
+    let resolved = if obj_index < 0 {
+        count.checked_sub(obj_index.unsigned_abs())
+    } else {
+        (obj_index as usize).checked_sub(1)
+    };
+
+    match resolved {
+        Some(resolved) if resolved < count => Ok(Some(resolved)),
+        _ => Err(ObjErrorKind::IndexOutOfRange(obj_index)),
+    }
+}
@@ -0,0 +1,193 @@
+use std::io::{self, Write};
+
+use super::{FaceState, FaceVertex, Scene};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FloatFormat {
+    /// The shortest representation that parses back to the same `f32`.
+    Shortest,
+    /// A fixed number of decimals. This does not round-trip in general.
+    Fixed(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndexMode {
+    Absolute,
+    /// Negative indices counting back from the last vertex written.
+    Relative,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WriteOptions {
+    pub float_format: FloatFormat,
+    pub index_mode: IndexMode,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        WriteOptions {
+            float_format: FloatFormat::Shortest,
+            index_mode: IndexMode::Absolute,
+        }
+    }
+}
+
+/// Writes `scene` as OBJ text. With `FloatFormat::Shortest`, parsing the output
+/// with `obj::parse` gives back a scene equal to one that was produced by `obj::parse`.
+/// `Scene::attributes` have no OBJ representation and are not written.
+pub fn write<W: Write>(scene: &Scene, writer: W, options: &WriteOptions) -> io::Result<()> {
+    let mut writer = io::BufWriter::new(writer);
+
+    for library in &scene.material_libraries {
+        writeln!(writer, "mtllib {}", library)?;
+    }
+
+    let has_color = scene.color.len() == scene.position.len();
+
+    for (i, position) in scene.position.chunks(3).enumerate() {
+        writer.write_all(b"v")?;
+        write_floats(&mut writer, position, options.float_format)?;
+
+        if has_color {
+            write_floats(
+                &mut writer,
+                &scene.color[i * 3..i * 3 + 3],
+                options.float_format,
+            )?;
+        }
+
+        writer.write_all(b"\n")?;
+    }
+
+    for (keyword, data, components) in [("vt", &scene.texcoord, 2), ("vn", &scene.normal, 3)] {
+        for vertex in data.chunks(components) {
+            writer.write_all(keyword.as_bytes())?;
+            write_floats(&mut writer, vertex, options.float_format)?;
+            writer.write_all(b"\n")?;
+        }
+    }
+
+    let counts = (
+        scene.position_count(),
+        scene.texcoord_count(),
+        scene.normal_count(),
+    );
+
+    let default_state = FaceState::default();
+    let mut current_state = &default_state;
+    let mut current_index = None;
+
+    for face in &scene.faces {
+        if current_index != Some(face.state) {
+            let state = &scene.states[face.state];
+            write_state_change(&mut writer, current_state, state)?;
+            current_state = state;
+            current_index = Some(face.state);
+        }
+
+        writer.write_all(b"f")?;
+
+        for vertex in &face.vertices {
+            writer.write_all(b" ")?;
+            write_face_vertex(&mut writer, vertex, counts, options.index_mode)?;
+        }
+
+        writer.write_all(b"\n")?;
+    }
+
+    writer.flush()
+}
+
+pub fn to_string(scene: &Scene, options: &WriteOptions) -> String {
+    let mut buffer = Vec::new();
+
+    write(scene, &mut buffer, options).expect("writing to a Vec cannot fail");
+
+    String::from_utf8(buffer).expect("the writer only emits UTF-8")
+}
+
+fn write_floats<W: Write>(writer: &mut W, nums: &[f32], format: FloatFormat) -> io::Result<()> {
+    for &num in nums {
+        writer.write_all(b" ")?;
+        write_float(writer, num, format)?;
+    }
+
+    Ok(())
+}
+
+/// Shared with the MTL writer so both format numbers the same way.
+pub(crate) fn write_float<W: Write>(
+    writer: &mut W,
+    num: f32,
+    format: FloatFormat,
+) -> io::Result<()> {
+    match format {
+        FloatFormat::Shortest => write!(writer, "{}", num),
+        FloatFormat::Fixed(precision) => write!(writer, "{:.*}", precision, num),
+    }
+}
+
+fn write_state_change<W: Write>(
+    writer: &mut W,
+    previous: &FaceState,
+    state: &FaceState,
+) -> io::Result<()> {
+    if previous.object != state.object {
+        match &state.object {
+            Some(object) => writeln!(writer, "o {}", object)?,
+            None => writeln!(writer, "o")?,
+        }
+    }
+
+    if previous.groups != state.groups {
+        writer.write_all(b"g")?;
+
+        for group in &state.groups {
+            write!(writer, " {}", group)?;
+        }
+
+        writer.write_all(b"\n")?;
+    }
+
+    if previous.material != state.material {
+        match &state.material {
+            Some(material) => writeln!(writer, "usemtl {}", material)?,
+            None => writeln!(writer, "usemtl")?,
+        }
+    }
+
+    if previous.smoothing_group != state.smoothing_group {
+        match state.smoothing_group {
+            0 => writeln!(writer, "s off")?,
+            group => writeln!(writer, "s {}", group)?,
+        }
+    }
+
+    Ok(())
+}
+
+fn write_face_vertex<W: Write>(
+    writer: &mut W,
+    vertex: &FaceVertex,
+    (position_count, texcoord_count, normal_count): (usize, usize, usize),
+    index_mode: IndexMode,
+) -> io::Result<()> {
+    let index = |index: usize, count: usize| match index_mode {
+        IndexMode::Absolute => index as isize + 1,
+        IndexMode::Relative => index as isize - count as isize,
+    };
+
+    write!(writer, "{}", index(vertex.position, position_count))?;
+
+    match (vertex.texcoord, vertex.normal) {
+        (None, None) => Ok(()),
+        (Some(texcoord), None) => write!(writer, "/{}", index(texcoord, texcoord_count)),
+        (None, Some(normal)) => write!(writer, "//{}", index(normal, normal_count)),
+        (Some(texcoord), Some(normal)) => write!(
+            writer,
+            "/{}/{}",
+            index(texcoord, texcoord_count),
+            index(normal, normal_count)
+        ),
+    }
+}
@@ -0,0 +1,281 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::obj::{Face, FaceState, FaceVertex, Scene, SingleIndexed};
+use crate::parser::Groups;
+use crate::triangulate::Triangulation;
+
+/// The optional parts of the `[ST][C][N][4]OFF` header keyword.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Header {
+    texcoord: bool,
+    color: bool,
+    normal: bool,
+    /// `4OFF`, vertices are `x y z w` and divided by `w` when read.
+    homogeneous: bool,
+}
+
+impl Header {
+    fn parse(keyword: &str) -> Option<Self> {
+        let mut header = Header::default();
+        let mut rest = keyword;
+
+        for (prefix, flag) in [
+            ("ST", &mut header.texcoord),
+            ("C", &mut header.color),
+            ("N", &mut header.normal),
+            ("4", &mut header.homogeneous),
+        ] {
+            if let Some(stripped) = rest.strip_prefix(prefix) {
+                *flag = true;
+                rest = stripped;
+            }
+        }
+
+        if rest == "OFF" {
+            Some(header)
+        } else {
+            None
+        }
+    }
+
+    fn keyword(&self) -> String {
+        let mut keyword = String::new();
+
+        for (prefix, flag) in [
+            ("ST", self.texcoord),
+            ("C", self.color),
+            ("N", self.normal),
+            ("4", self.homogeneous),
+        ] {
+            if flag {
+                keyword.push_str(prefix);
+            }
+        }
+
+        keyword + "OFF"
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum OffError {
+    InvalidHeader(String),
+    UnexpectedEof,
+    InvalidLine { line: usize, message: String },
+}
+
+impl fmt::Display for OffError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OffError::InvalidHeader(keyword) => write!(f, "invalid OFF header `{}`", keyword),
+            OffError::UnexpectedEof => write!(f, "unexpected end of OFF data"),
+            OffError::InvalidLine { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for OffError {}
+
+/// Reads OFF, COFF, NOFF, STOFF, 4OFF and their combinations into a `Scene`.
+/// Normals, texcoords and colours are per position, so every face vertex indexes
+/// all streams with its position index. If any colour value in the file is above
+/// 1, all colours are taken to be `0..=255` and scaled to `[0, 1]`. Alpha is
+/// dropped. Face colours and the edge count are ignored.
+pub fn read(off_file: &str) -> Result<Scene, OffError> {
+    let mut lines = off_file
+        .lines()
+        .enumerate()
+        .map(|(number, line)| (number + 1, line.split('#').next().unwrap_or("").trim()))
+        .filter(|(_, line)| !line.is_empty());
+
+    let (mut counts_line, first) = lines.next().ok_or(OffError::UnexpectedEof)?;
+    let mut first = first.split_whitespace();
+    let keyword = first.next().unwrap_or("");
+    let header =
+        Header::parse(keyword).ok_or_else(|| OffError::InvalidHeader(keyword.to_string()))?;
+
+    // The counts may follow the keyword on the same line.
+    let mut counts: Vec<&str> = first.collect();
+
+    if counts.is_empty() {
+        let (number, line) = lines.next().ok_or(OffError::UnexpectedEof)?;
+        counts = line.split_whitespace().collect();
+        counts_line = number;
+    }
+
+    let error = |line: usize, message: String| OffError::InvalidLine { line, message };
+
+    let counts: Vec<usize> = counts
+        .iter()
+        .take(2)
+        .map(|count| count.parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| error(counts_line, "invalid vertex or face count".to_string()))?;
+
+    let (vertex_count, face_count) = match counts[..] {
+        [vertices, faces] => (vertices, faces),
+        _ => return Err(error(counts_line, "missing face count".to_string())),
+    };
+
+    let mut scene = Scene::default();
+    scene.states.push(FaceState::default());
+
+    let position_len = if header.homogeneous { 4 } else { 3 };
+    let fixed_len = position_len + 3 * header.normal as usize + 2 * header.texcoord as usize;
+    let mut values = vec![];
+    let mut byte_colors = false;
+
+    for _ in 0..vertex_count {
+        let (number, line) = lines.next().ok_or(OffError::UnexpectedEof)?;
+
+        values.clear();
+
+        for value in line.split_whitespace() {
+            values.push(
+                value
+                    .parse::<f32>()
+                    .map_err(|_| error(number, format!("invalid value `{}`", value)))?,
+            );
+        }
+
+        let color_len = values.len().checked_sub(fixed_len);
+        let color_len = match (header.color, color_len) {
+            (false, Some(0)) => 0,
+            (true, Some(len @ 3..=4)) => len,
+            _ => return Err(error(number, "wrong number of vertex values".to_string())),
+        };
+
+        let (position, rest) = values.split_at(position_len);
+
+        if header.homogeneous {
+            let w = position[3];
+            scene
+                .position
+                .extend(position[..3].iter().map(|coordinate| coordinate / w));
+        } else {
+            scene.position.extend_from_slice(position);
+        }
+
+        let (normal, rest) = rest.split_at(3 * header.normal as usize);
+        let (color, texcoord) = rest.split_at(color_len);
+
+        scene.normal.extend_from_slice(normal);
+        scene.texcoord.extend_from_slice(texcoord);
+
+        if header.color {
+            byte_colors |= color.iter().any(|&c| c > 1.0);
+            scene.color.extend_from_slice(&color[..3]);
+        }
+    }
+
+    if byte_colors {
+        for c in &mut scene.color {
+            *c /= 255.0;
+        }
+    }
+
+    for _ in 0..face_count {
+        let (number, line) = lines.next().ok_or(OffError::UnexpectedEof)?;
+        let mut parts = line.split_whitespace();
+
+        let len: usize = parts
+            .next()
+            .and_then(|len| len.parse().ok())
+            .ok_or_else(|| error(number, "invalid face vertex count".to_string()))?;
+
+        let vertices = parts
+            .by_ref()
+            .take(len)
+            .map(|index| match index.parse::<usize>() {
+                Ok(position) if position < vertex_count => Ok(FaceVertex {
+                    position,
+                    texcoord: Some(position).filter(|_| header.texcoord),
+                    normal: Some(position).filter(|_| header.normal),
+                }),
+                _ => Err(error(number, format!("invalid vertex index `{}`", index))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if vertices.len() != len {
+            return Err(error(number, "face has too few vertex indices".to_string()));
+        }
+
+        scene.faces.push(Face { vertices, state: 0 });
+    }
+
+    Ok(scene)
+}
+
+/// Reads OFF into one triangle soup, the representation `parser::parse_obj_threaded`
+/// produces for OBJ.
+pub fn read_groups(off_file: &str, triangulation: Triangulation) -> Result<Groups, OffError> {
+    let scene = read(off_file)?;
+
+    Ok(vec![scene.triangle_soup_with(&scene.faces, triangulation)])
+}
+
+/// Writes `scene` as OFF, with `ST`, `C` and `N` in the header when every face has
+/// texcoords, the scene has colours, or every face has normals. Like PLY, OFF has
+/// one index per vertex, so corners whose indices differ become separate vertices.
+/// Colours are written as RGBA floats. Groups, materials and attributes are dropped.
+pub fn write<W: Write>(scene: &Scene, writer: W) -> io::Result<()> {
+    let vertices = SingleIndexed::new(scene);
+    let mut writer = io::BufWriter::new(writer);
+
+    let header = Header {
+        texcoord: vertices.has_texcoord,
+        color: vertices.has_color,
+        normal: vertices.has_normal,
+        homogeneous: false,
+    };
+
+    writeln!(writer, "{}", header.keyword())?;
+    writeln!(
+        writer,
+        "{} {} 0",
+        vertices.corners.len(),
+        vertices.faces.len()
+    )?;
+
+    for corner in &vertices.corners {
+        let position = corner.position * 3;
+        let [x, y, z] = [0, 1, 2].map(|i| scene.position[position + i]);
+        write!(writer, "{} {} {}", x, y, z)?;
+
+        if let (true, Some(normal)) = (vertices.has_normal, corner.normal) {
+            write_floats(&mut writer, &scene.normal[normal * 3..normal * 3 + 3])?;
+        }
+
+        if vertices.has_color {
+            write_floats(&mut writer, &scene.color[position..position + 3])?;
+            writer.write_all(b" 1")?;
+        }
+
+        if let (true, Some(texcoord)) = (vertices.has_texcoord, corner.texcoord) {
+            write_floats(&mut writer, &scene.texcoord[texcoord * 2..texcoord * 2 + 2])?;
+        }
+
+        writer.write_all(b"\n")?;
+    }
+
+    for face in &vertices.faces {
+        write!(writer, "{}", face.len())?;
+
+        for index in face {
+            write!(writer, " {}", index)?;
+        }
+
+        writer.write_all(b"\n")?;
+    }
+
+    writer.flush()
+}
+
+fn write_floats<W: Write>(writer: &mut W, nums: &[f32]) -> io::Result<()> {
+    for num in nums {
+        write!(writer, " {}", num)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,87 @@
+//! Where the chunked stages of the threaded parsers run. By default that is a
+//! `ThreadPool` of `NUM_CORES` threads owned by the parse. With the `rayon` feature
+//! it is the rayon pool of the caller, so a parse started inside
+//! `rayon::ThreadPool::install` or on a rayon worker does not add threads of its own.
+
+use super::thread_pool::WorkerTiming;
+
+#[cfg(not(feature = "rayon"))]
+pub(crate) struct Executor {
+    pool: super::thread_pool::ThreadPool,
+}
+
+#[cfg(not(feature = "rayon"))]
+impl Executor {
+    pub fn new() -> Self {
+        Self::with_threads(super::NUM_CORES)
+    }
+
+    pub fn with_threads(threads: usize) -> Self {
+        Executor {
+            pool: super::thread_pool::ThreadPool::new(threads.max(1)),
+        }
+    }
+
+    pub fn run_chunks<T>(&self, chunks: usize, task: T) -> Vec<WorkerTiming>
+    where
+        T: Fn(usize) + Sync,
+    {
+        self.pool.run_chunks(chunks, task)
+    }
+}
+
+#[cfg(feature = "rayon")]
+pub(crate) struct Executor;
+
+#[cfg(feature = "rayon")]
+impl Executor {
+    pub fn new() -> Self {
+        Executor
+    }
+
+    /// The caller's pool decides the number of threads.
+    pub fn with_threads(_threads: usize) -> Self {
+        Executor
+    }
+
+    /// `ThreadPool::run_chunks` on the current rayon pool. Rayon steals work on its
+    /// own but does not say when, so `WorkerTiming::stolen` is always zero, and
+    /// there is one timing per thread of the pool.
+    pub fn run_chunks<T>(&self, chunks: usize, task: T) -> Vec<WorkerTiming>
+    where
+        T: Fn(usize) + Sync,
+    {
+        use rayon::prelude::*;
+        use std::sync::Mutex;
+        use std::time::Instant;
+
+        let threads = rayon::current_num_threads();
+        let timings: Vec<Mutex<WorkerTiming>> = (0..threads)
+            .map(|worker| {
+                Mutex::new(WorkerTiming {
+                    worker,
+                    ..WorkerTiming::default()
+                })
+            })
+            .collect();
+
+        let start = Instant::now();
+
+        (0..chunks).into_par_iter().for_each(|chunk| {
+            let busy = Instant::now();
+            task(chunk);
+            let busy = busy.elapsed();
+
+            let worker = rayon::current_thread_index().unwrap_or(0) % threads;
+            let mut timing = timings[worker].lock().unwrap();
+            timing.busy += busy;
+            timing.chunks += 1;
+            timing.elapsed = start.elapsed();
+        });
+
+        timings
+            .into_iter()
+            .map(|timing| timing.into_inner().unwrap())
+            .collect()
+    }
+}
@@ -0,0 +1,363 @@
+//! A single pass alternative to the three stage pipeline of `parse_obj_threaded`.
+//!
+//! The three stage pipeline classifies every line into `(start, end)` spans, then
+//! parses the vertex spans, then expands the face spans, synchronising the workers
+//! between each stage. Here every worker parses the vertices and faces of its chunk
+//! in one go, recording face corners as raw indices. Relative indices and error
+//! line numbers only need the number of vertices and lines in earlier chunks, so a
+//! prefix sum over the chunks resolves them, and a second, cheap pass gathers the
+//! positions of every triangle.
+
+use std::mem;
+use std::sync::mpsc;
+use std::sync::Arc;
+
+use super::executor::Executor;
+use super::{next_line_break, number, raw_index, Float, Groups, Origin, VertexData, NUM_CHUNKS};
+use crate::obj::{ObjError, ObjErrorKind};
+use crate::triangulate::{triangulate, Triangulation};
+
+/// Faces of one group, as in `sequential`, with positions not resolved yet.
+#[derive(Default)]
+struct PendingGroup {
+    /// 0-based position indices. Relative indices are stored relative to the
+    /// first vertex of the chunk until the fixup adds the vertices before it.
+    corners: Vec<isize>,
+    /// Ascending slots of `corners` holding relative indices, with the index as
+    /// written for errors.
+    relative: Vec<(usize, isize)>,
+    face_ends: Vec<usize>,
+    /// Line of each face within the chunk, for errors.
+    lines: Vec<usize>,
+}
+
+#[derive(Default)]
+struct Chunk<F> {
+    positions: Vec<F>,
+    /// The first group continues the last group of the previous chunk.
+    groups: Vec<PendingGroup>,
+    lines: usize,
+    /// The first malformed line, numbered within the chunk.
+    error: Option<ObjError>,
+}
+
+/// `parse_obj_fused_as` with `f32` positions and fan triangulation.
+pub fn parse_obj_fused(obj_file: String) -> Result<Groups, ObjError> {
+    let (groups, _) = parse_obj_fused_as(obj_file, Triangulation::Fan, None)?;
+
+    Ok(groups)
+}
+
+/// Parses OBJ into the same groups as `parse_obj_threaded`, in one parallel pass
+/// over the text. Malformed input gives the same `ObjError`, although with several
+/// errors in the file another one may be reported first. Relative (negative) face
+/// indices are resolved as well. Returns the groups and the offset subtracted from
+/// positions.
+pub fn parse_obj_fused_as<F: Float>(
+    obj_file: String,
+    triangulation: Triangulation,
+    origin: Option<Origin>,
+) -> Result<(Groups<F>, [f64; 3]), ObjError> {
+    let obj_file = Arc::new(obj_file);
+    let executor = Executor::new();
+
+    let offset = match origin {
+        Some(Origin::Offset(offset)) => Some(offset),
+        Some(Origin::FirstVertex) => Some(first_position(&obj_file)?),
+        None => None,
+    };
+
+    let mut chunks = parse_chunks::<F>(Arc::clone(&obj_file), offset, &executor);
+
+    if let Some(error) = first_error(&mut chunks) {
+        return Err(error);
+    }
+
+    // Prefix sums of vertices and lines, and the positions of all chunks in order
+    let mut vertex_prefix = Vec::with_capacity(chunks.len());
+    let mut line_prefix = Vec::with_capacity(chunks.len());
+    let mut positions = Vec::with_capacity(chunks.iter().map(|chunk| chunk.positions.len()).sum());
+    let mut lines = 0;
+
+    for chunk in &mut chunks {
+        vertex_prefix.push(positions.len() / 3);
+        line_prefix.push(lines);
+        lines += chunk.lines;
+        positions.extend_from_slice(&mem::take(&mut chunk.positions));
+    }
+
+    let fixup = Fixup {
+        chunks,
+        vertex_prefix,
+        line_prefix,
+        positions,
+        triangulation,
+    };
+
+    let groups = gather(fixup, &executor)?;
+
+    Ok((groups, offset.unwrap_or([0.0; 3])))
+}
+
+fn parse_chunks<F: Float>(
+    obj_file: Arc<String>,
+    offset: Option<[f64; 3]>,
+    executor: &Executor,
+) -> Vec<Chunk<F>> {
+    let len = obj_file.len();
+    let chunk_size = len / NUM_CHUNKS + 1;
+
+    // Chunks start after a line break, so that they hold whole lines and the lines
+    // of every chunk can be counted
+    let split = move |obj_file: &str, id: usize| match id {
+        0 => 0,
+        NUM_CHUNKS => len,
+        id => (next_line_break(obj_file, id * chunk_size) + 1).min(len),
+    };
+
+    let (tx, rx) = mpsc::channel();
+
+    executor.run_chunks(NUM_CHUNKS, move |id| {
+        let chunk = &obj_file[split(&obj_file, id)..split(&obj_file, id + 1)];
+        tx.send((parse_chunk(chunk, offset), id)).unwrap();
+    });
+
+    // Every chunk has been sent by the time `run_chunks` returns
+    let mut chunks: Vec<_> = rx.try_iter().collect();
+    chunks.sort_by_key(|(_, id)| *id);
+
+    chunks.into_iter().map(|(chunk, _)| chunk).collect()
+}
+
+fn parse_chunk<F: Float>(text: &str, offset: Option<[f64; 3]>) -> Chunk<F> {
+    let mut chunk = Chunk {
+        positions: Vec::with_capacity(text.len() / 30),
+        groups: vec![PendingGroup::default()],
+        ..Chunk::default()
+    };
+
+    for line in text.split_inclusive('\n') {
+        chunk.lines += 1;
+
+        let line_number = chunk.lines;
+        let error = |kind| ObjError {
+            line: line_number,
+            kind,
+        };
+
+        let mut parts = line.split_ascii_whitespace();
+
+        match parts.next() {
+            Some("v") => {
+                // Like the threaded parser, extra components are dropped and missing ones are zero
+                for component in 0..3 {
+                    let num = match parts.next() {
+                        Some(num) => num,
+                        None => {
+                            chunk.positions.push(F::default());
+                            continue;
+                        }
+                    };
+
+                    let parsed = match offset {
+                        Some(offset) => number::parse_f64(num)
+                            .map(|parsed| F::from_f64(parsed - offset[component])),
+                        None => F::parse(num),
+                    };
+
+                    match parsed {
+                        Some(parsed) => chunk.positions.push(parsed),
+                        None => {
+                            chunk.error = Some(error(ObjErrorKind::InvalidNumber(num.to_string())));
+                            return chunk;
+                        }
+                    }
+                }
+            }
+            Some("f") => {
+                let vertices = (chunk.positions.len() / 3) as isize;
+                let group = chunk.groups.last_mut().unwrap();
+
+                for vertex in parts {
+                    match raw_index(vertex) {
+                        Some(index) if index > 0 => group.corners.push(index - 1),
+                        Some(index) => {
+                            group.relative.push((group.corners.len(), index));
+                            group.corners.push(vertices + index);
+                        }
+                        None => {
+                            chunk.error =
+                                Some(error(ObjErrorKind::InvalidIndex(vertex.to_string())));
+                            return chunk;
+                        }
+                    }
+                }
+
+                group.face_ends.push(group.corners.len());
+                group.lines.push(line_number);
+            }
+            Some(keyword @ ("vt" | "vn")) => {
+                // Only positions are written, but the numbers are checked like the
+                // threaded parser does
+                let components = if keyword == "vt" { 2 } else { 3 };
+
+                if let Some(num) = parts.take(components).find(|num| F::parse(num).is_none()) {
+                    chunk.error = Some(error(ObjErrorKind::InvalidNumber(num.to_string())));
+                    return chunk;
+                }
+            }
+            Some("g") => chunk.groups.push(PendingGroup::default()),
+            _ => {}
+        }
+    }
+
+    chunk
+}
+
+/// The earliest parse error, with its line number in the whole file.
+fn first_error<F>(chunks: &mut [Chunk<F>]) -> Option<ObjError> {
+    let mut lines = 0;
+
+    for chunk in chunks {
+        if let Some(mut error) = chunk.error.take() {
+            error.line += lines;
+            return Some(error);
+        }
+
+        lines += chunk.lines;
+    }
+
+    None
+}
+
+fn first_position(obj_file: &str) -> Result<[f64; 3], ObjError> {
+    let mut position = [0.0; 3];
+
+    let first = obj_file
+        .lines()
+        .enumerate()
+        .find(|(_, line)| line.split_ascii_whitespace().next() == Some("v"));
+
+    if let Some((line, first)) = first {
+        for (coordinate, num) in position.iter_mut().zip(first.split_whitespace().skip(1)) {
+            *coordinate = number::parse_f64(num).ok_or_else(|| ObjError {
+                line: line + 1,
+                kind: ObjErrorKind::InvalidNumber(num.to_string()),
+            })?;
+        }
+    }
+
+    Ok(position)
+}
+
+struct Fixup<F> {
+    chunks: Vec<Chunk<F>>,
+    vertex_prefix: Vec<usize>,
+    line_prefix: Vec<usize>,
+    positions: Vec<F>,
+    triangulation: Triangulation,
+}
+
+fn gather<F: Float>(fixup: Fixup<F>, executor: &Executor) -> Result<Groups<F>, ObjError> {
+    let fixup = Arc::new(fixup);
+    let (tx, rx) = mpsc::channel();
+
+    executor.run_chunks(NUM_CHUNKS, move |id| {
+        tx.send((gather_chunk(&fixup, id), id)).unwrap();
+    });
+
+    let mut messages: Vec<_> = rx.try_iter().collect();
+    messages.sort_by_key(|(_, id)| *id);
+
+    let mut groups: Groups<F> = vec![];
+
+    for (chunk_groups, _) in messages {
+        let mut iter = chunk_groups?.into_iter();
+        let first = iter.next().unwrap();
+        match groups.last_mut() {
+            Some(last) => last.extend(first),
+            None => groups.push(first),
+        }
+        groups.extend(iter);
+    }
+
+    Ok(groups)
+}
+
+fn gather_chunk<F: Float>(fixup: &Fixup<F>, id: usize) -> Result<Groups<F>, ObjError> {
+    let Fixup {
+        chunks,
+        vertex_prefix,
+        line_prefix,
+        positions,
+        triangulation,
+    } = fixup;
+
+    let vertices = positions.len() / 3;
+    let prefix = vertex_prefix[id] as isize;
+    let position = |index: usize| &positions[index * 3..index * 3 + 3];
+
+    let mut indices = vec![];
+    let mut polygon = vec![];
+
+    chunks[id]
+        .groups
+        .iter()
+        .map(|group| {
+            let mut data = VertexData::new();
+            let mut relative = group.relative.iter().peekable();
+            let mut start = 0;
+
+            for (&end, &line) in group.face_ends.iter().zip(&group.lines) {
+                indices.clear();
+
+                for slot in start..end {
+                    let mut index = group.corners[slot];
+                    let mut raw = index + 1;
+
+                    if let Some(&(_, written)) =
+                        relative.next_if(|&&(relative, _)| relative == slot)
+                    {
+                        index += prefix;
+                        raw = written;
+                    }
+
+                    if index < 0 || index as usize >= vertices {
+                        return Err(ObjError {
+                            line: line_prefix[id] + line,
+                            kind: ObjErrorKind::IndexOutOfRange(raw),
+                        });
+                    }
+
+                    indices.push(index as usize);
+                }
+
+                start = end;
+
+                if *triangulation == Triangulation::Fan {
+                    for i in 1..indices.len().saturating_sub(1) {
+                        for corner in [indices[0], indices[i], indices[i + 1]] {
+                            data.position.extend_from_slice(position(corner));
+                        }
+                    }
+                } else if let Some(&first) = indices.first() {
+                    // Relative to the first corner, as in `parse_index`
+                    let first = position(first);
+                    polygon.clear();
+                    polygon.extend(indices.iter().map(|&index| {
+                        let position = position(index);
+                        [0, 1, 2].map(|i| (position[i].to_f64() - first[i].to_f64()) as f32)
+                    }));
+
+                    for triangle in triangulate(&polygon, *triangulation) {
+                        for corner in triangle {
+                            data.position.extend_from_slice(position(indices[corner]));
+                        }
+                    }
+                }
+            }
+
+            Ok(data)
+        })
+        .collect()
+}
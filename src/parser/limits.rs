@@ -0,0 +1,139 @@
+//! Limits for parsing untrusted input. Every limit is checked before the memory it
+//! guards is allocated: the input size before preprocessing and while `call`
+//! statements are expanded, the element counts once the lines have been classified
+//! and before any vertex or face is parsed, and the output size face by face.
+//!
+//! Limits apply to `parse_obj_with_options`, `parse_obj_file_with_options`,
+//! `parse_obj_bytes_with_options` and their `_as` variants, to
+//! `cache::load_or_parse_limited`, and to the size of the entries an
+//! `archive::Archive::with_limits` reads. Everything else is unbounded:
+//! `parse_obj_threaded`, `parse_obj_file`, `parse_obj_bytes`, the `fused` and
+//! `sequential` parsers, `obj::parse`, `mtl::parse` and
+//! `asynchronous::load_obj_async`.
+
+use std::error::Error;
+use std::fmt;
+
+use super::preprocess::DEFAULT_MAX_INCLUDE_DEPTH;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+    /// The OBJ source, including expanded `call` statements.
+    InputBytes,
+    /// Each of `v`, `vt` and `vn` statements.
+    Vertices,
+    Faces,
+    VerticesPerFace,
+    Groups,
+    /// The positions of all groups, after triangulation.
+    OutputBytes,
+    /// `call` statements nested in the files they include.
+    IncludeDepth,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Limit::InputBytes => "input bytes",
+            Limit::Vertices => "vertices",
+            Limit::Faces => "faces",
+            Limit::VerticesPerFace => "vertices per face",
+            Limit::Groups => "groups",
+            Limit::OutputBytes => "output bytes",
+            Limit::IncludeDepth => "nested calls",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LimitExceeded {
+    pub limit: Limit,
+    pub max: usize,
+    /// What the input needed, or at least needed when the parse stopped.
+    pub found: usize,
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} exceed the limit of {}",
+            self.found, self.limit, self.max
+        )
+    }
+}
+
+impl Error for LimitExceeded {}
+
+/// Maximum sizes for `ParseOptions::limits`, unlimited by default except for the
+/// include depth, which stops a file that calls itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    pub max_input_bytes: usize,
+    pub max_vertices: usize,
+    pub max_faces: usize,
+    pub max_vertices_per_face: usize,
+    pub max_groups: usize,
+    pub max_output_bytes: usize,
+    pub max_include_depth: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_input_bytes: usize::MAX,
+            max_vertices: usize::MAX,
+            max_faces: usize::MAX,
+            max_vertices_per_face: usize::MAX,
+            max_groups: usize::MAX,
+            max_output_bytes: usize::MAX,
+            max_include_depth: DEFAULT_MAX_INCLUDE_DEPTH,
+        }
+    }
+}
+
+impl Limits {
+    /// A starting point for user uploads: 256 MiB in, 1 GiB out, 16 million
+    /// vertices and faces, 256 vertices per face, 65536 groups and the default
+    /// include depth.
+    pub fn untrusted() -> Self {
+        Limits {
+            max_input_bytes: 256 << 20,
+            max_vertices: 16 << 20,
+            max_faces: 16 << 20,
+            max_vertices_per_face: 256,
+            max_groups: 1 << 16,
+            max_output_bytes: 1 << 30,
+            max_include_depth: DEFAULT_MAX_INCLUDE_DEPTH,
+        }
+    }
+
+    pub fn check(&self, limit: Limit, found: usize) -> Result<(), LimitExceeded> {
+        let max = self.max(limit);
+
+        if found > max {
+            Err(LimitExceeded { limit, max, found })
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn max(&self, limit: Limit) -> usize {
+        match limit {
+            Limit::InputBytes => self.max_input_bytes,
+            Limit::Vertices => self.max_vertices,
+            Limit::Faces => self.max_faces,
+            Limit::VerticesPerFace => self.max_vertices_per_face,
+            Limit::Groups => self.max_groups,
+            Limit::OutputBytes => self.max_output_bytes,
+            Limit::IncludeDepth => self.max_include_depth,
+        }
+    }
+
+    /// Whether faces need to be checked one by one.
+    pub(crate) fn limits_faces(&self) -> bool {
+        self.max_vertices_per_face != usize::MAX || self.max_output_bytes != usize::MAX
+    }
+}
@@ -1,130 +1,573 @@
+use std::convert::TryFrom;
 use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
 use std::sync::mpsc;
 use std::sync::Arc;
 
-mod thread_pool;
-
-use thread_pool::ThreadPool;
+pub mod compression;
+mod executor;
+pub mod fused;
+pub mod limits;
+pub mod number;
+pub mod preprocess;
+pub mod progress;
+pub mod sequential;
+pub mod thread_pool;
+
+use crate::obj::{ObjError, ObjErrorKind};
+use crate::triangulate::{triangulate, Triangulation};
+use compression::Compression;
+use executor::Executor;
+use limits::{Limit, Limits};
+use preprocess::{FreeFormElement, IncludeResolver, PreprocessError, SkippedStatement};
+use progress::{CancellationToken, Monitor, Progress, Stage};
+use thread_pool::WorkerTiming;
 
 const NUM_CORES: usize = 4;
+/// Each stage splits its input into this many chunks per worker, so that workers
+/// finishing early can steal the rest instead of idling.
+const CHUNKS_PER_CORE: usize = 16;
+const NUM_CHUNKS: usize = NUM_CORES * CHUNKS_PER_CORE;
+
+/// The number type the parsers write, `f32` by default or `f64` for coordinates
+/// that need the precision, such as georeferenced exports. Every parser but
+/// `parse_obj_threaded_timed` has an `_as` variant taking it. What is built on top
+/// of them stays `f32`: `obj::Scene`, the `cache` format, `archive`, `asynchronous`
+/// and the writers and exporters.
+pub trait Float: Copy + Default + PartialEq + fmt::Debug + Send + Sync + 'static {
+    fn parse(num: &str) -> Option<Self>;
+    fn from_f64(num: f64) -> Self;
+    fn to_f64(self) -> f64;
+}
+
+impl Float for f32 {
+    fn parse(num: &str) -> Option<Self> {
+        number::parse_f32(num)
+    }
+
+    fn from_f64(num: f64) -> Self {
+        num as f32
+    }
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl Float for f64 {
+    fn parse(num: &str) -> Option<Self> {
+        number::parse_f64(num)
+    }
+
+    fn from_f64(num: f64) -> Self {
+        num
+    }
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+}
+
+/// Moves positions to a local origin. The offset is subtracted in `f64` before the
+/// result is narrowed to the output type, so large coordinates such as UTM keep
+/// their precision in `f32`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Origin {
+    Offset([f64; 3]),
+    /// The position of the first `v` statement.
+    FirstVertex,
+}
 
+pub struct ParseOptions {
+    pub include_resolver: Option<Box<dyn IncludeResolver>>,
+    pub triangulation: Triangulation,
+    pub origin: Option<Origin>,
+    /// Worker threads of the parse, ignored with the `rayon` feature. The file is
+    /// split into the same chunks whatever the count, so the result is the same.
+    pub threads: usize,
+    /// Counters the workers add to while parsing.
+    pub progress: Option<Arc<Progress>>,
+    /// Stops the parse with `Err(Cancelled)` once cancelled.
+    pub cancellation: Option<CancellationToken>,
+    /// Fails the parse with `Err(LimitExceeded)` on input that would need more.
+    pub limits: Limits,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            include_resolver: None,
+            triangulation: Triangulation::Fan,
+            origin: None,
+            threads: NUM_CORES,
+            progress: None,
+            cancellation: None,
+            limits: Limits::default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ObjData<F = f32> {
+    pub groups: Groups<F>,
+    pub free_form: Vec<FreeFormElement>,
+    pub skipped: Vec<SkippedStatement>,
+    /// The offset subtracted from every position, zero without `ParseOptions::origin`.
+    pub origin: [f64; 3],
+}
+
+/// `parse_obj_threaded` after expanding `call` statements, with the options'
+/// triangulation, origin, limits, progress and cancellation.
+pub fn parse_obj_with_options(
+    obj_file: &str,
+    options: &ParseOptions,
+) -> Result<ObjData, Box<dyn Error>> {
+    parse_obj_with_options_as(obj_file, options)
+}
+
+/// `parse_obj_with_options` writing `F`, e.g. `parse_obj_with_options_as::<f64>`.
+pub fn parse_obj_with_options_as<F: Float>(
+    obj_file: &str,
+    options: &ParseOptions,
+) -> Result<ObjData<F>, Box<dyn Error>> {
+    let limits = options.limits;
+    limits.check(Limit::InputBytes, obj_file.len())?;
+
+    let preprocessed =
+        preprocess::preprocess_limited(obj_file, options.include_resolver.as_deref(), &limits)
+            .map_err(|error| match error {
+                PreprocessError::Limit(exceeded) => Box::new(exceeded) as Box<dyn Error>,
+                error => Box::new(error),
+            })?;
+
+    let monitor = Monitor::new(
+        options.progress.clone(),
+        options.cancellation.clone(),
+        limits,
+    );
+
+    let (groups, origin, _) = parse_threaded(
+        preprocessed.source,
+        options.triangulation,
+        options.origin,
+        monitor,
+        &Executor::with_threads(options.threads),
+    )?;
+
+    Ok(ObjData {
+        groups,
+        free_form: preprocessed.free_form,
+        skipped: preprocessed.skipped,
+        origin,
+    })
+}
+
+/// Parses the OBJ file at `path`. Gzip, zstd and bzip2 files are detected by their
+/// magic bytes and decoded as a stream into `sequential::parse_obj_reader`, which
+/// gives the same groups. Uncompressed files go through `parse_obj_threaded`.
+///
+/// Nothing bounds the decoded size, use `parse_obj_file_with_options` for
+/// untrusted files.
+pub fn parse_obj_file<P: AsRef<Path>>(path: P) -> Result<Groups, Box<dyn Error>> {
+    parse_obj_file_as(path)
+}
+
+/// `parse_obj_file` writing `F`.
+pub fn parse_obj_file_as<F: Float, P: AsRef<Path>>(path: P) -> Result<Groups<F>, Box<dyn Error>> {
+    let mut file = BufReader::new(File::open(path)?);
+
+    match Compression::detect(file.fill_buf()?) {
+        Compression::None => {
+            let mut obj_file = String::new();
+            file.read_to_string(&mut obj_file)?;
+            parse_obj_threaded_as(obj_file)
+        }
+        compression => {
+            sequential::parse_obj_reader_as(compression.decoder(file)?, Triangulation::Fan)
+        }
+    }
+}
+
+/// `parse_obj_file` for a file that has already been read into memory.
+pub fn parse_obj_bytes(obj_file: Vec<u8>) -> Result<Groups, Box<dyn Error>> {
+    parse_obj_bytes_as(obj_file)
+}
+
+/// `parse_obj_bytes` writing `F`.
+pub fn parse_obj_bytes_as<F: Float>(obj_file: Vec<u8>) -> Result<Groups<F>, Box<dyn Error>> {
+    match Compression::detect(&obj_file) {
+        Compression::None => parse_obj_threaded_as(String::from_utf8(obj_file)?),
+        compression => {
+            sequential::parse_obj_reader_as(compression.decoder(&obj_file[..])?, Triangulation::Fan)
+        }
+    }
+}
+
+/// `parse_obj_with_options` on the OBJ file at `path`, compressed or not. The file
+/// is decoded into memory first, failing with `LimitExceeded` as soon as it grows
+/// past `Limits::max_input_bytes`.
+pub fn parse_obj_file_with_options<P: AsRef<Path>>(
+    path: P,
+    options: &ParseOptions,
+) -> Result<ObjData, Box<dyn Error>> {
+    parse_obj_file_with_options_as(path, options)
+}
+
+/// `parse_obj_file_with_options` writing `F`.
+pub fn parse_obj_file_with_options_as<F: Float, P: AsRef<Path>>(
+    path: P,
+    options: &ParseOptions,
+) -> Result<ObjData<F>, Box<dyn Error>> {
+    let mut file = BufReader::new(File::open(path)?);
+    let compression = Compression::detect(file.fill_buf()?);
+    let obj_file = read_limited(compression.decoder(file)?, &options.limits)?;
+
+    parse_obj_with_options_as(&obj_file, options)
+}
+
+/// `parse_obj_file_with_options` for a file that has already been read into memory.
+pub fn parse_obj_bytes_with_options(
+    obj_file: &[u8],
+    options: &ParseOptions,
+) -> Result<ObjData, Box<dyn Error>> {
+    parse_obj_bytes_with_options_as(obj_file, options)
+}
+
+/// `parse_obj_bytes_with_options` writing `F`.
+pub fn parse_obj_bytes_with_options_as<F: Float>(
+    obj_file: &[u8],
+    options: &ParseOptions,
+) -> Result<ObjData<F>, Box<dyn Error>> {
+    let decoder = Compression::detect(obj_file).decoder(obj_file)?;
+    let obj_file = read_limited(decoder, &options.limits)?;
+
+    parse_obj_with_options_as(&obj_file, options)
+}
+
+/// Reads `reader` as UTF-8, stopping one byte past `Limits::max_input_bytes` so a
+/// small compressed file cannot decode into an unbounded allocation.
+fn read_limited<R: Read>(reader: R, limits: &Limits) -> Result<String, Box<dyn Error>> {
+    let max = limits.max_input_bytes;
+    let mut bytes = vec![];
+
+    reader
+        .take(u64::try_from(max).unwrap_or(u64::MAX).saturating_add(1))
+        .read_to_end(&mut bytes)?;
+    limits.check(Limit::InputBytes, bytes.len())?;
+
+    Ok(String::from_utf8(bytes)?)
+}
+
+/// Parses OBJ text into one triangle soup per `g` group, on `NUM_CORES` threads.
+/// Relative (negative) face indices count back from the `v` lines before the face.
+///
+/// Malformed vertex data or faces are an `ObjError`: the first one in the vertex
+/// data, else the first one in the faces. The fuzz targets in `fuzz/` check that
+/// no input makes this panic, nor `parse_obj_with_options`, `parse_obj_bytes`,
+/// `sequential::parse_obj_reader`, `fused::parse_obj_fused` or `mtl::parse`.
 pub fn parse_obj_threaded(obj_file: String) -> Result<Groups, Box<dyn Error>> {
+    parse_obj_threaded_as(obj_file)
+}
+
+/// `parse_obj_threaded` writing `F`, e.g. `parse_obj_threaded_as::<f64>`.
+pub fn parse_obj_threaded_as<F: Float>(obj_file: String) -> Result<Groups<F>, Box<dyn Error>> {
+    let (groups, _, _) = parse_threaded(
+        obj_file,
+        Triangulation::Fan,
+        None,
+        Monitor::default(),
+        &Executor::new(),
+    )?;
+
+    Ok(groups)
+}
+
+/// Per worker timings of the three stages of the threaded parser.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PipelineTimings {
+    /// Splitting the file into `f`, `v`, `vt` and `vn` lines.
+    pub extract: Vec<WorkerTiming>,
+    pub vertex: Vec<WorkerTiming>,
+    pub index: Vec<WorkerTiming>,
+}
+
+/// `parse_obj_threaded`, also returning how the work was spread over the workers.
+pub fn parse_obj_threaded_timed(
+    obj_file: String,
+) -> Result<(Groups, PipelineTimings), Box<dyn Error>> {
+    let (groups, _, timings) = parse_threaded(
+        obj_file,
+        Triangulation::Fan,
+        None,
+        Monitor::default(),
+        &Executor::new(),
+    )?;
+
+    Ok((groups, timings))
+}
+
+/// The groups, the origin they are relative to, and how long each stage took.
+type Parsed<F> = (Groups<F>, [f64; 3], PipelineTimings);
+
+fn parse_threaded<F: Float>(
+    obj_file: String,
+    triangulation: Triangulation,
+    origin: Option<Origin>,
+    monitor: Monitor,
+    executor: &Executor,
+) -> Result<Parsed<F>, Box<dyn Error>> {
     let obj_file = Arc::new(obj_file);
 
-    let thread_pool = ThreadPool::new(NUM_CORES);
+    let (index_vertex, extract) =
+        extract_vertices_and_indices(Arc::clone(&obj_file), &monitor, executor)?;
 
-    let index_vertex = extract_vertices_and_indices(Arc::clone(&obj_file), &thread_pool);
+    let offset = match origin {
+        Some(Origin::Offset(offset)) => Some(offset),
+        Some(Origin::FirstVertex) => Some(first_position(&index_vertex.vertex, &obj_file)?),
+        None => None,
+    };
 
-    let vertex_data = parse_vertex(index_vertex.vertex, Arc::clone(&obj_file), &thread_pool);
+    // Kept for `parse_index`, which resolves relative indices by the `v` lines
+    // before their face
+    let vertex_lines = Arc::new(index_vertex.vertex);
 
-    let groups = parse_index(
+    let (vertex_data, vertex) = parse_vertex(
+        Arc::clone(&vertex_lines),
+        Arc::clone(&obj_file),
+        offset,
+        &monitor,
+        executor,
+    )?;
+
+    let (groups, index) = parse_index(
         index_vertex.index,
+        vertex_lines,
         vertex_data,
         Arc::clone(&obj_file),
-        &thread_pool,
-    );
+        triangulation,
+        &monitor,
+        executor,
+    )?;
+
+    let timings = PipelineTimings {
+        extract,
+        vertex,
+        index,
+    };
+
+    Ok((groups, offset.unwrap_or([0.0; 3]), timings))
+}
 
-    Ok(groups)
+fn first_position(vertex: &Vertex, obj_file: &str) -> Result<[f64; 3], ObjError> {
+    let mut position = [0.0; 3];
+
+    if let Some(&(start, end)) = vertex.position.iter().flatten().next() {
+        let parts = obj_file[start..end].split_whitespace().skip(1);
+
+        for (coordinate, num) in position.iter_mut().zip(parts) {
+            *coordinate = number::parse_f64(num).ok_or_else(|| {
+                line_error(
+                    obj_file,
+                    start,
+                    ObjErrorKind::InvalidNumber(num.to_string()),
+                )
+            })?;
+        }
+    }
+
+    Ok(position)
+}
+
+/// An error at byte `offset`, numbering lines as `sequential::parse_obj_reader` does.
+fn line_error(obj_file: &str, offset: usize, kind: ObjErrorKind) -> ObjError {
+    let line = obj_file.as_bytes()[..offset]
+        .iter()
+        .filter(|&&byte| byte == b'\n')
+        .count();
+
+    ObjError {
+        line: line + 1,
+        kind,
+    }
+}
+
+/// The first error of any chunk, as `(byte offset, kind)` pairs.
+fn first_error(
+    obj_file: &str,
+    errors: impl Iterator<Item = (usize, ObjErrorKind)>,
+) -> Result<(), ObjError> {
+    match errors.min_by_key(|(offset, _)| *offset) {
+        Some((offset, kind)) => Err(line_error(obj_file, offset, kind)),
+        None => Ok(()),
+    }
 }
 
 fn extract_vertices_and_indices(
     obj_file: Arc<String>,
-    thread_pool: &ThreadPool,
-) -> IndexVertexInfo {
-    let obj_file = Arc::new(obj_file);
+    monitor: &Monitor,
+    executor: &Executor,
+) -> Result<(IndexVertexInfo, Vec<WorkerTiming>), Box<dyn Error>> {
     let len = obj_file.len();
-    let chunk_size = len / NUM_CORES + 1;
+    let chunk_size = len / NUM_CHUNKS + 1;
 
     let (tx, rx) = mpsc::channel();
+    let worker_monitor = monitor.clone();
+
+    let timings = executor.run_chunks(
+        NUM_CHUNKS,
+        #[inline(never)]
+        move |id| {
+            let monitor = &worker_monitor;
+
+            if monitor.is_stopped() {
+                return;
+            }
+
+            // Chunks start after a line break, so every span is a whole line
+            let left_split_index = {
+                if id == 0 {
+                    0
+                } else {
+                    (next_line_break(&obj_file, id * chunk_size) + 1).min(len)
+                }
+            };
 
-    thread_pool.execute(|id| {
-        let tx = tx.clone();
-        let obj_file = Arc::clone(&obj_file);
-        Box::new(
-            #[inline(never)]
-            move || {
-                let left_split_index = {
-                    let (_, right) = obj_file.split_at(id * chunk_size);
-                    id * chunk_size + right.find('\n').unwrap()
-                };
-
-                let right_split_index = {
-                    if id == NUM_CORES - 1 {
-                        len
+            let right_split_index = {
+                if id == NUM_CHUNKS - 1 {
+                    len
+                } else {
+                    (next_line_break(&obj_file, (id + 1) * chunk_size) + 1).min(len)
+                }
+            };
+
+            let chunk = &obj_file[left_split_index..right_split_index];
+
+            let mut ticker = monitor.ticker(Stage::Extract);
+            let lines = chunk
+                .split_inclusive('\n')
+                .take_while(|line| ticker.tick(line.len()));
+
+            let (index, vertex, texcoord, normal, faces, groups, _) = lines.fold(
+                (
+                    Vec::with_capacity(chunk.len() / 30),
+                    Vec::with_capacity(chunk.len() / 30),
+                    Vec::with_capacity(chunk.len() / 30),
+                    Vec::with_capacity(chunk.len() / 30),
+                    0,
+                    0,
+                    left_split_index,
+                ),
+                |(
+                    mut index,
+                    mut vertex,
+                    mut texcoord,
+                    mut normal,
+                    mut faces,
+                    mut groups,
+                    location,
+                ),
+                 line| {
+                    let new_location = location + line.len();
+
+                    if line.starts_with('f') || line.starts_with('g') {
+                        index.push((location, new_location));
+
+                        // Counted for the limits, as `parse_index` will see them
+                        match line.split_whitespace().next() {
+                            Some("f") => faces += 1,
+                            Some("g") => groups += 1,
+                            _ => {}
+                        }
                     } else {
-                        let (_, right) = obj_file.split_at((id + 1) * chunk_size);
-                        (id + 1) * chunk_size + right.find('\n').unwrap()
-                    }
-                };
-
-                let chunk = &obj_file[left_split_index..right_split_index];
-
-                let (index, vertex, texcoord, normal, _) = chunk.split_inclusive('\n').fold(
-                    (
-                        Vec::with_capacity(chunk.len() / 30),
-                        Vec::with_capacity(chunk.len() / 30),
-                        Vec::with_capacity(chunk.len() / 30),
-                        Vec::with_capacity(chunk.len() / 30),
-                        left_split_index,
-                    ),
-                    |(mut index, mut vertex, mut texcoord, mut normal, location), line| {
-                        let new_location = location + line.len();
-
-                        if line.starts_with('f') || line.starts_with('g') {
-                            index.push((location, new_location));
-                        } else {
-                            let extend = match line.split_once(' ') {
-                                Some(("v", _)) => Some(&mut vertex),
-                                Some(("vt", _)) => Some(&mut texcoord),
-                                Some(("vn", _)) => Some(&mut normal),
-                                _ => None,
-                            };
-
-                            if let Some(extend) = extend {
-                                extend.push((location, new_location));
-                            }
+                        let extend = match line.split_once(' ') {
+                            Some(("v", _)) => Some(&mut vertex),
+                            Some(("vt", _)) => Some(&mut texcoord),
+                            Some(("vn", _)) => Some(&mut normal),
+                            _ => None,
+                        };
+
+                        if let Some(extend) = extend {
+                            extend.push((location, new_location));
                         }
+                    }
 
-                        (index, vertex, texcoord, normal, new_location)
-                    },
-                );
+                    (index, vertex, texcoord, normal, faces, groups, new_location)
+                },
+            );
 
-                tx.send(((index, vertex, texcoord, normal), id)).unwrap();
-            },
-        )
-    });
+            tx.send(((index, vertex, texcoord, normal, faces, groups), id))
+                .unwrap();
+        },
+    );
 
-    drop(tx);
+    monitor.check()?;
 
-    let mut messages = Vec::with_capacity(NUM_CORES);
+    let mut messages = Vec::with_capacity(NUM_CHUNKS);
 
     let mut index_len = 0;
     let mut position_len = 0;
     let mut texcoord_len = 0;
     let mut normal_len = 0;
+    let mut faces = 0;
+    // Faces before the first `g` go into a group of their own
+    let mut groups = 1;
 
-    for message in rx {
-        let ((index, position, texcoord, normal), _) = &message;
+    // Every chunk has been sent by the time `run_chunks` returns
+    for message in rx.try_iter() {
+        let ((index, position, texcoord, normal, chunk_faces, chunk_groups), _) = &message;
         index_len += index.len();
         position_len += position.len();
         texcoord_len += texcoord.len();
         normal_len += normal.len();
+        faces += chunk_faces;
+        groups += chunk_groups;
         messages.push(message);
     }
 
-    messages.sort_by(|(_, a_id), (_, b_id)| a_id.cmp(&b_id));
+    // Before `parse_vertex` allocates the vertices and `parse_index` the groups
+    let limits = &monitor.limits;
+    limits.check(
+        Limit::Vertices,
+        position_len.max(texcoord_len).max(normal_len),
+    )?;
+    limits.check(Limit::Faces, faces)?;
+    limits.check(Limit::Groups, groups)?;
+
+    messages.sort_by_key(|(_, id)| *id);
 
     let mut index = Index::new(index_len);
     let mut vertex = Vertex::new(position_len, texcoord_len, normal_len);
 
     for message in messages {
-        let ((index_extend, position, texcoord, normal), _) = message;
+        let ((index_extend, position, texcoord, normal, _, _), _) = message;
 
         index.extend_fit(index_extend);
         vertex.extend_fit(position, texcoord, normal);
     }
 
-    IndexVertexInfo { index, vertex }
+    pad_chunks(&mut index.data);
+    pad_chunks(&mut vertex.position);
+    pad_chunks(&mut vertex.texcoord);
+    pad_chunks(&mut vertex.normal);
+
+    Ok((IndexVertexInfo { index, vertex }, timings))
+}
+
+// Byte search so that `from` does not need to be on a char boundary
+fn next_line_break(obj_file: &str, from: usize) -> usize {
+    let len = obj_file.len();
+
+    if from >= len {
+        return len;
+    }
+
+    obj_file.as_bytes()[from..]
+        .iter()
+        .position(|&byte| byte == b'\n')
+        .map_or(len, |offset| from + offset)
 }
 
 struct IndexVertexInfo {
@@ -143,17 +586,17 @@ struct Index {
 impl Index {
     fn new(length: usize) -> Self {
         let mut index = Index {
-            data: Vec::with_capacity(NUM_CORES),
+            data: Vec::with_capacity(NUM_CHUNKS),
             size: length,
         };
 
-        index.data.push(Vec::with_capacity(length / NUM_CORES + 1));
+        index.data.push(Vec::with_capacity(length / NUM_CHUNKS + 1));
 
         index
     }
 
     fn extend_fit(&mut self, extend_data: Vec<StartEndPair>) {
-        extend_fit(&mut self.data, extend_data, self.size / NUM_CORES + 1);
+        extend_fit(&mut self.data, extend_data, self.size / NUM_CHUNKS + 1);
     }
 }
 
@@ -169,9 +612,9 @@ struct Vertex {
 impl Vertex {
     fn new(position_size: usize, texcoord_size: usize, normal_size: usize) -> Self {
         let mut vertex = Vertex {
-            position: Vec::with_capacity(NUM_CORES),
-            texcoord: Vec::with_capacity(NUM_CORES),
-            normal: Vec::with_capacity(NUM_CORES),
+            position: Vec::with_capacity(NUM_CHUNKS),
+            texcoord: Vec::with_capacity(NUM_CHUNKS),
+            normal: Vec::with_capacity(NUM_CHUNKS),
             position_size,
             texcoord_size,
             normal_size,
@@ -179,13 +622,13 @@ impl Vertex {
 
         vertex
             .position
-            .push(Vec::with_capacity(position_size / NUM_CORES + 1));
+            .push(Vec::with_capacity(position_size / NUM_CHUNKS + 1));
         vertex
             .texcoord
-            .push(Vec::with_capacity(texcoord_size / NUM_CORES + 1));
+            .push(Vec::with_capacity(texcoord_size / NUM_CHUNKS + 1));
         vertex
             .normal
-            .push(Vec::with_capacity(normal_size / NUM_CORES + 1));
+            .push(Vec::with_capacity(normal_size / NUM_CHUNKS + 1));
 
         vertex
     }
@@ -199,17 +642,17 @@ impl Vertex {
         extend_fit(
             &mut self.position,
             extend_position,
-            self.position_size / NUM_CORES + 1,
+            self.position_size / NUM_CHUNKS + 1,
         );
         extend_fit(
             &mut self.texcoord,
             extend_texcoord,
-            self.texcoord_size / NUM_CORES + 1,
+            self.texcoord_size / NUM_CHUNKS + 1,
         );
         extend_fit(
             &mut self.normal,
             extend_normal,
-            self.normal_size / NUM_CORES + 1,
+            self.normal_size / NUM_CHUNKS + 1,
         );
     }
 }
@@ -232,18 +675,24 @@ fn extend_fit(data: &mut Data, extend_data: Vec<(usize, usize)>, fit_size: usize
     last_index.extend(&extend_data[current_index..]);
 }
 
-fn parse_vertex(vertex: Vertex, obj_file: Arc<String>, thread_pool: &ThreadPool) -> VertexData {
-    let Vertex {
-        position_size,
-        texcoord_size,
-        normal_size,
-        position,
-        normal,
-        texcoord,
-        ..
-    } = vertex;
+// Chunks are indexed by their id, so every id needs a chunk, even if empty
+fn pad_chunks(data: &mut Data) {
+    data.resize_with(NUM_CHUNKS.max(data.len()), Vec::new);
+}
+
+fn parse_vertex<F: Float>(
+    vertex: Arc<Vertex>,
+    obj_file: Arc<String>,
+    offset: Option<[f64; 3]>,
+    monitor: &Monitor,
+    executor: &Executor,
+) -> Result<(VertexData<F>, Vec<WorkerTiming>), Box<dyn Error>> {
+    let position_size = vertex.position_size;
+    let texcoord_size = vertex.texcoord_size;
+    let normal_size = vertex.normal_size;
 
-    let mut vertex_data = VertexData::with_capacity(position_size, texcoord_size, normal_size);
+    let mut vertex_data: VertexData<F> =
+        VertexData::with_capacity(position_size, texcoord_size, normal_size);
 
     let position_ptr = FloatPtr(vertex_data.position.as_mut_ptr());
     let texcoord_ptr = FloatPtr(vertex_data.texcoord.as_mut_ptr());
@@ -251,71 +700,89 @@ fn parse_vertex(vertex: Vertex, obj_file: Arc<String>, thread_pool: &ThreadPool)
 
     let (tx, rx) = mpsc::channel();
 
-    let position = Arc::new(position);
-    let texcoord = Arc::new(texcoord);
-    let normal = Arc::new(normal);
-
-    thread_pool.execute(|id| {
-        let obj_file = Arc::clone(&obj_file);
-        let tx = tx.clone();
-        let position_ptr = position_ptr.clone();
-        let texcoord_ptr = texcoord_ptr.clone();
-        let normal_ptr = normal_ptr.clone();
-        let position = Arc::clone(&position);
-        let texcoord = Arc::clone(&texcoord);
-        let normal = Arc::clone(&normal);
-        Box::new(
-            #[inline(never)]
-            move || {
-                let FloatPtr(mut position_ptr) = position_ptr;
-                let FloatPtr(mut texcoord_ptr) = texcoord_ptr;
-                let FloatPtr(mut normal_ptr) = normal_ptr;
+    let worker_monitor = monitor.clone();
+    let worker_file = Arc::clone(&obj_file);
+
+    let timings = executor.run_chunks(
+        NUM_CHUNKS,
+        #[inline(never)]
+        move |id| {
+            let mut ticker = worker_monitor.ticker(Stage::Vertex);
+            let mut error = None;
+
+            let FloatPtr(position_ptr) = position_ptr.clone();
+            let FloatPtr(texcoord_ptr) = texcoord_ptr.clone();
+            let FloatPtr(normal_ptr) = normal_ptr.clone();
+
+            'chunk: for (data, mut ptr, size, components, offset) in [
+                (&vertex.position, position_ptr, position_size, 3, offset),
+                (&vertex.normal, normal_ptr, normal_size, 3, None),
+                (&vertex.texcoord, texcoord_ptr, texcoord_size, 2, None),
+            ] {
+                let chunk = &data[id];
+
+                if chunk.is_empty() {
+                    continue;
+                }
 
                 unsafe {
-                    position_ptr = position_ptr.add(3 * id * (position_size / NUM_CORES + 1));
-                    texcoord_ptr = texcoord_ptr.add(2 * id * (texcoord_size / NUM_CORES + 1));
-                    normal_ptr = normal_ptr.add(3 * id * (normal_size / NUM_CORES + 1));
+                    ptr = ptr.add(components * id * (size / NUM_CHUNKS + 1));
                 }
 
-                for (data, mut ptr) in [
-                    (position, position_ptr),
-                    (normal, normal_ptr),
-                    (texcoord, texcoord_ptr),
-                ] {
-                    if data.len() != NUM_CORES {
-                        continue;
+                for &(start, end) in chunk {
+                    if !ticker.tick(end - start) {
+                        break 'chunk;
                     }
 
-                    for &(start, end) in &data[id] {
-                        let line = &obj_file[start..end].trim();
+                    let line = &worker_file[start..end].trim();
 
-                        if line.is_empty() {
-                            continue;
-                        }
-
-                        let mut parts = line.split_whitespace();
-
-                        parts.next().unwrap();
+                    let mut parts = line.split_whitespace();
 
-                        for num in parts {
-                            let num = num.parse().unwrap();
+                    parts.next();
 
-                            unsafe {
-                                ptr.write(num);
-                                ptr = ptr.add(1);
+                    // Extra components such as `w` or vertex colours are dropped,
+                    // missing ones are zero, so exactly `components` floats are written
+                    for component in 0..components {
+                        let num = match parts.next() {
+                            Some(num) => match offset {
+                                Some(offset) => number::parse_f64(num)
+                                    .map(|parsed| F::from_f64(parsed - offset[component])),
+                                None => F::parse(num),
+                            }
+                            .ok_or(num),
+                            None => Ok(F::default()),
+                        };
+
+                        let num = match num {
+                            Ok(num) => num,
+                            Err(num) => {
+                                // The rest of the chunk stays unwritten, the lengths
+                                // are never set for a parse with errors
+                                error = Some((start, ObjErrorKind::InvalidNumber(num.to_string())));
+                                break 'chunk;
                             }
+                        };
+
+                        unsafe {
+                            ptr.write(num);
+                            ptr = ptr.add(1);
                         }
                     }
                 }
+            }
+
+            tx.send(error).unwrap();
+        },
+    );
 
-                tx.send(()).unwrap();
-            },
-        )
-    });
+    // A stopped parse may have left floats unwritten, so the lengths must not be set
+    monitor.check()?;
 
-    drop(tx);
+    // Every chunk has been written by the time `run_chunks` returns
+    let errors: Vec<_> = rx.try_iter().collect();
+    assert_eq!(errors.len(), NUM_CHUNKS);
 
-    for _ in rx {}
+    first_error(&obj_file, errors.into_iter().flatten())?;
 
     unsafe {
         vertex_data.position.set_len(position_size * 3);
@@ -323,130 +790,212 @@ fn parse_vertex(vertex: Vertex, obj_file: Arc<String>, thread_pool: &ThreadPool)
         vertex_data.normal.set_len(normal_size * 3);
     }
 
-    vertex_data
+    Ok((vertex_data, timings))
 }
 
 #[derive(Clone)]
-struct FloatPtr(*mut f32);
+struct FloatPtr<F>(*mut F);
 
 // No idea if this is actually safe or not
 // Hopefully so
-unsafe impl Send for FloatPtr {}
-unsafe impl Sync for FloatPtr {}
+unsafe impl<F> Send for FloatPtr<F> {}
+unsafe impl<F> Sync for FloatPtr<F> {}
 
-fn parse_index(
+fn parse_index<F: Float>(
     index: Index,
-    vertex_data: VertexData,
+    vertex_lines: Arc<Vertex>,
+    vertex_data: VertexData<F>,
     obj_file: Arc<String>,
-    thread_pool: &ThreadPool,
-) -> Groups {
+    triangulation: Triangulation,
+    monitor: &Monitor,
+    executor: &Executor,
+) -> Result<(Groups<F>, Vec<WorkerTiming>), Box<dyn Error>> {
     let (tx, rx) = mpsc::channel();
+    let worker_monitor = monitor.clone();
+    let worker_file = Arc::clone(&obj_file);
+    let limits_faces = monitor.limits.limits_faces();
+    let corner_bytes = std::mem::size_of::<F>() * 3;
 
     let index = Arc::new(index.data);
     let vertex_data = Arc::new(vertex_data);
+    let vertices = vertex_data.position.len() / 3;
+
+    let timings = executor.run_chunks(
+        NUM_CHUNKS,
+        #[inline(never)]
+        move |id| {
+            let mut ticker = worker_monitor.ticker(Stage::Index);
+            let mut groups = vec![VertexData::new()];
+            let mut indices = vec![];
+            let mut error = None;
+
+            'lines: for &(start, end) in &index[id] {
+                if !ticker.tick(end - start) {
+                    break;
+                }
+
+                let mut parts = worker_file[start..end].split_whitespace();
 
-    thread_pool.execute(|id| {
-        let index = Arc::clone(&index);
-        let obj_file = Arc::clone(&obj_file);
-        let tx = tx.clone();
-        let vertex_data = Arc::clone(&vertex_data);
-        Box::new(
-            #[inline(never)]
-            move || {
-                let mut groups = vec![VertexData::new()];
-
-                for &(start, end) in &index[id] {
-                    let line = &obj_file[start..end].trim();
-
-                    if line.is_empty() {
-                        continue;
+                match parts.next() {
+                    Some("g") => {
+                        groups.push(VertexData::new());
                     }
+                    Some("f") => {
+                        indices.clear();
+
+                        let mut before = None;
+
+                        for corner in parts {
+                            match raw_index(corner) {
+                                Some(raw) => {
+                                    // Only relative indices need the `v` lines before
+                                    let index = if raw < 0 {
+                                        before
+                                            .get_or_insert_with(|| {
+                                                positions_before(&vertex_lines.position, start)
+                                            })
+                                            .checked_sub(raw.unsigned_abs())
+                                    } else {
+                                        Some(raw as usize - 1)
+                                    };
+
+                                    match index.filter(|&index| index < vertices) {
+                                        Some(index) => indices.push(index),
+                                        None => {
+                                            error =
+                                                Some((start, ObjErrorKind::IndexOutOfRange(raw)));
+                                            break 'lines;
+                                        }
+                                    }
+                                }
+                                None => {
+                                    error = Some((
+                                        start,
+                                        ObjErrorKind::InvalidIndex(corner.to_string()),
+                                    ));
+                                    break 'lines;
+                                }
+                            }
+                        }
 
-                    let mut parts = line.split_whitespace();
+                        if limits_faces && !worker_monitor.face(indices.len(), corner_bytes) {
+                            break;
+                        }
 
-                    let keyword = parts.next().unwrap();
+                        let group = groups.last_mut().unwrap();
 
-                    match keyword {
-                        "g" => {
-                            groups.push(VertexData::new());
-                        }
-                        "f" => {
-                            let group = groups.last_mut().unwrap();
-                            let first = parts.next().unwrap();
-
-                            let mut second = parts.next().unwrap();
-                            let mut third;
-
-                            for vertex in parts {
-                                third = vertex;
-                                add_vertex(first, group, &*vertex_data);
-                                add_vertex(second, group, &*vertex_data);
-                                add_vertex(third, group, &*vertex_data);
-                                second = third;
+                        if triangulation == Triangulation::Fan {
+                            for i in 1..indices.len().saturating_sub(1) {
+                                add_vertex(indices[0], group, &vertex_data);
+                                add_vertex(indices[i], group, &vertex_data);
+                                add_vertex(indices[i + 1], group, &vertex_data);
+                            }
+                        } else if let Some(&first) = indices.first() {
+                            let position = |index: usize| {
+                                [0, 1, 2].map(|i| vertex_data.position[index * 3 + i].to_f64())
+                            };
+
+                            // Relative to the first corner, so f64 input keeps its
+                            // precision when narrowed for triangulation
+                            let first = position(first);
+                            let polygon: Vec<[f32; 3]> = indices
+                                .iter()
+                                .map(|&index| {
+                                    let position = position(index);
+                                    [0, 1, 2].map(|i| (position[i] - first[i]) as f32)
+                                })
+                                .collect();
+
+                            for triangle in triangulate(&polygon, triangulation) {
+                                for corner in triangle {
+                                    add_vertex(indices[corner], group, &vertex_data);
+                                }
                             }
                         }
-                        _ => {}
                     }
+                    _ => {}
                 }
+            }
 
-                tx.send((groups, id)).unwrap();
-            },
-        )
-    });
+            tx.send((groups, id, error)).unwrap();
+        },
+    );
 
-    drop(tx);
+    monitor.check()?;
 
-    let mut messages = vec![];
+    let mut messages: Vec<_> = rx.try_iter().collect();
 
-    for message in rx {
-        messages.push(message);
-    }
+    messages.sort_by_key(|(_, id, _)| *id);
 
-    messages.sort_by(|(_, id_a), (_, id_b)| id_a.cmp(&id_b));
+    first_error(
+        &obj_file,
+        messages.iter_mut().filter_map(|(_, _, error)| error.take()),
+    )?;
 
-    let mut groups: Vec<Group> = vec![];
+    let mut groups: Groups<F> = vec![];
 
-    for (group, _) in messages {
+    for (group, _, _) in messages {
         let mut iter = group.into_iter();
         let first = iter.next().unwrap();
-        if let Some(last) = groups.last_mut() {
-            last.extend(first);
+        match groups.last_mut() {
+            Some(last) => last.extend(first),
+            None => groups.push(first),
         }
         groups.extend(iter);
     }
 
-    groups
+    Ok((groups, timings))
 }
 
-fn add_vertex(vert: &str, dst: &mut VertexData, src: &VertexData) {
-    // let mut iter = vert.split('/');
+/// The position index of a face vertex such as `3`, `3/1` or `-1/1/2` as written,
+/// negative for relative ones.
+fn raw_index(vertex: &str) -> Option<isize> {
+    let index = vertex.split('/').next()?;
 
-    let obj_index = vert; //iter.next().unwrap();
-    let obj_index = obj_index.parse::<usize>().unwrap() - 1;
-    dst.position
-        .extend_from_slice(&src.position[obj_index * 3..obj_index * 3 + 3]);
+    let (index, sign) = match index.strip_prefix('-') {
+        Some(index) => (index, -1),
+        None => (index, 1),
+    };
 
-    // if let Some(obj_index) = iter.next() {
-    //     let obj_index = obj_index.parse::<usize>().unwrap() - 1;
-    //     dst.texcoord
-    //         .extend_from_slice(&src.texcoord[obj_index * 2..obj_index * 2 + 2]);
-    // }
+    number::parse_usize(index)
+        .filter(|&index| index > 0)
+        .and_then(|index| isize::try_from(index).ok())
+        .map(|index| sign * index)
+}
 
-    // if let Some(obj_index) = iter.next() {
-    //     let obj_index = obj_index.parse::<usize>().unwrap() - 1;
-    //     dst.normal
-    //         .extend_from_slice(&src.normal[obj_index * 3..obj_index * 3 + 3]);
-    // }
+/// How many `v` lines of `position` start before byte `offset`. The chunks are in
+/// file order, so the one holding `offset` is found by its first line.
+fn positions_before(position: &Data, offset: usize) -> usize {
+    let chunk = position
+        .partition_point(|lines| matches!(lines.first(), Some(&(start, _)) if start < offset));
+
+    match chunk.checked_sub(1) {
+        Some(last) => {
+            position[..last].iter().map(Vec::len).sum::<usize>()
+                + position[last].partition_point(|&(start, _)| start < offset)
+        }
+        None => 0,
+    }
+}
+
+fn out_of_range(index: usize) -> ObjErrorKind {
+    ObjErrorKind::IndexOutOfRange(isize::try_from(index + 1).unwrap_or(isize::MAX))
+}
+
+/// Appends the position at `index`, which must be in range.
+fn add_vertex<F: Float>(index: usize, dst: &mut VertexData<F>, src: &VertexData<F>) {
+    dst.position
+        .extend_from_slice(&src.position[index * 3..index * 3 + 3]);
 }
 
 #[derive(Clone, Debug, PartialEq)]
-pub struct VertexData {
-    pub position: Vec<f32>,
-    pub texcoord: Vec<f32>,
-    pub normal: Vec<f32>,
+pub struct VertexData<F = f32> {
+    pub position: Vec<F>,
+    pub texcoord: Vec<F>,
+    pub normal: Vec<F>,
 }
 
-impl VertexData {
+impl<F> VertexData<F> {
     fn new() -> Self {
         VertexData {
             position: Vec::with_capacity(200),
@@ -463,12 +1012,12 @@ impl VertexData {
         }
     }
 
-    fn extend(&mut self, data: VertexData) {
+    fn extend(&mut self, data: VertexData<F>) {
         self.position.extend(data.position);
         self.texcoord.extend(data.texcoord);
         self.normal.extend(data.normal);
     }
 }
 
-type Group = VertexData;
-type Groups = Vec<Group>;
+pub type Group<F = f32> = VertexData<F>;
+pub type Groups<F = f32> = Vec<Group<F>>;
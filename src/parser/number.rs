@@ -0,0 +1,207 @@
+//! Number parsing specialised for OBJ files, which are almost entirely short
+//! decimals such as `-0.123456`. Those are parsed with Clinger's fast path, when
+//! both the mantissa and the power of ten are exact in the target type, so one
+//! correctly rounded multiplication or division gives the correctly rounded
+//! result. Everything else, long mantissas, large exponents, `inf`, `nan` and
+//! malformed input, falls back to the standard library, so the results are always
+//! exactly those of `str::parse`.
+
+const POW10_F32: [f32; 11] = [1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10];
+
+const POW10_F64: [f64; 23] = [
+    1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10, 1e11, 1e12, 1e13, 1e14, 1e15, 1e16,
+    1e17, 1e18, 1e19, 1e20, 1e21, 1e22,
+];
+
+/// A decimal as `mantissa * 10^exponent`.
+struct Decimal {
+    negative: bool,
+    mantissa: u64,
+    exponent: i32,
+}
+
+/// Splits `[+-]digits[.digits][(e|E)[+-]digits]`. `None` for anything else or when
+/// the mantissa overflows, which sends the input to the slow path.
+#[inline]
+fn decompose(num: &[u8]) -> Option<Decimal> {
+    let len = num.len();
+    let mut i = 0;
+
+    let negative = match num.first()? {
+        b'-' => {
+            i += 1;
+            true
+        }
+        b'+' => {
+            i += 1;
+            false
+        }
+        _ => false,
+    };
+
+    let mut mantissa = 0u64;
+    let digits_start = i;
+
+    while i < len && num[i].is_ascii_digit() {
+        mantissa = mantissa
+            .checked_mul(10)?
+            .checked_add((num[i] - b'0') as u64)?;
+        i += 1;
+    }
+
+    let mut digits = i - digits_start;
+    let mut exponent = 0i32;
+
+    if i < len && num[i] == b'.' {
+        i += 1;
+        let fraction_start = i;
+
+        while i < len && num[i].is_ascii_digit() {
+            mantissa = mantissa
+                .checked_mul(10)?
+                .checked_add((num[i] - b'0') as u64)?;
+            i += 1;
+        }
+
+        digits += i - fraction_start;
+        exponent -= (i - fraction_start) as i32;
+    }
+
+    if digits == 0 {
+        return None;
+    }
+
+    if i < len && (num[i] == b'e' || num[i] == b'E') {
+        i += 1;
+
+        let negative_exponent = match num.get(i)? {
+            b'-' => {
+                i += 1;
+                true
+            }
+            b'+' => {
+                i += 1;
+                false
+            }
+            _ => false,
+        };
+
+        if i == len || len - i > 4 {
+            return None;
+        }
+
+        let mut explicit = 0i32;
+
+        while i < len {
+            if !num[i].is_ascii_digit() {
+                return None;
+            }
+
+            explicit = explicit * 10 + (num[i] - b'0') as i32;
+            i += 1;
+        }
+
+        exponent += if negative_exponent {
+            -explicit
+        } else {
+            explicit
+        };
+    }
+
+    if i == len {
+        Some(Decimal {
+            negative,
+            mantissa,
+            exponent,
+        })
+    } else {
+        None
+    }
+}
+
+#[inline]
+fn fast_f32(num: &str) -> Option<f32> {
+    let Decimal {
+        negative,
+        mantissa,
+        exponent,
+    } = decompose(num.as_bytes())?;
+
+    let value = if mantissa == 0 {
+        0.0
+    } else if mantissa <= 1 << 24 && (-10..=10).contains(&exponent) {
+        let mantissa = mantissa as f32;
+
+        if exponent < 0 {
+            mantissa / POW10_F32[-exponent as usize]
+        } else {
+            mantissa * POW10_F32[exponent as usize]
+        }
+    } else {
+        return None;
+    };
+
+    Some(if negative { -value } else { value })
+}
+
+#[inline]
+fn fast_f64(num: &str) -> Option<f64> {
+    let Decimal {
+        negative,
+        mantissa,
+        exponent,
+    } = decompose(num.as_bytes())?;
+
+    let value = if mantissa == 0 {
+        0.0
+    } else if mantissa <= 1 << 53 && (-22..=22).contains(&exponent) {
+        let mantissa = mantissa as f64;
+
+        if exponent < 0 {
+            mantissa / POW10_F64[-exponent as usize]
+        } else {
+            mantissa * POW10_F64[exponent as usize]
+        }
+    } else {
+        return None;
+    };
+
+    Some(if negative { -value } else { value })
+}
+
+/// `num.parse::<f32>().ok()`, but faster for short decimals.
+#[inline]
+pub fn parse_f32(num: &str) -> Option<f32> {
+    fast_f32(num).or_else(|| num.parse().ok())
+}
+
+/// `num.parse::<f64>().ok()`, but faster for short decimals.
+#[inline]
+pub fn parse_f64(num: &str) -> Option<f64> {
+    fast_f64(num).or_else(|| num.parse().ok())
+}
+
+/// `num.parse::<usize>().ok()`, but faster for plain digits.
+#[inline]
+pub fn parse_usize(num: &str) -> Option<usize> {
+    let bytes = num.as_bytes();
+
+    if bytes.is_empty() {
+        return None;
+    }
+
+    let mut value = 0usize;
+
+    for &byte in bytes {
+        let digit = byte.wrapping_sub(b'0');
+
+        if digit > 9 {
+            return num.parse().ok();
+        }
+
+        // Overflow is an error for std as well
+        value = value.checked_mul(10)?.checked_add(digit as usize)?;
+    }
+
+    Some(value)
+}
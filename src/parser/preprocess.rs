@@ -0,0 +1,392 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use super::limits::{Limit, LimitExceeded, Limits};
+
+pub const DEFAULT_MAX_INCLUDE_DEPTH: usize = 8;
+
+/// Supplies the contents of files referenced by `call` statements.
+pub trait IncludeResolver: Send + Sync {
+    fn resolve(&self, filename: &str) -> io::Result<String>;
+}
+
+impl<F> IncludeResolver for F
+where
+    F: Fn(&str) -> io::Result<String> + Send + Sync,
+{
+    fn resolve(&self, filename: &str) -> io::Result<String> {
+        self(filename)
+    }
+}
+
+/// Resolves `call` filenames relative to a directory on disk. Absolute filenames
+/// and `..` components are refused, so an OBJ file cannot read files outside it.
+pub struct FsResolver {
+    root: PathBuf,
+}
+
+impl FsResolver {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        FsResolver { root: root.into() }
+    }
+}
+
+impl IncludeResolver for FsResolver {
+    fn resolve(&self, filename: &str) -> io::Result<String> {
+        let path = Path::new(filename);
+        let relative = path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+
+        if !relative {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not a path below the include directory", filename),
+            ));
+        }
+
+        fs::read_to_string(self.root.join(path))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MergingGroup {
+    pub id: u32,
+    pub resolution: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FreeFormKind {
+    Curve,
+    Curve2D,
+    Surface,
+}
+
+/// A free-form statement together with the merging group active where it appears.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FreeFormElement {
+    pub kind: FreeFormKind,
+    pub statement: String,
+    pub location: Location,
+    pub merging_group: Option<MergingGroup>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The statement is never executed, e.g. `csh`.
+    Refused,
+    /// A `call` was found but no include resolver was configured.
+    NoIncludeResolver,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SkippedStatement {
+    pub statement: String,
+    pub location: Location,
+    pub reason: SkipReason,
+}
+
+/// Where a statement came from. `file` is `None` for the top-level source.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Location {
+    pub file: Option<String>,
+    pub line: usize,
+}
+
+#[derive(Debug)]
+pub enum PreprocessError {
+    Resolve {
+        filename: String,
+        source: io::Error,
+    },
+    Malformed {
+        statement: String,
+        location: Location,
+    },
+    /// The expanded source would grow past `Limits::max_input_bytes`, or `call`
+    /// statements nest deeper than `Limits::max_include_depth`.
+    Limit(LimitExceeded),
+}
+
+impl fmt::Display for PreprocessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreprocessError::Resolve { filename, source } => {
+                write!(f, "could not resolve call {}: {}", filename, source)
+            }
+            PreprocessError::Malformed {
+                statement,
+                location,
+            } => write!(
+                f,
+                "malformed statement `{}` at line {}",
+                statement, location.line
+            ),
+            PreprocessError::Limit(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for PreprocessError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PreprocessError::Resolve { source, .. } => Some(source),
+            PreprocessError::Limit(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+pub struct Preprocessed {
+    pub source: String,
+    pub free_form: Vec<FreeFormElement>,
+    pub skipped: Vec<SkippedStatement>,
+}
+
+/// Expands `call` statements, records `mg` on the free-form elements that follow it
+/// and strips `csh` so that it can never reach the parser.
+pub fn preprocess(
+    obj_file: &str,
+    resolver: Option<&dyn IncludeResolver>,
+    max_depth: usize,
+) -> Result<Preprocessed, PreprocessError> {
+    let limits = Limits {
+        max_include_depth: max_depth,
+        ..Limits::default()
+    };
+
+    preprocess_limited(obj_file, resolver, &limits)
+}
+
+/// `preprocess` with the include depth of `limits`, failing before the expanded
+/// source grows past `Limits::max_input_bytes`.
+pub fn preprocess_limited(
+    obj_file: &str,
+    resolver: Option<&dyn IncludeResolver>,
+    limits: &Limits,
+) -> Result<Preprocessed, PreprocessError> {
+    let mut state = State {
+        resolver,
+        max_depth: limits.max_include_depth,
+        max_bytes: limits.max_input_bytes,
+        merging_group: None,
+        output: Preprocessed {
+            source: String::with_capacity(obj_file.len()),
+            free_form: vec![],
+            skipped: vec![],
+        },
+    };
+
+    state.expand(obj_file, None, 0)?;
+
+    Ok(state.output)
+}
+
+struct State<'a> {
+    resolver: Option<&'a dyn IncludeResolver>,
+    max_depth: usize,
+    max_bytes: usize,
+    merging_group: Option<MergingGroup>,
+    output: Preprocessed,
+}
+
+impl State<'_> {
+    fn expand(
+        &mut self,
+        obj_file: &str,
+        file: Option<&str>,
+        depth: usize,
+    ) -> Result<(), PreprocessError> {
+        for (number, line) in obj_file.split_inclusive('\n').enumerate() {
+            let location = || Location {
+                file: file.map(String::from),
+                line: number + 1,
+            };
+
+            let statement = line.trim();
+            let mut parts = statement.split_whitespace();
+
+            match parts.next() {
+                Some("csh") => {
+                    self.output.skipped.push(SkippedStatement {
+                        statement: statement.to_string(),
+                        location: location(),
+                        reason: SkipReason::Refused,
+                    });
+                }
+                Some("call") => {
+                    let filename = match parts.next() {
+                        Some(filename) => filename,
+                        None => {
+                            return Err(PreprocessError::Malformed {
+                                statement: statement.to_string(),
+                                location: location(),
+                            })
+                        }
+                    };
+
+                    let resolver = match self.resolver {
+                        Some(resolver) => resolver,
+                        None => {
+                            self.output.skipped.push(SkippedStatement {
+                                statement: statement.to_string(),
+                                location: location(),
+                                reason: SkipReason::NoIncludeResolver,
+                            });
+                            continue;
+                        }
+                    };
+
+                    if depth >= self.max_depth {
+                        return Err(PreprocessError::Limit(LimitExceeded {
+                            limit: Limit::IncludeDepth,
+                            max: self.max_depth,
+                            found: depth + 1,
+                        }));
+                    }
+
+                    let included =
+                        resolver
+                            .resolve(filename)
+                            .map_err(|source| PreprocessError::Resolve {
+                                filename: filename.to_string(),
+                                source,
+                            })?;
+
+                    self.reserve(included.len())?;
+
+                    // Arguments can grow the included text past what was reserved
+                    let args: Vec<_> = parts.collect();
+                    let budget = self.max_bytes.saturating_sub(self.output.source.len());
+                    let included = substitute_args(&included, &args, budget)
+                        .map_err(|len| self.exceeded(len))?;
+
+                    self.expand(&included, Some(filename), depth + 1)?;
+
+                    if !self.output.source.ends_with('\n') {
+                        self.push("\n")?;
+                    }
+                }
+                Some("mg") => {
+                    self.merging_group =
+                        parse_merging_group(parts).ok_or_else(|| PreprocessError::Malformed {
+                            statement: statement.to_string(),
+                            location: location(),
+                        })?;
+                    self.push(line)?;
+                }
+                Some(keyword @ ("curv" | "curv2" | "surf")) => {
+                    let kind = match keyword {
+                        "curv" => FreeFormKind::Curve,
+                        "curv2" => FreeFormKind::Curve2D,
+                        _ => FreeFormKind::Surface,
+                    };
+
+                    self.output.free_form.push(FreeFormElement {
+                        kind,
+                        statement: statement.to_string(),
+                        location: location(),
+                        merging_group: self.merging_group,
+                    });
+                    self.push(line)?;
+                }
+                _ => self.push(line)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn push(&mut self, text: &str) -> Result<(), PreprocessError> {
+        self.reserve(text.len())?;
+        self.output.source.push_str(text);
+
+        Ok(())
+    }
+
+    /// Fails if `bytes` more would not fit in `max_bytes`.
+    fn reserve(&self, bytes: usize) -> Result<(), PreprocessError> {
+        if self.output.source.len().saturating_add(bytes) > self.max_bytes {
+            return Err(self.exceeded(bytes));
+        }
+
+        Ok(())
+    }
+
+    fn exceeded(&self, bytes: usize) -> PreprocessError {
+        PreprocessError::Limit(LimitExceeded {
+            limit: Limit::InputBytes,
+            max: self.max_bytes,
+            found: self.output.source.len().saturating_add(bytes),
+        })
+    }
+}
+
+/// `mg off` and `mg 0` clear the merging group, otherwise `mg id resolution`.
+fn parse_merging_group<'a>(
+    mut parts: impl Iterator<Item = &'a str>,
+) -> Option<Option<MergingGroup>> {
+    let id = parts.next()?;
+
+    if id == "off" {
+        return Some(None);
+    }
+
+    let id = id.parse().ok()?;
+
+    if id == 0 {
+        return Some(None);
+    }
+
+    let resolution = parts.next()?.parse().ok()?;
+
+    Some(Some(MergingGroup { id, resolution }))
+}
+
+/// Replaces `$1`, `$2`, ... with the arguments of the `call`. References to
+/// arguments that were not supplied are left untouched. Stops with the length
+/// reached as soon as the result grows past `max_len`.
+fn substitute_args(included: &str, args: &[&str], max_len: usize) -> Result<String, usize> {
+    if !included.contains('$') {
+        return Ok(included.to_string());
+    }
+
+    let mut result = String::with_capacity(included.len());
+    let mut rest = included;
+
+    while let Some(dollar) = rest.find('$') {
+        result.push_str(&rest[..dollar]);
+        rest = &rest[dollar + 1..];
+
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+
+        let arg = rest[..digits]
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| n.checked_sub(1))
+            .and_then(|n| args.get(n));
+
+        match arg {
+            Some(arg) => result.push_str(arg),
+            None => {
+                result.push('$');
+                result.push_str(&rest[..digits]);
+            }
+        }
+
+        if result.len() > max_len {
+            return Err(result.len());
+        }
+
+        rest = &rest[digits..];
+    }
+
+    result.push_str(rest);
+
+    Ok(result)
+}
@@ -0,0 +1,220 @@
+//! Progress reporting and cancellation for the threaded parser. Workers add to the
+//! counters of a `Progress` and check a `CancellationToken` every
+//! `LINES_PER_CHECK` lines, so neither costs anything per line. The same checks
+//! stop the workers once one of them has exceeded the parse's `Limits`.
+
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+
+use super::limits::{Limit, LimitExceeded, Limits};
+
+const LINES_PER_CHECK: usize = 4096;
+
+/// The parse was stopped through its `CancellationToken`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "parse cancelled")
+    }
+}
+
+impl Error for Cancelled {}
+
+/// Stops a parse from another thread. Clones share the same flag.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Bytes and lines one stage has gone through so far, summed over all workers.
+#[derive(Debug, Default)]
+pub struct StageProgress {
+    bytes: AtomicUsize,
+    lines: AtomicUsize,
+}
+
+impl StageProgress {
+    pub fn bytes(&self) -> usize {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn lines(&self) -> usize {
+        self.lines.load(Ordering::Relaxed)
+    }
+
+    fn add(&self, bytes: usize, lines: usize) {
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+        self.lines.fetch_add(lines, Ordering::Relaxed);
+    }
+}
+
+/// Counters for the three stages of the threaded parser. `extract` goes through
+/// every line of the file, `vertex` through the `v`, `vt` and `vn` lines and
+/// `index` through the `f` and `g` lines, so each stage is done when its counters
+/// reach those totals. Share it with a `ParseOptions` through an `Arc` and read it
+/// from another thread while the parse runs.
+#[derive(Debug, Default)]
+pub struct Progress {
+    pub extract: StageProgress,
+    pub vertex: StageProgress,
+    pub index: StageProgress,
+}
+
+impl Progress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Stage {
+    Extract,
+    Vertex,
+    Index,
+}
+
+/// What the workers of one parse report to and check, cheap to clone into them.
+#[derive(Clone, Default)]
+pub(crate) struct Monitor {
+    pub progress: Option<Arc<Progress>>,
+    pub cancellation: Option<CancellationToken>,
+    pub limits: Limits,
+    /// Output bytes reserved by all workers so far.
+    output: Arc<AtomicUsize>,
+    /// The first limit a worker exceeded.
+    exceeded: Arc<OnceLock<LimitExceeded>>,
+}
+
+impl Monitor {
+    pub fn new(
+        progress: Option<Arc<Progress>>,
+        cancellation: Option<CancellationToken>,
+        limits: Limits,
+    ) -> Self {
+        Monitor {
+            progress,
+            cancellation,
+            limits,
+            ..Monitor::default()
+        }
+    }
+
+    /// Whether the workers should stop, because of a cancellation or an exceeded limit.
+    pub fn is_stopped(&self) -> bool {
+        self.exceeded.get().is_some()
+            || self
+                .cancellation
+                .as_ref()
+                .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// `Err(LimitExceeded)` or `Err(Cancelled)` once the workers should stop.
+    pub fn check(&self) -> Result<(), Box<dyn Error>> {
+        if let Some(exceeded) = self.exceeded.get() {
+            Err(Box::new(exceeded.clone()))
+        } else if self.is_stopped() {
+            Err(Box::new(Cancelled))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Checks `found` against a limit, stopping all workers if it is exceeded.
+    pub fn limit(&self, limit: Limit, found: usize) -> bool {
+        match self.limits.check(limit, found) {
+            Ok(()) => true,
+            Err(exceeded) => {
+                // Only the first worker to exceed a limit reports it
+                let _ = self.exceeded.set(exceeded);
+                false
+            }
+        }
+    }
+
+    /// Checks a face with `corners` corners and reserves the bytes its triangles
+    /// will take, `corner_bytes` each, before they are written.
+    pub fn face(&self, corners: usize, corner_bytes: usize) -> bool {
+        if !self.limit(Limit::VerticesPerFace, corners) {
+            return false;
+        }
+
+        if self.limits.max_output_bytes == usize::MAX {
+            return true;
+        }
+
+        let bytes = corners.saturating_sub(2) * 3 * corner_bytes;
+        let output = self.output.fetch_add(bytes, Ordering::Relaxed) + bytes;
+
+        self.limit(Limit::OutputBytes, output)
+    }
+
+    pub fn ticker(&self, stage: Stage) -> Ticker<'_> {
+        let progress = self.progress.as_deref().map(|progress| match stage {
+            Stage::Extract => &progress.extract,
+            Stage::Vertex => &progress.vertex,
+            Stage::Index => &progress.index,
+        });
+
+        Ticker {
+            monitor: self,
+            progress,
+            bytes: 0,
+            lines: 0,
+        }
+    }
+}
+
+/// Batches the progress of one chunk, flushed every `LINES_PER_CHECK` lines and
+/// when dropped.
+pub(crate) struct Ticker<'a> {
+    monitor: &'a Monitor,
+    progress: Option<&'a StageProgress>,
+    bytes: usize,
+    lines: usize,
+}
+
+impl Ticker<'_> {
+    /// Counts a line of `bytes` bytes, `false` once the parse has been stopped.
+    pub fn tick(&mut self, bytes: usize) -> bool {
+        self.bytes += bytes;
+        self.lines += 1;
+
+        if self.lines < LINES_PER_CHECK {
+            return true;
+        }
+
+        self.flush();
+        !self.monitor.is_stopped()
+    }
+
+    fn flush(&mut self) {
+        if let Some(progress) = self.progress {
+            progress.add(self.bytes, self.lines);
+        }
+
+        self.bytes = 0;
+        self.lines = 0;
+    }
+}
+
+impl Drop for Ticker<'_> {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
@@ -0,0 +1,139 @@
+use std::io::BufRead;
+
+use super::{out_of_range, raw_index, Float, Groups, VertexData};
+use crate::obj::{ObjError, ObjErrorKind};
+use crate::triangulate::{triangulate, Triangulation};
+
+/// Faces of one group as position indices, resolved once every vertex is known
+/// because faces may reference vertices defined further down the file.
+#[derive(Default)]
+struct PendingGroup {
+    corners: Vec<usize>,
+    face_ends: Vec<usize>,
+    lines: Vec<usize>,
+}
+
+/// Parses OBJ line by line from `reader` into the same groups as
+/// `parse_obj_threaded`, without holding the text in memory. This is the front-end
+/// for input that can only be read as a stream, such as compressed files. Like the
+/// threaded parser, malformed input is reported as an `ObjError`, although the
+/// first error in the file may be another one.
+pub fn parse_obj_reader<R: BufRead>(
+    reader: R,
+    triangulation: Triangulation,
+) -> Result<Groups, Box<dyn std::error::Error>> {
+    parse_obj_reader_as(reader, triangulation)
+}
+
+/// `parse_obj_reader` writing `F`, e.g. `parse_obj_reader_as::<_, f64>`.
+pub fn parse_obj_reader_as<R: BufRead, F: Float>(
+    mut reader: R,
+    triangulation: Triangulation,
+) -> Result<Groups<F>, Box<dyn std::error::Error>> {
+    let mut positions: Vec<F> = vec![];
+    let mut groups = vec![PendingGroup::default()];
+    let mut line = String::new();
+    let mut number = 0;
+
+    loop {
+        line.clear();
+
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+
+        number += 1;
+
+        let error = |kind| ObjError { line: number, kind };
+
+        if line.starts_with('f') || line.starts_with('g') {
+            let mut parts = line.split_whitespace();
+
+            match parts.next() {
+                Some("g") => groups.push(PendingGroup::default()),
+                Some("f") => {
+                    let group = groups.last_mut().unwrap();
+
+                    for vertex in parts {
+                        let raw = raw_index(vertex)
+                            .ok_or_else(|| error(ObjErrorKind::InvalidIndex(vertex.to_string())))?;
+
+                        // Relative indices count back from the vertices read so far,
+                        // others may point further down the file
+                        let position = if raw < 0 {
+                            (positions.len() / 3)
+                                .checked_sub(raw.unsigned_abs())
+                                .ok_or_else(|| error(ObjErrorKind::IndexOutOfRange(raw)))?
+                        } else {
+                            raw as usize - 1
+                        };
+
+                        group.corners.push(position);
+                    }
+
+                    group.face_ends.push(group.corners.len());
+                    group.lines.push(number);
+                }
+                _ => {}
+            }
+        } else if let Some(("v", rest)) = line.split_once(' ') {
+            let mut parts = rest.split_whitespace();
+
+            // Like the threaded parser, extra components are dropped and missing ones are zero
+            for _ in 0..3 {
+                let num = match parts.next() {
+                    Some(num) => F::parse(num)
+                        .ok_or_else(|| error(ObjErrorKind::InvalidNumber(num.to_string())))?,
+                    None => F::default(),
+                };
+
+                positions.push(num);
+            }
+        }
+    }
+
+    let mut polygon = vec![];
+
+    let groups: Result<Groups<F>, ObjError> = groups
+        .into_iter()
+        .map(|group| {
+            let mut data = VertexData::new();
+            let mut start = 0;
+
+            for (&end, &line) in group.face_ends.iter().zip(&group.lines) {
+                let corners = &group.corners[start..end];
+                start = end;
+
+                if let Some(&corner) = corners.iter().find(|&&c| c >= positions.len() / 3) {
+                    return Err(ObjError {
+                        line,
+                        kind: out_of_range(corner),
+                    });
+                }
+
+                let position = |corner: usize| &positions[corner * 3..corner * 3 + 3];
+
+                // Relative to the first corner, as in `parse_index`
+                polygon.clear();
+
+                if let Some(&first) = corners.first() {
+                    let first = position(first);
+                    polygon.extend(corners.iter().map(|&corner| {
+                        let position = position(corner);
+                        [0, 1, 2].map(|i| (position[i].to_f64() - first[i].to_f64()) as f32)
+                    }));
+                }
+
+                for triangle in triangulate(&polygon, triangulation) {
+                    for corner in triangle {
+                        data.position.extend_from_slice(position(corners[corner]));
+                    }
+                }
+            }
+
+            Ok(data)
+        })
+        .collect();
+
+    Ok(groups?)
+}
@@ -1,5 +1,12 @@
-use std::sync::mpsc;
+use std::any::Any;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
 
 type Job = Box<dyn FnOnce() + 'static + Send>;
 
@@ -41,6 +48,177 @@ impl ThreadPool {
             self.execute_id(work(id), id);
         }
     }
+
+    /// Runs `work(id)` once on every worker and returns the results in id order.
+    /// Unlike `execute`, the closure may borrow from the caller, because this only
+    /// returns once every worker is done with it. A panicking worker is caught, so
+    /// the pool stays usable, and reported as the `WorkerPanic` of the lowest id.
+    ///
+    /// Calling this from a job running on the same pool deadlocks.
+    pub fn try_scope<'env, T, W>(&self, work: W) -> Result<Vec<T>, WorkerPanic>
+    where
+        W: Fn(usize) -> T + Sync + 'env,
+        T: Send + 'env,
+    {
+        let (tx, rx) = mpsc::channel();
+        let work = &work;
+
+        for id in 0..self.size {
+            let tx = tx.clone();
+            let job: Box<dyn FnOnce() + Send + '_> = Box::new(move || {
+                let result = panic::catch_unwind(AssertUnwindSafe(|| work(id)));
+                let _ = tx.send((id, result));
+            });
+
+            // SAFETY: the loop below only ends once every job has dropped its
+            // sender, which is the last thing a job does, and panics are caught,
+            // so no job outlives the borrows of this call
+            let job: Job = unsafe { mem::transmute(job) };
+            self.execute_id(job, id);
+        }
+
+        drop(tx);
+
+        let mut results: Vec<Option<T>> = (0..self.size).map(|_| None).collect();
+        let mut panicked: Option<WorkerPanic> = None;
+
+        for (id, result) in rx {
+            match result {
+                Ok(result) => results[id] = Some(result),
+                Err(payload) => {
+                    if panicked
+                        .as_ref()
+                        .is_none_or(|panicked| id < panicked.worker)
+                    {
+                        panicked = Some(WorkerPanic {
+                            worker: id,
+                            payload,
+                        });
+                    }
+                }
+            }
+        }
+
+        match panicked {
+            Some(panicked) => Err(panicked),
+            None => Ok(results.into_iter().map(Option::unwrap).collect()),
+        }
+    }
+
+    /// `try_scope`, re-raising a worker's panic in the caller.
+    pub fn scope<'env, T, W>(&self, work: W) -> Vec<T>
+    where
+        W: Fn(usize) -> T + Sync + 'env,
+        T: Send + 'env,
+    {
+        self.try_scope(work)
+            .unwrap_or_else(|panicked| panic::resume_unwind(panicked.into_payload()))
+    }
+
+    /// Runs `task(chunk)` for every chunk in `0..chunks` and blocks until all are
+    /// done. Each worker starts on its own contiguous run of chunks, and once that
+    /// is empty steals from the back of the other workers' runs, so a worker that
+    /// drew cheap chunks keeps busy instead of waiting on the slowest one. A panic
+    /// in a task is re-raised once the other workers have run out of chunks.
+    pub fn run_chunks<T>(&self, chunks: usize, task: T) -> Vec<WorkerTiming>
+    where
+        T: Fn(usize) + Sync,
+    {
+        let size = self.size;
+        let queues: Vec<Mutex<VecDeque<usize>>> = (0..size)
+            .map(|id| Mutex::new((id * chunks / size..(id + 1) * chunks / size).collect()))
+            .collect();
+
+        self.scope(|id| {
+            let start = Instant::now();
+            let mut timing = WorkerTiming {
+                worker: id,
+                ..WorkerTiming::default()
+            };
+
+            loop {
+                let own = queues[id].lock().unwrap().pop_front();
+                let (chunk, stolen) = match own {
+                    Some(chunk) => (chunk, false),
+                    None => match (1..size)
+                        .find_map(|offset| queues[(id + offset) % size].lock().unwrap().pop_back())
+                    {
+                        Some(chunk) => (chunk, true),
+                        None => break,
+                    },
+                };
+
+                let busy = Instant::now();
+                task(chunk);
+                timing.busy += busy.elapsed();
+                timing.chunks += 1;
+
+                if stolen {
+                    timing.stolen += 1;
+                }
+            }
+
+            timing.elapsed = start.elapsed();
+            timing
+        })
+    }
+}
+
+/// A panic caught on a worker of the pool.
+pub struct WorkerPanic {
+    pub worker: usize,
+    payload: Box<dyn Any + Send>,
+}
+
+impl WorkerPanic {
+    /// The panic message, if it was a string.
+    pub fn message(&self) -> Option<&str> {
+        match self.payload.downcast_ref::<&str>() {
+            Some(message) => Some(message),
+            None => self.payload.downcast_ref::<String>().map(String::as_str),
+        }
+    }
+
+    /// The payload, e.g. for `std::panic::resume_unwind`.
+    pub fn into_payload(self) -> Box<dyn Any + Send> {
+        self.payload
+    }
+}
+
+impl fmt::Debug for WorkerPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WorkerPanic")
+            .field("worker", &self.worker)
+            .field("message", &self.message())
+            .finish()
+    }
+}
+
+impl fmt::Display for WorkerPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "worker {} panicked", self.worker)?;
+
+        match self.message() {
+            Some(message) => write!(f, ": {}", message),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Error for WorkerPanic {}
+
+/// What one worker did during `ThreadPool::run_chunks`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WorkerTiming {
+    pub worker: usize,
+    /// Chunks run, including stolen ones.
+    pub chunks: usize,
+    /// Chunks taken from another worker's queue.
+    pub stolen: usize,
+    /// Time spent inside chunks.
+    pub busy: Duration,
+    /// Time from picking up the job until no chunks were left.
+    pub elapsed: Duration,
 }
 
 impl Drop for ThreadPool {
@@ -64,8 +242,10 @@ struct Worker {
 impl Worker {
     fn new(receiver: mpsc::Receiver<Job>) -> Self {
         let thread = thread::spawn(move || {
+            // A panicking job must not take the worker down with it, or every
+            // later job sent to this worker would be lost
             for work in receiver {
-                work();
+                let _ = panic::catch_unwind(AssertUnwindSafe(work));
             }
         });
         Worker {
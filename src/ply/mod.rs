@@ -0,0 +1,693 @@
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+use std::str;
+
+use crate::obj::{Face, FaceState, FaceVertex, Scene, SingleIndexed, VertexAttribute};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScalarType {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl ScalarType {
+    fn from_name(name: &str) -> Option<Self> {
+        let scalar = match name {
+            "char" | "int8" => ScalarType::Int8,
+            "uchar" | "uint8" => ScalarType::UInt8,
+            "short" | "int16" => ScalarType::Int16,
+            "ushort" | "uint16" => ScalarType::UInt16,
+            "int" | "int32" => ScalarType::Int32,
+            "uint" | "uint32" => ScalarType::UInt32,
+            "float" | "float32" => ScalarType::Float32,
+            "double" | "float64" => ScalarType::Float64,
+            _ => return None,
+        };
+
+        Some(scalar)
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ScalarType::Int8 | ScalarType::UInt8 => 1,
+            ScalarType::Int16 | ScalarType::UInt16 => 2,
+            ScalarType::Int32 | ScalarType::UInt32 | ScalarType::Float32 => 4,
+            ScalarType::Float64 => 8,
+        }
+    }
+
+    fn is_integer(self) -> bool {
+        !matches!(self, ScalarType::Float32 | ScalarType::Float64)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum PropertyType {
+    Scalar(ScalarType),
+    List { count: ScalarType, item: ScalarType },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Property {
+    name: String,
+    property_type: PropertyType,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PlyError {
+    InvalidHeader(String),
+    UnexpectedEof,
+    InvalidValue(String),
+    IndexOutOfRange(i64),
+    /// A list property on `vertex`, which no stream or attribute can hold.
+    ListVertexProperty(String),
+    /// `x`, `y` or `z` is not a scalar property of `vertex`.
+    MissingPosition(&'static str),
+}
+
+impl fmt::Display for PlyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlyError::InvalidHeader(line) => write!(f, "invalid PLY header line `{}`", line),
+            PlyError::UnexpectedEof => write!(f, "unexpected end of PLY data"),
+            PlyError::InvalidValue(value) => write!(f, "invalid PLY value `{}`", value),
+            PlyError::IndexOutOfRange(index) => write!(f, "vertex index {} is out of range", index),
+            PlyError::ListVertexProperty(name) => {
+                write!(f, "list vertex property `{}` is not supported", name)
+            }
+            PlyError::MissingPosition(axis) => {
+                write!(f, "vertex element has no scalar `{}` property", axis)
+            }
+        }
+    }
+}
+
+impl Error for PlyError {}
+
+/// Reads ASCII or binary PLY into a `Scene`. `x y z`, `nx ny nz`, `s t` (or `u v`,
+/// `texture_u texture_v`) and `red green blue` map to the scene's streams, integer
+/// colours are scaled to `[0, 1]`. Other vertex properties are kept as named
+/// `VertexAttribute`s, list properties are an error. Faces come from
+/// `vertex_indices` (or `vertex_index`), their other properties and elements other
+/// than `vertex` and `face` are skipped, as are elements without properties.
+pub fn read(ply_file: &[u8]) -> Result<Scene, PlyError> {
+    let (format, elements, body) = parse_header(ply_file)?;
+
+    let mut reader: Box<dyn ValueReader<'_>> = match format {
+        Format::Ascii => Box::new(AsciiReader {
+            tokens: str::from_utf8(body)
+                .map_err(|_| PlyError::InvalidValue("non UTF-8 ASCII body".to_string()))?
+                .split_ascii_whitespace(),
+        }),
+        Format::BinaryLittleEndian => Box::new(BinaryReader {
+            data: body,
+            big_endian: false,
+        }),
+        Format::BinaryBigEndian => Box::new(BinaryReader {
+            data: body,
+            big_endian: true,
+        }),
+    };
+
+    let mut scene = Scene::default();
+    let mut vertex_count = 0;
+    let mut has_texcoord = false;
+    let mut has_normal = false;
+    let mut faces = vec![];
+
+    for element in &elements {
+        // Nothing would be read from the body per item, so an untrusted count
+        // would only spin
+        if element.properties.is_empty() && element.name != "vertex" {
+            continue;
+        }
+
+        match element.name.as_str() {
+            "vertex" => {
+                if let Some(list) = element
+                    .properties
+                    .iter()
+                    .find(|property| matches!(property.property_type, PropertyType::List { .. }))
+                {
+                    return Err(PlyError::ListVertexProperty(list.name.clone()));
+                }
+
+                let streams = VertexStreams::new(&element.properties);
+
+                if let Some(axis) = (0..3).find(|&axis| streams.position[axis].is_none()) {
+                    return Err(PlyError::MissingPosition(["x", "y", "z"][axis]));
+                }
+
+                vertex_count = element.count;
+                has_texcoord = streams.texcoord.iter().all(Option::is_some);
+                has_normal = streams.normal.iter().all(Option::is_some);
+
+                scene.attributes = streams
+                    .extra
+                    .iter()
+                    .map(|&i| VertexAttribute {
+                        name: element.properties[i].name.clone(),
+                        // The header's count is untrusted, but every value takes
+                        // at least a byte of the body
+                        values: Vec::with_capacity(element.count.min(body.len())),
+                    })
+                    .collect();
+
+                let mut values = vec![0.0; element.properties.len()];
+
+                for _ in 0..element.count {
+                    for (value, property) in values.iter_mut().zip(&element.properties) {
+                        if let PropertyType::Scalar(scalar) = property.property_type {
+                            *value = reader.read(scalar)?;
+                        }
+                    }
+
+                    streams.push(&mut scene, &values, &element.properties);
+                }
+            }
+            "face" => {
+                for _ in 0..element.count {
+                    let mut indices = None;
+
+                    for property in &element.properties {
+                        match &property.property_type {
+                            PropertyType::List { count, item }
+                                if property.name == "vertex_indices"
+                                    || property.name == "vertex_index" =>
+                            {
+                                let length = reader.read(*count)? as usize;
+                                let mut list = Vec::with_capacity(length.min(64));
+
+                                for _ in 0..length {
+                                    list.push(reader.read(*item)? as i64);
+                                }
+
+                                indices = Some(list);
+                            }
+                            PropertyType::List { count, item } => {
+                                skip_list(&mut *reader, *count, *item)?
+                            }
+                            PropertyType::Scalar(scalar) => {
+                                reader.read(*scalar)?;
+                            }
+                        }
+                    }
+
+                    if let Some(indices) = indices {
+                        faces.push(indices);
+                    }
+                }
+            }
+            _ => {
+                for _ in 0..element.count {
+                    for property in &element.properties {
+                        match &property.property_type {
+                            PropertyType::Scalar(scalar) => {
+                                reader.read(*scalar)?;
+                            }
+                            PropertyType::List { count, item } => {
+                                skip_list(&mut *reader, *count, *item)?
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    if !faces.is_empty() {
+        scene.states.push(FaceState::default());
+    }
+
+    for indices in faces {
+        let vertices = indices
+            .into_iter()
+            .map(|index| {
+                if index < 0 || index as usize >= vertex_count {
+                    return Err(PlyError::IndexOutOfRange(index));
+                }
+
+                let index = index as usize;
+
+                Ok(FaceVertex {
+                    position: index,
+                    texcoord: if has_texcoord { Some(index) } else { None },
+                    normal: if has_normal { Some(index) } else { None },
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        scene.faces.push(Face { vertices, state: 0 });
+    }
+
+    Ok(scene)
+}
+
+fn skip_list(
+    reader: &mut dyn ValueReader<'_>,
+    count: ScalarType,
+    item: ScalarType,
+) -> Result<(), PlyError> {
+    let length = reader.read(count)? as usize;
+
+    for _ in 0..length {
+        reader.read(item)?;
+    }
+
+    Ok(())
+}
+
+/// Which property feeds which stream, by index into the vertex properties.
+struct VertexStreams {
+    position: [Option<usize>; 3],
+    normal: [Option<usize>; 3],
+    texcoord: [Option<usize>; 2],
+    color: [Option<usize>; 3],
+    extra: Vec<usize>,
+}
+
+impl VertexStreams {
+    fn new(properties: &[Property]) -> Self {
+        let find = |names: &[&str]| {
+            properties.iter().position(|property| {
+                names.contains(&property.name.as_str())
+                    && matches!(property.property_type, PropertyType::Scalar(_))
+            })
+        };
+
+        let position = [find(&["x"]), find(&["y"]), find(&["z"])];
+        let normal = [find(&["nx"]), find(&["ny"]), find(&["nz"])];
+        let texcoord = [
+            find(&["s", "u", "texture_u"]),
+            find(&["t", "v", "texture_v"]),
+        ];
+        let color = [
+            find(&["red", "r"]),
+            find(&["green", "g"]),
+            find(&["blue", "b"]),
+        ];
+
+        let mut known: Vec<usize> = position.iter().flatten().copied().collect();
+
+        for stream in [&normal[..], &texcoord[..], &color[..]] {
+            if stream.iter().all(Option::is_some) {
+                known.extend(stream.iter().flatten());
+            }
+        }
+
+        let extra = (0..properties.len())
+            .filter(|i| {
+                !known.contains(i)
+                    && matches!(properties[*i].property_type, PropertyType::Scalar(_))
+            })
+            .collect();
+
+        VertexStreams {
+            position,
+            normal,
+            texcoord,
+            color,
+            extra,
+        }
+    }
+
+    fn push(&self, scene: &mut Scene, values: &[f64], properties: &[Property]) {
+        let value = |index: Option<usize>| index.map_or(0.0, |index| values[index] as f32);
+
+        scene
+            .position
+            .extend(self.position.iter().map(|&index| value(index)));
+
+        if self.normal.iter().all(Option::is_some) {
+            scene
+                .normal
+                .extend(self.normal.iter().map(|&index| value(index)));
+        }
+
+        if self.texcoord.iter().all(Option::is_some) {
+            scene
+                .texcoord
+                .extend(self.texcoord.iter().map(|&index| value(index)));
+        }
+
+        if self.color.iter().all(Option::is_some) {
+            scene
+                .color
+                .extend(self.color.iter().flatten().map(|&index| {
+                    match properties[index].property_type {
+                        PropertyType::Scalar(ScalarType::UInt8) => values[index] as f32 / 255.0,
+                        PropertyType::Scalar(ScalarType::UInt16) => values[index] as f32 / 65535.0,
+                        _ => values[index] as f32,
+                    }
+                }));
+        }
+
+        for (attribute, &index) in scene.attributes.iter_mut().zip(&self.extra) {
+            attribute.values.push(values[index]);
+        }
+    }
+}
+
+fn parse_header(ply_file: &[u8]) -> Result<(Format, Vec<Element>, &[u8]), PlyError> {
+    let mut format = None;
+    let mut elements: Vec<Element> = vec![];
+    let mut rest = ply_file;
+    let mut first = true;
+
+    loop {
+        let end = rest
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .ok_or(PlyError::UnexpectedEof)?;
+        let line = String::from_utf8_lossy(&rest[..end]);
+        let line = line.trim();
+        rest = &rest[end + 1..];
+
+        let invalid = || PlyError::InvalidHeader(line.to_string());
+        let mut parts = line.split_whitespace();
+
+        if first {
+            if line != "ply" {
+                return Err(invalid());
+            }
+
+            first = false;
+            continue;
+        }
+
+        match parts.next() {
+            Some("format") => {
+                format = Some(match parts.next() {
+                    Some("ascii") => Format::Ascii,
+                    Some("binary_little_endian") => Format::BinaryLittleEndian,
+                    Some("binary_big_endian") => Format::BinaryBigEndian,
+                    _ => return Err(invalid()),
+                });
+            }
+            Some("element") => {
+                let name = parts.next().ok_or_else(invalid)?;
+                let count = parts
+                    .next()
+                    .and_then(|count| count.parse().ok())
+                    .ok_or_else(invalid)?;
+
+                elements.push(Element {
+                    name: name.to_string(),
+                    count,
+                    properties: vec![],
+                });
+            }
+            Some("property") => {
+                let element = elements.last_mut().ok_or_else(invalid)?;
+                let scalar = |name: Option<&str>| name.and_then(ScalarType::from_name);
+
+                let property_type = match parts.next() {
+                    Some("list") => PropertyType::List {
+                        count: scalar(parts.next()).ok_or_else(invalid)?,
+                        item: scalar(parts.next()).ok_or_else(invalid)?,
+                    },
+                    name => PropertyType::Scalar(scalar(name).ok_or_else(invalid)?),
+                };
+
+                if let PropertyType::List { count, .. } = property_type {
+                    if !count.is_integer() {
+                        return Err(invalid());
+                    }
+                }
+
+                element.properties.push(Property {
+                    name: parts.next().ok_or_else(invalid)?.to_string(),
+                    property_type,
+                });
+            }
+            Some("end_header") => break,
+            Some("comment") | Some("obj_info") | None => {}
+            _ => return Err(invalid()),
+        }
+    }
+
+    let format = format.ok_or_else(|| PlyError::InvalidHeader("missing format".to_string()))?;
+
+    Ok((format, elements, rest))
+}
+
+trait ValueReader<'a> {
+    fn read(&mut self, scalar: ScalarType) -> Result<f64, PlyError>;
+}
+
+struct AsciiReader<'a> {
+    tokens: str::SplitAsciiWhitespace<'a>,
+}
+
+impl<'a> ValueReader<'a> for AsciiReader<'a> {
+    fn read(&mut self, scalar: ScalarType) -> Result<f64, PlyError> {
+        let token = self.tokens.next().ok_or(PlyError::UnexpectedEof)?;
+        let invalid = || PlyError::InvalidValue(token.to_string());
+
+        if scalar.is_integer() {
+            token
+                .parse::<i64>()
+                .map(|value| value as f64)
+                .map_err(|_| invalid())
+        } else {
+            token.parse().map_err(|_| invalid())
+        }
+    }
+}
+
+struct BinaryReader<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> ValueReader<'a> for BinaryReader<'a> {
+    fn read(&mut self, scalar: ScalarType) -> Result<f64, PlyError> {
+        let size = scalar.size();
+
+        if self.data.len() < size {
+            return Err(PlyError::UnexpectedEof);
+        }
+
+        let mut bytes = [0; 8];
+        bytes[..size].copy_from_slice(&self.data[..size]);
+        self.data = &self.data[size..];
+
+        if self.big_endian {
+            bytes[..size].reverse();
+        }
+
+        let value = match scalar {
+            ScalarType::Int8 => bytes[0] as i8 as f64,
+            ScalarType::UInt8 => bytes[0] as f64,
+            ScalarType::Int16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            ScalarType::UInt16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            ScalarType::Int32 => {
+                i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64
+            }
+            ScalarType::UInt32 => {
+                u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64
+            }
+            ScalarType::Float32 => {
+                f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64
+            }
+            ScalarType::Float64 => f64::from_le_bytes(bytes),
+        };
+
+        Ok(value)
+    }
+}
+
+/// Writes `scene` as PLY. Positions are `float`, normals `nx ny nz`, texcoords `s t`,
+/// colours `uchar` `red green blue` and `Scene::attributes` `double`.
+///
+/// PLY has one index per vertex, so when faces use texcoord or normal indices that
+/// differ from the position index, each distinct combination becomes its own vertex.
+pub fn write<W: Write>(scene: &Scene, writer: W, format: Format) -> io::Result<()> {
+    let vertices = SingleIndexed::new(scene);
+    let mut writer = io::BufWriter::new(writer);
+
+    let format_name = match format {
+        Format::Ascii => "ascii",
+        Format::BinaryLittleEndian => "binary_little_endian",
+        Format::BinaryBigEndian => "binary_big_endian",
+    };
+
+    writeln!(writer, "ply\nformat {} 1.0", format_name)?;
+    writeln!(writer, "comment written by rust_obj_parser")?;
+    writeln!(writer, "element vertex {}", vertices.corners.len())?;
+    writeln!(
+        writer,
+        "property float x\nproperty float y\nproperty float z"
+    )?;
+
+    if vertices.has_normal {
+        writeln!(
+            writer,
+            "property float nx\nproperty float ny\nproperty float nz"
+        )?;
+    }
+
+    if vertices.has_texcoord {
+        writeln!(writer, "property float s\nproperty float t")?;
+    }
+
+    if vertices.has_color {
+        writeln!(
+            writer,
+            "property uchar red\nproperty uchar green\nproperty uchar blue"
+        )?;
+    }
+
+    for attribute in &scene.attributes {
+        writeln!(writer, "property double {}", attribute.name)?;
+    }
+
+    writeln!(writer, "element face {}", vertices.faces.len())?;
+    writeln!(writer, "property list uchar int vertex_indices")?;
+    writeln!(writer, "end_header")?;
+
+    let mut values = ValueWriter {
+        writer: &mut writer,
+        format,
+        first: true,
+    };
+
+    for corner in &vertices.corners {
+        let position = corner.position * 3;
+
+        for &num in &scene.position[position..position + 3] {
+            values.write_f32(num)?;
+        }
+
+        if let (true, Some(normal)) = (vertices.has_normal, corner.normal) {
+            for &num in &scene.normal[normal * 3..normal * 3 + 3] {
+                values.write_f32(num)?;
+            }
+        }
+
+        if let (true, Some(texcoord)) = (vertices.has_texcoord, corner.texcoord) {
+            for &num in &scene.texcoord[texcoord * 2..texcoord * 2 + 2] {
+                values.write_f32(num)?;
+            }
+        }
+
+        if vertices.has_color {
+            for &num in &scene.color[position..position + 3] {
+                values.write_u8((num.clamp(0.0, 1.0) * 255.0).round() as u8)?;
+            }
+        }
+
+        for attribute in &scene.attributes {
+            values.write_f64(attribute.values[corner.position])?;
+        }
+
+        values.end_line()?;
+    }
+
+    for face in &vertices.faces {
+        let length = u8::try_from(face.len()).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "face has more than 255 vertices",
+            )
+        })?;
+        values.write_u8(length)?;
+
+        for &index in face {
+            let index = i32::try_from(index).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "vertex index does not fit in a PLY int",
+                )
+            })?;
+            values.write_i32(index)?;
+        }
+
+        values.end_line()?;
+    }
+
+    writer.flush()
+}
+
+struct ValueWriter<'a, W: Write> {
+    writer: &'a mut W,
+    format: Format,
+    first: bool,
+}
+
+impl<W: Write> ValueWriter<'_, W> {
+    fn write_ascii(&mut self, value: impl fmt::Display) -> io::Result<()> {
+        if !self.first {
+            self.writer.write_all(b" ")?;
+        }
+
+        self.first = false;
+        write!(self.writer, "{}", value)
+    }
+
+    fn write_bytes<const N: usize>(&mut self, le: [u8; N], be: [u8; N]) -> io::Result<()> {
+        match self.format {
+            Format::BinaryLittleEndian => self.writer.write_all(&le),
+            _ => self.writer.write_all(&be),
+        }
+    }
+
+    fn write_f32(&mut self, value: f32) -> io::Result<()> {
+        match self.format {
+            Format::Ascii => self.write_ascii(value),
+            _ => self.write_bytes(value.to_le_bytes(), value.to_be_bytes()),
+        }
+    }
+
+    fn write_f64(&mut self, value: f64) -> io::Result<()> {
+        match self.format {
+            Format::Ascii => self.write_ascii(value),
+            _ => self.write_bytes(value.to_le_bytes(), value.to_be_bytes()),
+        }
+    }
+
+    fn write_i32(&mut self, value: i32) -> io::Result<()> {
+        match self.format {
+            Format::Ascii => self.write_ascii(value),
+            _ => self.write_bytes(value.to_le_bytes(), value.to_be_bytes()),
+        }
+    }
+
+    fn write_u8(&mut self, value: u8) -> io::Result<()> {
+        match self.format {
+            Format::Ascii => self.write_ascii(value),
+            _ => self.writer.write_all(&[value]),
+        }
+    }
+
+    fn end_line(&mut self) -> io::Result<()> {
+        self.first = true;
+
+        match self.format {
+            Format::Ascii => self.writer.write_all(b"\n"),
+            _ => Ok(()),
+        }
+    }
+}
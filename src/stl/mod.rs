@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::math;
+use crate::obj::{Face, FaceState, FaceVertex, Scene};
+use crate::parser::{Groups, VertexData};
+
+const HEADER_LEN: usize = 80;
+const TRIANGLE_LEN: usize = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Ascii,
+    Binary,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum StlError {
+    Truncated,
+    InvalidAscii { line: usize, message: String },
+}
+
+impl fmt::Display for StlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StlError::Truncated => write!(f, "binary STL is truncated"),
+            StlError::InvalidAscii { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for StlError {}
+
+/// Reads ASCII or binary STL into one triangle soup per solid. Each vertex gets the
+/// facet normal, computed from the positions when the file has a zero normal.
+pub fn read(stl_file: &[u8]) -> Result<Groups, StlError> {
+    match detect_format(stl_file) {
+        Format::Binary => read_binary(stl_file).map(|group| vec![group]),
+        Format::Ascii => {
+            read_ascii(stl_file).map(|solids| solids.into_iter().map(|(_, group)| group).collect())
+        }
+    }
+}
+
+/// Reads STL and welds vertices with bit-identical positions into an indexed
+/// `Scene`, one object per solid.
+pub fn read_indexed(stl_file: &[u8]) -> Result<Scene, StlError> {
+    let solids = match detect_format(stl_file) {
+        Format::Binary => vec![(None, read_binary(stl_file)?)],
+        Format::Ascii => read_ascii(stl_file)?,
+    };
+
+    let mut scene = Scene::default();
+    let mut welded = HashMap::new();
+
+    for (name, solid) in solids {
+        scene.states.push(FaceState {
+            object: name,
+            ..FaceState::default()
+        });
+
+        let state = scene.states.len() - 1;
+
+        for triangle in solid.position.chunks_exact(9) {
+            let vertices = triangle
+                .chunks_exact(3)
+                .map(|position| FaceVertex {
+                    position: weld(&mut scene.position, &mut welded, position),
+                    texcoord: None,
+                    normal: None,
+                })
+                .collect();
+
+            scene.faces.push(Face { vertices, state });
+        }
+    }
+
+    Ok(scene)
+}
+
+pub(crate) fn weld(
+    positions: &mut Vec<f32>,
+    welded: &mut HashMap<[u32; 3], usize>,
+    position: &[f32],
+) -> usize {
+    // + 0.0 turns -0.0 into 0.0 so both weld together
+    let key = [0, 1, 2].map(|i| (position[i] + 0.0).to_bits());
+
+    *welded.entry(key).or_insert_with(|| {
+        positions.extend_from_slice(position);
+        positions.len() / 3 - 1
+    })
+}
+
+/// Binary files may also start with `solid`, so the size is checked first and
+/// ASCII files must not contain NUL bytes.
+pub fn detect_format(stl_file: &[u8]) -> Format {
+    if stl_file.len() >= HEADER_LEN + 4 {
+        let count = u32::from_le_bytes([stl_file[80], stl_file[81], stl_file[82], stl_file[83]]);
+
+        if HEADER_LEN + 4 + count as usize * TRIANGLE_LEN == stl_file.len() {
+            return Format::Binary;
+        }
+    }
+
+    let start = &stl_file[..stl_file.len().min(512)];
+
+    if start.trim_ascii_start().starts_with(b"solid") && !start.contains(&0) {
+        Format::Ascii
+    } else {
+        Format::Binary
+    }
+}
+
+fn read_binary(stl_file: &[u8]) -> Result<VertexData, StlError> {
+    if stl_file.len() < HEADER_LEN + 4 {
+        return Err(StlError::Truncated);
+    }
+
+    let count =
+        u32::from_le_bytes([stl_file[80], stl_file[81], stl_file[82], stl_file[83]]) as usize;
+    let triangles = &stl_file[HEADER_LEN + 4..];
+
+    if triangles.len() / TRIANGLE_LEN < count {
+        return Err(StlError::Truncated);
+    }
+
+    let mut group = VertexData {
+        position: Vec::with_capacity(count * 9),
+        texcoord: vec![],
+        normal: Vec::with_capacity(count * 9),
+    };
+
+    for triangle in triangles.chunks_exact(TRIANGLE_LEN).take(count) {
+        let floats: Vec<f32> = triangle[..48]
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect();
+
+        push_facet(&mut group, &floats[0..3], &floats[3..12]);
+    }
+
+    Ok(group)
+}
+
+fn push_facet(group: &mut VertexData, normal: &[f32], positions: &[f32]) {
+    let normal = math::normalize([normal[0], normal[1], normal[2]])
+        .or_else(|| math::triangle_normal(positions))
+        .unwrap_or([0.0; 3]);
+
+    group.position.extend_from_slice(positions);
+
+    for _ in 0..3 {
+        group.normal.extend_from_slice(&normal);
+    }
+}
+
+fn read_ascii(stl_file: &[u8]) -> Result<Vec<(Option<String>, VertexData)>, StlError> {
+    let stl_file = String::from_utf8_lossy(stl_file);
+    let mut solids = vec![];
+    let mut normal = [0.0; 3];
+    let mut positions = Vec::with_capacity(9);
+
+    for (number, line) in stl_file.lines().enumerate() {
+        let error = |message: &str| StlError::InvalidAscii {
+            line: number + 1,
+            message: message.to_string(),
+        };
+
+        let mut parts = line.split_whitespace();
+
+        match parts.next() {
+            Some("solid") => {
+                let name = parts.collect::<Vec<_>>().join(" ");
+                let name = if name.is_empty() { None } else { Some(name) };
+                solids.push((
+                    name,
+                    VertexData {
+                        position: vec![],
+                        texcoord: vec![],
+                        normal: vec![],
+                    },
+                ));
+            }
+            Some("facet") => {
+                if parts.next() != Some("normal") {
+                    return Err(error("expected `facet normal`"));
+                }
+
+                normal = parse_floats(parts).ok_or_else(|| error("invalid normal"))?;
+                positions.clear();
+            }
+            Some("vertex") => {
+                if positions.len() == 9 {
+                    return Err(error("facet has more than 3 vertices"));
+                }
+
+                let vertex: [f32; 3] =
+                    parse_floats(parts).ok_or_else(|| error("invalid vertex"))?;
+                positions.extend_from_slice(&vertex);
+            }
+            Some("endfacet") => {
+                let (_, group) = solids
+                    .last_mut()
+                    .ok_or_else(|| error("facet outside solid"))?;
+
+                if positions.len() != 9 {
+                    return Err(error("facet does not have 3 vertices"));
+                }
+
+                push_facet(group, &normal, &positions);
+            }
+            _ => {}
+        }
+    }
+
+    Ok(solids)
+}
+
+fn parse_floats<'a>(mut parts: impl Iterator<Item = &'a str>) -> Option<[f32; 3]> {
+    let mut floats = [0.0; 3];
+
+    for float in &mut floats {
+        *float = parts.next()?.parse().ok()?;
+    }
+
+    Some(floats)
+}
+
+/// Writes triangle soups as one STL file. Facet normals are computed from the
+/// positions. Pass `std::slice::from_ref(&group)` for a single group, or
+/// `scene.triangle_soup(&scene.faces)` for an indexed scene.
+pub fn write<W: Write>(groups: &[VertexData], writer: W, format: Format) -> io::Result<()> {
+    let mut writer = io::BufWriter::new(writer);
+
+    let triangles = || {
+        groups
+            .iter()
+            .flat_map(|group| group.position.chunks_exact(9))
+    };
+    let normal = |triangle| math::triangle_normal(triangle).unwrap_or([0.0; 3]);
+
+    match format {
+        Format::Binary => {
+            let mut header = [0; HEADER_LEN];
+            let name = b"binary STL written by rust_obj_parser";
+            header[..name.len()].copy_from_slice(name);
+            writer.write_all(&header)?;
+
+            let count = triangles().count();
+            let count = u32::try_from(count).map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidInput, "too many triangles for STL")
+            })?;
+            writer.write_all(&count.to_le_bytes())?;
+
+            for triangle in triangles() {
+                for num in normal(triangle).iter().chain(triangle) {
+                    writer.write_all(&num.to_le_bytes())?;
+                }
+
+                writer.write_all(&[0, 0])?;
+            }
+        }
+        Format::Ascii => {
+            writeln!(writer, "solid rust_obj_parser")?;
+
+            for triangle in triangles() {
+                let [x, y, z] = normal(triangle);
+                writeln!(writer, "  facet normal {:e} {:e} {:e}", x, y, z)?;
+                writeln!(writer, "    outer loop")?;
+
+                for vertex in triangle.chunks_exact(3) {
+                    writeln!(
+                        writer,
+                        "      vertex {:e} {:e} {:e}",
+                        vertex[0], vertex[1], vertex[2]
+                    )?;
+                }
+
+                writeln!(writer, "    endloop")?;
+                writeln!(writer, "  endfacet")?;
+            }
+
+            writeln!(writer, "endsolid rust_obj_parser")?;
+        }
+    }
+
+    writer.flush()
+}
@@ -0,0 +1,94 @@
+use crate::math;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Triangulation {
+    /// `(0, i, i + 1)`. Fast and exact for convex polygons.
+    #[default]
+    Fan,
+    /// Ear clipping in the polygon's best fit plane. Handles concave polygons and
+    /// falls back to a fan for polygons it cannot clip, e.g. self intersecting ones.
+    EarClipping,
+}
+
+/// Triangles of a polygon as indices into `polygon`, keeping its winding.
+pub fn triangulate(polygon: &[[f32; 3]], triangulation: Triangulation) -> Vec<[usize; 3]> {
+    match triangulation {
+        Triangulation::Fan => fan(polygon.len()),
+        Triangulation::EarClipping if polygon.len() > 3 => {
+            ear_clip(polygon).unwrap_or_else(|| fan(polygon.len()))
+        }
+        Triangulation::EarClipping => fan(polygon.len()),
+    }
+}
+
+fn fan(len: usize) -> Vec<[usize; 3]> {
+    (1..len.saturating_sub(1)).map(|i| [0, i, i + 1]).collect()
+}
+
+fn ear_clip(polygon: &[[f32; 3]]) -> Option<Vec<[usize; 3]>> {
+    let normal = math::normalize(newell_normal(polygon))?;
+
+    // Project onto the plane of the two axes the normal is least aligned with.
+    // Dropping axis k keeps the orientation when the axes stay cyclic (k + 1, k + 2).
+    let dominant = (0..3).max_by(|&a, &b| normal[a].abs().total_cmp(&normal[b].abs()))?;
+    let (x, y) = ((dominant + 1) % 3, (dominant + 2) % 3);
+    let orientation = normal[dominant].signum();
+
+    let points: Vec<[f32; 2]> = polygon.iter().map(|p| [p[x], p[y]]).collect();
+    let cross = |a: usize, b: usize, c: usize| {
+        let (a, b, c) = (points[a], points[b], points[c]);
+        ((b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])) * orientation
+    };
+
+    let mut remaining: Vec<usize> = (0..polygon.len()).collect();
+    let mut triangles = Vec::with_capacity(polygon.len() - 2);
+
+    while remaining.len() > 3 {
+        let len = remaining.len();
+
+        let ear = (0..len).find(|&i| {
+            let prev = remaining[(i + len - 1) % len];
+            let current = remaining[i];
+            let next = remaining[(i + 1) % len];
+
+            cross(prev, current, next) > 0.0
+                && remaining.iter().all(|&other| {
+                    other == prev
+                        || other == current
+                        || other == next
+                        || points[other] == points[prev]
+                        || points[other] == points[current]
+                        || points[other] == points[next]
+                        || cross(prev, current, other) < 0.0
+                        || cross(current, next, other) < 0.0
+                        || cross(next, prev, other) < 0.0
+                })
+        })?;
+
+        triangles.push([
+            remaining[(ear + len - 1) % len],
+            remaining[ear],
+            remaining[(ear + 1) % len],
+        ]);
+        remaining.remove(ear);
+    }
+
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+
+    Some(triangles)
+}
+
+/// The area weighted normal of a possibly non planar polygon.
+fn newell_normal(polygon: &[[f32; 3]]) -> [f32; 3] {
+    let mut normal = [0.0; 3];
+
+    for (i, current) in polygon.iter().enumerate() {
+        let next = polygon[(i + 1) % polygon.len()];
+
+        normal[0] += (current[1] - next[1]) * (current[2] + next[2]);
+        normal[1] += (current[2] - next[2]) * (current[0] + next[0]);
+        normal[2] += (current[0] - next[0]) * (current[1] + next[1]);
+    }
+
+    normal
+}
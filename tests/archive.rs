@@ -0,0 +1,156 @@
+use std::io::{Cursor, Write};
+
+use rust_obj_parser::archive::{
+    Archive, ArchiveError, BrokenReason, BrokenReference, ReferenceKind,
+};
+use rust_obj_parser::parser::limits::{Limit, Limits};
+use zip::write::SimpleFileOptions;
+
+fn zip(entries: &[(&str, &str)]) -> Cursor<Vec<u8>> {
+    let mut writer = zip::ZipWriter::new(Cursor::new(vec![]));
+
+    for (name, contents) in entries {
+        if name.ends_with('/') {
+            writer
+                .add_directory(*name, SimpleFileOptions::default())
+                .unwrap();
+        } else {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+    }
+
+    let mut cursor = writer.finish().unwrap();
+    cursor.set_position(0);
+    cursor
+}
+
+const CAR_OBJ: &str = "mtllib Materials\\CAR.MTL gone.mtl
+v 0 0 0
+v 1 0 0
+v 0 1 0
+usemtl paint
+f 1 2 3
+";
+
+const CAR_MTL: &str = "newmtl paint
+Kd 1 0 0
+map_Kd ..\\Textures\\Paint.PNG
+bump -bm 0.5 ..\\textures\\missing.png
+newmtl glass
+map_d glass.png
+";
+
+fn car_archive() -> Cursor<Vec<u8>> {
+    zip(&[
+        ("car/", ""),
+        ("car/car.obj", CAR_OBJ),
+        ("car/materials/car.mtl", CAR_MTL),
+        ("car/textures/paint.png", "png"),
+        ("car/glass.png", "png"),
+        ("readme.txt", "not a model"),
+    ])
+}
+
+#[test]
+fn finds_obj_entries() {
+    let archive = Archive::new(car_archive()).unwrap();
+
+    assert_eq!(archive.obj_entries().collect::<Vec<_>>(), ["car/car.obj"]);
+    assert!(!archive.entries().contains(&"car/".to_string()));
+}
+
+#[test]
+fn resolves_references_ignoring_case() {
+    let mut archive = Archive::new(car_archive()).unwrap();
+    let assets = archive.load_all().unwrap();
+
+    assert_eq!(assets.len(), 1);
+    let car = &assets[0];
+
+    assert_eq!(car.scene.faces.len(), 1);
+    assert_eq!(car.material_libraries.len(), 1);
+    assert_eq!(car.material_libraries[0].path, "car/materials/car.mtl");
+    assert_eq!(car.material_libraries[0].materials[0].name, "paint");
+
+    let textures: Vec<_> = car
+        .textures
+        .iter()
+        .map(|texture| (texture.material.as_str(), texture.path.as_str()))
+        .collect();
+    // glass.png is not next to the MTL file, but next to the OBJ file.
+    assert_eq!(
+        textures,
+        [
+            ("paint", "car/textures/paint.png"),
+            ("glass", "car/glass.png")
+        ]
+    );
+
+    assert_eq!(archive.read(&car.textures[0].path).unwrap(), b"png");
+}
+
+#[test]
+fn reports_broken_references() {
+    let mut archive = Archive::new(car_archive()).unwrap();
+    let car = archive.load_obj("car/car.obj").unwrap();
+
+    assert_eq!(
+        car.broken_references,
+        [
+            BrokenReference {
+                kind: ReferenceKind::Texture,
+                reference: "..\\textures\\missing.png".to_string(),
+                from: "car/materials/car.mtl".to_string(),
+                reason: BrokenReason::Missing,
+            },
+            BrokenReference {
+                kind: ReferenceKind::MaterialLibrary,
+                reference: "gone.mtl".to_string(),
+                from: "car/car.obj".to_string(),
+                reason: BrokenReason::Missing,
+            },
+        ]
+    );
+}
+
+#[test]
+fn invalid_material_libraries_are_reported() {
+    let mut archive = Archive::new(zip(&[
+        ("model.obj", "mtllib model.mtl ../outside.mtl\n"),
+        ("model.mtl", "Kd 1 0 0\n"),
+    ]))
+    .unwrap();
+
+    let model = archive.load_obj("model.obj").unwrap();
+    let reasons: Vec<_> = model
+        .broken_references
+        .iter()
+        .map(|broken| &broken.reason)
+        .collect();
+
+    assert!(matches!(reasons[0], BrokenReason::Invalid(_)));
+    assert_eq!(reasons[1], &BrokenReason::Missing);
+}
+
+#[test]
+fn entries_are_read_up_to_the_input_limit() {
+    let limits = Limits {
+        max_input_bytes: 40,
+        ..Limits::default()
+    };
+    let mut archive = Archive::with_limits(car_archive(), limits).unwrap();
+
+    assert_eq!(archive.read("car/glass.png").unwrap(), b"png");
+
+    match archive.load_obj("car/car.obj") {
+        Err(ArchiveError::Limit { path, error }) => {
+            assert_eq!(path, "car/car.obj");
+            assert_eq!(error.limit, Limit::InputBytes);
+            assert_eq!(error.found, 41);
+        }
+        other => panic!("{:?}", other),
+    }
+}
@@ -0,0 +1,135 @@
+#![cfg(feature = "async")]
+
+use std::fs;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::pin;
+use std::task::{Context, Waker};
+
+use rust_obj_parser::asynchronous::{load_obj_async, AsyncLoadError};
+use rust_obj_parser::parser;
+
+fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+}
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "rust_obj_parser-async-{}-{}",
+        name,
+        std::process::id()
+    ));
+    fs::create_dir_all(dir.join("materials")).unwrap();
+    dir
+}
+
+const OBJ: &str = "mtllib materials/metal.mtl wood.mtl
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+usemtl steel
+f 1 2 3 4
+";
+
+#[test]
+fn loads_obj_and_material_libraries() {
+    let dir = scratch_dir("load");
+    fs::write(dir.join("model.obj"), OBJ).unwrap();
+    fs::write(
+        dir.join("materials/metal.mtl"),
+        "newmtl steel\nKd 0.5 0.5 0.5\n",
+    )
+    .unwrap();
+    fs::write(dir.join("wood.mtl"), "newmtl oak\nnewmtl pine\n").unwrap();
+
+    let loaded = runtime()
+        .block_on(load_obj_async(dir.join("model.obj")))
+        .unwrap();
+
+    assert_eq!(
+        loaded.groups,
+        parser::parse_obj_threaded(OBJ.to_string()).unwrap()
+    );
+
+    let libraries: Vec<(&str, Vec<&str>)> = loaded
+        .material_libraries
+        .iter()
+        .map(|library| {
+            let names = library
+                .materials
+                .iter()
+                .map(|material| material.name.as_str())
+                .collect();
+            (library.reference.as_str(), names)
+        })
+        .collect();
+    assert_eq!(
+        libraries,
+        [
+            ("materials/metal.mtl", vec!["steel"]),
+            ("wood.mtl", vec!["oak", "pine"])
+        ]
+    );
+    assert_eq!(
+        loaded.material_libraries[0].path,
+        dir.join("materials/metal.mtl")
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn reports_missing_and_malformed_files() {
+    let dir = scratch_dir("errors");
+    fs::write(dir.join("model.obj"), OBJ).unwrap();
+    fs::write(dir.join("materials/metal.mtl"), "Kd 1 1 1\n").unwrap();
+
+    let rt = runtime();
+
+    match rt.block_on(load_obj_async(dir.join("missing.obj"))) {
+        Err(AsyncLoadError::Io { path, .. }) => assert_eq!(path, dir.join("missing.obj")),
+        other => panic!("{:?}", other),
+    }
+
+    match rt.block_on(load_obj_async(dir.join("model.obj"))) {
+        Err(AsyncLoadError::Mtl { path, .. }) | Err(AsyncLoadError::Io { path, .. }) => {
+            assert!(path == dir.join("materials/metal.mtl") || path == dir.join("wood.mtl"))
+        }
+        other => panic!("{:?}", other),
+    }
+
+    fs::write(dir.join("bad.obj"), "v 0 0 0\nf 1 2 3\n").unwrap();
+    match rt.block_on(load_obj_async(dir.join("bad.obj"))) {
+        Err(AsyncLoadError::Obj { error, .. }) => assert_eq!(error.line, 2),
+        other => panic!("{:?}", other),
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn dropping_the_future_cancels_the_load() {
+    let dir = scratch_dir("cancel");
+    fs::write(dir.join("model.obj"), OBJ).unwrap();
+    fs::write(dir.join("materials/metal.mtl"), "newmtl steel\n").unwrap();
+    fs::write(dir.join("wood.mtl"), "newmtl oak\n").unwrap();
+
+    let rt = runtime();
+
+    {
+        let _guard = rt.enter();
+        let mut load = pin!(load_obj_async(dir.join("model.obj")));
+        let mut context = Context::from_waker(Waker::noop());
+
+        assert!(load.as_mut().poll(&mut context).is_pending());
+    }
+
+    // The runtime is unaffected by the abandoned load.
+    let loaded = rt.block_on(load_obj_async(dir.join("model.obj"))).unwrap();
+    assert_eq!(loaded.material_libraries.len(), 2);
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,155 @@
+use std::borrow::Cow;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use rust_obj_parser::cache::{self, CacheError, SourceInfo};
+use rust_obj_parser::parser;
+use rust_obj_parser::parser::limits::{Limit, LimitExceeded, Limits};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rust_obj_parser-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn al_cache() -> (parser::Groups, SourceInfo, Vec<u8>) {
+    let obj_file = fs::read_to_string("al.obj").unwrap();
+    let source = SourceInfo::new(obj_file.as_bytes(), Some(SystemTime::now()));
+    let groups = parser::parse_obj_threaded(obj_file).unwrap();
+
+    let mut bytes = vec![];
+    cache::write(&groups, &source, &mut bytes).unwrap();
+
+    (groups, source, bytes)
+}
+
+#[test]
+fn round_trip() {
+    let (groups, source, bytes) = al_cache();
+    let cache = cache::load(&bytes).unwrap();
+
+    assert_eq!(cache.source, source);
+    assert_eq!(cache.to_groups(), groups);
+}
+
+#[test]
+fn aligned_bytes_are_borrowed() {
+    let (_, _, bytes) = al_cache();
+
+    // A u32 buffer guarantees the alignment a plain Vec<u8> only has in practice.
+    let mut words = vec![0u32; bytes.len() / 4 + 1];
+    let aligned =
+        unsafe { std::slice::from_raw_parts_mut(words.as_mut_ptr() as *mut u8, bytes.len()) };
+    aligned.copy_from_slice(&bytes);
+
+    let cache = cache::load(aligned).unwrap();
+
+    if cfg!(target_endian = "little") {
+        assert!(matches!(cache.groups[0].position, Cow::Borrowed(_)));
+    }
+
+    // Misaligned bytes still load, by copying.
+    let mut shifted = vec![0u8];
+    shifted.extend_from_slice(&bytes);
+    let misaligned = cache::load(&shifted[1..]).unwrap();
+    assert_eq!(misaligned, cache);
+}
+
+#[test]
+fn corruption_is_detected() {
+    let (_, _, mut bytes) = al_cache();
+
+    assert_eq!(cache::load(&bytes[..40]), Err(CacheError::Truncated));
+    assert_eq!(
+        cache::load(b"not a cache at all"),
+        Err(CacheError::InvalidMagic)
+    );
+
+    let last = bytes.len() - 1;
+    bytes[last] ^= 1;
+    assert_eq!(cache::load(&bytes), Err(CacheError::ChecksumMismatch));
+
+    bytes[8] = 2;
+    assert_eq!(cache::load(&bytes), Err(CacheError::UnsupportedVersion(2)));
+}
+
+#[test]
+fn missing_modification_time() {
+    let source = SourceInfo::new(b"v 0 0 0\n", None);
+
+    let mut bytes = vec![];
+    cache::write(&[], &source, &mut bytes).unwrap();
+
+    let cache = cache::load(&bytes).unwrap();
+    assert_eq!(cache.source.modified, None);
+    assert!(cache.groups.is_empty());
+}
+
+#[test]
+fn load_or_parse_invalidates_on_change() {
+    let dir = temp_dir("cache");
+    let obj_path = dir.join("triangle.obj");
+    let cache_path = dir.join("triangle.cache");
+
+    fs::write(&obj_path, "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+
+    let parsed = cache::load_or_parse(&obj_path, &cache_path).unwrap();
+    assert!(cache_path.exists());
+    assert_eq!(
+        cache::load_or_parse(&obj_path, &cache_path).unwrap(),
+        parsed
+    );
+
+    let cached = fs::read(&cache_path).unwrap();
+    assert!(cache::load(&cached)
+        .unwrap()
+        .source
+        .is_fresh_for(&obj_path)
+        .unwrap());
+
+    // Same size, different contents.
+    fs::write(&obj_path, "v 0 0 0\nv 2 0 0\nv 0 2 0\nf 1 2 3\n").unwrap();
+    let file = fs::File::options().write(true).open(&obj_path).unwrap();
+    file.set_modified(SystemTime::now() + Duration::from_secs(10))
+        .unwrap();
+
+    let reparsed = cache::load_or_parse(&obj_path, &cache_path).unwrap();
+    assert_eq!(reparsed[0].position[3], 2.0);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn load_or_parse_applies_limits() {
+    let dir = temp_dir("cache-limits");
+    let obj_path = dir.join("quad.obj");
+    let cache_path = dir.join("quad.cache");
+
+    fs::write(&obj_path, "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4\n").unwrap();
+
+    let limits = Limits {
+        max_vertices_per_face: 3,
+        ..Limits::default()
+    };
+    let error = cache::load_or_parse_limited(&obj_path, &cache_path, &limits)
+        .unwrap_err()
+        .downcast::<LimitExceeded>()
+        .unwrap();
+
+    assert_eq!(error.limit, Limit::VerticesPerFace);
+    assert!(!cache_path.exists());
+
+    let limits = Limits {
+        max_input_bytes: 10,
+        ..Limits::default()
+    };
+    let error = cache::load_or_parse_limited(&obj_path, &cache_path, &limits)
+        .unwrap_err()
+        .downcast::<LimitExceeded>()
+        .unwrap();
+
+    assert_eq!(error.limit, Limit::InputBytes);
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,139 @@
+use rust_obj_parser::mesh;
+use rust_obj_parser::mesh::cleanup::{self, CleanupOptions, CleanupReport};
+use rust_obj_parser::obj;
+
+// Every face has its own corners, slightly off on the top
+const SPLIT_CUBE: &str = "v 0 0 0\nv 0 1 0\nv 1 1 0\nv 1 0 0\nf 1 2 3 4\n\
+                          v 0 0 1\nv 1 0 1\nv 1 1 1.0000001\nv 0 1 1\nf 5 6 7 8\n\
+                          v 0 0 0\nv 1 0 0\nv 1 0 1\nv 0 0 1\nf 9 10 11 12\n\
+                          v 1 0 0\nv 1 1 0\nv 1 1 1\nv 1 0 1\nf 13 14 15 16\n\
+                          v 1 1 0\nv 0 1 0\nv 0 1 1\nv 1 1 1\nf 17 18 19 20\n\
+                          v 0 1 0\nv 0 0 0\nv 0 0 1\nv 0 1 1\nf 21 22 23 24\n";
+
+fn clean(obj_file: &str, options: &CleanupOptions) -> (obj::Scene, CleanupReport) {
+    let mut scene = obj::parse(obj_file).unwrap();
+    let report = cleanup::clean(&mut scene, options);
+
+    (scene, report)
+}
+
+#[test]
+fn welding_closes_a_mesh() {
+    let before = mesh::analyze_scene(&obj::parse(SPLIT_CUBE).unwrap());
+    assert_eq!(before.boundary_edges, 24);
+
+    let (scene, report) = clean(SPLIT_CUBE, &CleanupOptions::default());
+
+    assert_eq!(report.welded_positions, 16);
+    assert_eq!(report.removed_positions, 16);
+    assert_eq!(report.degenerate_faces, 0);
+    assert_eq!(scene.position_count(), 8);
+    assert_eq!(scene.faces.len(), 6);
+    assert_eq!(&scene.position[..6], &[0.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+
+    let after = mesh::analyze_scene(&scene);
+    assert!(after.is_closed_manifold(), "{:?}", after);
+    assert_eq!(after.unused_vertices, 0);
+
+    // Without an epsilon the slightly off corner stays apart
+    let exact = CleanupOptions {
+        epsilon: 0.0,
+        ..CleanupOptions::default()
+    };
+    let (scene, report) = clean(SPLIT_CUBE, &exact);
+
+    assert_eq!(report.welded_positions, 15);
+    assert_eq!(scene.position_count(), 9);
+    assert_eq!(mesh::analyze_scene(&scene).boundary_edges, 4);
+}
+
+#[test]
+fn degenerate_duplicate_and_unused() {
+    let obj_file = "v 0 0 0\nv 1 0 0\nv 2 0 0\nv 0 1 0\nv nan 0 0\nv 9 9 9\nv 0 1e-9 0\n\
+                    vt 0 0\nvt 1 1\nvt 5 5\nvn -0 0 1\nvn 0 0 1\nvn 0 0 1\n\
+                    f 1 2 3\nf 1 7 4\nf 1/1 2/1 4/2 7/1\nf 4 2 1\nf 1//3 2//2 5//1\n";
+    let (scene, report) = clean(obj_file, &CleanupOptions::default());
+
+    assert_eq!(
+        report,
+        CleanupReport {
+            welded_positions: 1,
+            welded_texcoords: 0,
+            welded_normals: 2,
+            degenerate_faces: 2,
+            duplicate_faces: 1,
+            removed_positions: 3,
+            removed_texcoords: 1,
+            removed_normals: 2,
+        }
+    );
+
+    // The quad lost its welded corner
+    assert_eq!(scene.faces.len(), 2);
+    assert_eq!(scene.faces[0].vertices.len(), 3);
+    assert_eq!(scene.position_count(), 4);
+    assert_eq!(scene.texcoord, vec![0.0, 0.0, 1.0, 1.0]);
+    assert_eq!(scene.normal, vec![0.0, 0.0, 1.0]);
+
+    let face = &scene.faces[1];
+    assert_eq!(face.vertices[0].normal, Some(0));
+    assert_eq!(face.vertices[1].normal, Some(0));
+    assert!(scene.position[face.vertices[2].position * 3].is_nan());
+
+    // Everything can be turned off
+    let nothing = CleanupOptions {
+        epsilon: 0.0,
+        remove_degenerate_faces: false,
+        remove_duplicate_faces: false,
+        remove_unused: false,
+        ..CleanupOptions::default()
+    };
+    let (scene, report) = clean(obj_file, &nothing);
+
+    assert_eq!(report.welded_normals, 2);
+    assert_eq!(report.degenerate_faces + report.duplicate_faces, 0);
+    assert_eq!(scene.faces.len(), 5);
+    assert_eq!(scene.position_count(), 7);
+}
+
+#[test]
+fn attributes_can_keep_vertices_apart() {
+    // Two quads with their own texcoords and colours on the shared edge
+    let obj_file = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+                    v 1 0 0 1 0 0\nv 2 0 0\nv 2 1 0\nv 1 1 0\n\
+                    vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\nvt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\n\
+                    f 1/1 2/2 3/3 4/4\nf 5/5 6/6 7/7 8/8\n";
+
+    let (scene, report) = clean(obj_file, &CleanupOptions::default());
+
+    assert_eq!(report.welded_positions, 2);
+    assert_eq!(report.welded_texcoords, 4);
+    assert_eq!(scene.position_count(), 6);
+    assert_eq!(scene.color.len(), 6 * 3);
+    assert_eq!(mesh::analyze_scene(&scene).boundary_edges, 6);
+
+    let respect = CleanupOptions {
+        respect_attributes: true,
+        ..CleanupOptions::default()
+    };
+    let (scene, report) = clean(obj_file, &respect);
+
+    assert_eq!(report.welded_positions, 0);
+    assert_eq!(scene.position_count(), 8);
+
+    // With the same texcoords only the colour keeps one of them apart
+    let shared = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+                  v 1 0 0 1 0 0\nv 2 0 0\nv 2 1 0\nv 1 1 0\n\
+                  vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\nvt 2 0\nvt 2 1\n\
+                  f 1/1 2/2 3/3 4/4\nf 5/2 6/5 7/6 8/3\n";
+    let (scene, report) = clean(shared, &respect);
+
+    assert_eq!(report.welded_positions, 1);
+    assert_eq!(scene.position_count(), 7);
+
+    let same_color = shared.replace("v 1 0 0 1 0 0", "v 1 0 0 1 1 1");
+    let (scene, report) = clean(&same_color, &respect);
+
+    assert_eq!(report.welded_positions, 2);
+    assert_eq!(scene.position_count(), 6);
+}
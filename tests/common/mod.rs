@@ -0,0 +1,36 @@
+// Each test crate that declares `mod common` compiles its own copy and uses only
+// part of it.
+#![allow(dead_code)]
+
+/// A small deterministic generator for the randomized tests.
+pub struct XorShift(pub u64);
+
+impl XorShift {
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+
+    /// Up to `max` decimal digits.
+    pub fn digits(&mut self, max: u64) -> String {
+        (0..self.below(max + 1))
+            .map(|_| char::from(b'0' + self.below(10) as u8))
+            .collect()
+    }
+
+    /// Any finite `f32`, by its bits.
+    pub fn float(&mut self) -> f32 {
+        loop {
+            let num = f32::from_bits(self.next() as u32);
+            if num.is_finite() {
+                return num;
+            }
+        }
+    }
+}
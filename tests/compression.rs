@@ -0,0 +1,180 @@
+use std::fs;
+use std::io::Write;
+
+use rust_obj_parser::obj::{ObjError, ObjErrorKind};
+use rust_obj_parser::parser::compression::Compression;
+use rust_obj_parser::parser::limits::{Limit, LimitExceeded, Limits};
+use rust_obj_parser::parser::{self, sequential, Origin, ParseOptions};
+use rust_obj_parser::triangulate::Triangulation;
+
+fn compressed(obj_file: &[u8], compression: Compression) -> Vec<u8> {
+    match compression {
+        Compression::None => obj_file.to_vec(),
+        Compression::Gzip => {
+            let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+            encoder.write_all(obj_file).unwrap();
+            encoder.finish().unwrap()
+        }
+        Compression::Zstd => zstd::encode_all(obj_file, 3).unwrap(),
+        Compression::Bzip2 => {
+            let mut encoder = bzip2::write::BzEncoder::new(vec![], bzip2::Compression::default());
+            encoder.write_all(obj_file).unwrap();
+            encoder.finish().unwrap()
+        }
+    }
+}
+
+#[test]
+fn compressed_files_parse_like_plain_ones() {
+    let obj_file = fs::read_to_string("al.obj").unwrap();
+    let expected = parser::parse_obj_threaded(obj_file.clone()).unwrap();
+
+    let dir = std::env::temp_dir().join(format!(
+        "rust_obj_parser-compression-{}",
+        std::process::id()
+    ));
+    fs::create_dir_all(&dir).unwrap();
+
+    for (compression, extension) in [
+        (Compression::None, "obj"),
+        (Compression::Gzip, "obj.gz"),
+        (Compression::Zstd, "obj.zst"),
+        (Compression::Bzip2, "obj.bz2"),
+    ] {
+        let bytes = compressed(obj_file.as_bytes(), compression);
+        assert_eq!(Compression::detect(&bytes), compression);
+
+        // Detection is by content, not by extension.
+        let path = dir.join(format!("al.{}", extension));
+        fs::write(&path, &bytes).unwrap();
+
+        assert_eq!(
+            parser::parse_obj_file(&path).unwrap(),
+            expected,
+            "{:?}",
+            compression
+        );
+        assert_eq!(
+            parser::parse_obj_bytes(bytes).unwrap(),
+            expected,
+            "{:?}",
+            compression
+        );
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn compressed_files_take_options() {
+    let obj_file = fs::read_to_string("al.obj").unwrap();
+    let options = ParseOptions {
+        triangulation: Triangulation::EarClipping,
+        origin: Some(Origin::FirstVertex),
+        ..ParseOptions::default()
+    };
+    let expected = parser::parse_obj_with_options_as::<f64>(&obj_file, &options).unwrap();
+
+    let path = std::env::temp_dir().join(format!(
+        "rust_obj_parser-compression-options-{}.obj.zst",
+        std::process::id()
+    ));
+    let bytes = compressed(obj_file.as_bytes(), Compression::Zstd);
+    fs::write(&path, &bytes).unwrap();
+
+    let from_file = parser::parse_obj_file_with_options_as::<f64, _>(&path, &options).unwrap();
+    let from_bytes = parser::parse_obj_bytes_with_options_as::<f64>(&bytes, &options).unwrap();
+
+    for parsed in [from_file, from_bytes] {
+        assert_eq!(parsed.groups, expected.groups);
+        assert_eq!(parsed.origin, expected.origin);
+    }
+
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn decompression_stops_at_the_input_limit() {
+    let max = 1 << 20;
+    let options = ParseOptions {
+        limits: Limits {
+            max_input_bytes: max,
+            ..Limits::default()
+        },
+        ..ParseOptions::default()
+    };
+
+    // A few kilobytes that decode to 16 MiB, and a plain file just over the limit
+    let bomb = compressed(&vec![b'#'; 16 << 20], Compression::Gzip);
+    let plain = vec![b'#'; max + 10];
+
+    for bytes in [bomb, plain] {
+        let exceeded = parser::parse_obj_bytes_with_options(&bytes, &options)
+            .unwrap_err()
+            .downcast::<LimitExceeded>()
+            .unwrap();
+
+        assert_eq!(
+            *exceeded,
+            LimitExceeded {
+                limit: Limit::InputBytes,
+                max,
+                found: max + 1,
+            }
+        );
+    }
+}
+
+#[test]
+fn concatenated_gzip_members() {
+    let mut bytes = compressed(b"v 0 0 0\nv 1 0 0\n", Compression::Gzip);
+    bytes.extend(compressed(b"v 0 1 0\nf 1 2 3\n", Compression::Gzip));
+
+    let groups = parser::parse_obj_bytes(bytes).unwrap();
+    assert_eq!(
+        groups[0].position,
+        [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]
+    );
+}
+
+#[test]
+fn sequential_matches_threaded() {
+    let obj_file = "f 3 1 2\nv 0 0 0\nv 1 0 0 1\nv 0 1\ng a\nf 1 2 3 1\ng\ng b\nf 2 3 1\n";
+
+    let threaded = parser::parse_obj_threaded(obj_file.to_string()).unwrap();
+    let sequential = sequential::parse_obj_reader(obj_file.as_bytes(), Triangulation::Fan).unwrap();
+
+    assert_eq!(sequential, threaded);
+}
+
+#[test]
+fn sequential_reports_errors() {
+    let error = |obj_file: &str| {
+        *sequential::parse_obj_reader(obj_file.as_bytes(), Triangulation::Fan)
+            .unwrap_err()
+            .downcast::<ObjError>()
+            .unwrap()
+    };
+
+    assert_eq!(
+        error("v 0 0 zero\n"),
+        ObjError {
+            line: 1,
+            kind: ObjErrorKind::InvalidNumber("zero".to_string())
+        }
+    );
+    assert_eq!(
+        error("v 0 0 0\nf 1 2 0\n"),
+        ObjError {
+            line: 2,
+            kind: ObjErrorKind::InvalidIndex("0".to_string())
+        }
+    );
+    assert_eq!(
+        error("v 0 0 0\n\nf 1 1 2\n"),
+        ObjError {
+            line: 3,
+            kind: ObjErrorKind::IndexOutOfRange(2)
+        }
+    );
+}
@@ -0,0 +1,203 @@
+//! Snapshots of what the parsers make of the files in `tests/corpus`. Each
+//! `name.obj` or `name.mtl` has its expected output in `name.json` next to it.
+//! After an intended change, rewrite them with
+//! `UPDATE_SNAPSHOTS=1 cargo test --test conformance` and review the diff.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rust_obj_parser::mtl::{self, Color, Material, TextureMap};
+use rust_obj_parser::obj::{self, FaceVertex, Scene};
+use rust_obj_parser::parser::{self, ObjData, ParseOptions};
+use serde_json::{json, Value};
+
+#[test]
+fn obj_corpus() {
+    for path in corpus("obj") {
+        let obj_file = fs::read_to_string(&path).unwrap();
+
+        let scene = match obj::parse(&obj_file) {
+            Ok(scene) => scene_json(&scene),
+            Err(error) => json!({ "error": error.to_string() }),
+        };
+
+        let threaded = match parser::parse_obj_with_options(&obj_file, &ParseOptions::default()) {
+            Ok(obj_data) => obj_data_json(&obj_data),
+            Err(error) => json!({ "error": error.to_string() }),
+        };
+
+        check_snapshot(&path, json!({ "scene": scene, "threaded": threaded }));
+    }
+}
+
+#[test]
+fn mtl_corpus() {
+    for path in corpus("mtl") {
+        let mtl_file = fs::read_to_string(&path).unwrap();
+
+        let materials = match mtl::parse(&mtl_file) {
+            Ok(materials) => Value::Array(materials.iter().map(material_json).collect()),
+            Err(error) => json!({ "error": error.to_string() }),
+        };
+
+        check_snapshot(&path, materials);
+    }
+}
+
+fn corpus(extension: &str) -> Vec<PathBuf> {
+    let mut paths: Vec<_> = fs::read_dir(Path::new("tests/corpus").join(extension))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == extension))
+        .collect();
+
+    paths.sort();
+    assert!(!paths.is_empty());
+
+    paths
+}
+
+fn check_snapshot(path: &Path, actual: Value) {
+    let snapshot = path.with_extension("json");
+    let actual = serde_json::to_string_pretty(&actual).unwrap() + "\n";
+
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::write(&snapshot, actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&snapshot).unwrap_or_else(|_| {
+        panic!(
+            "{} has no snapshot, run with UPDATE_SNAPSHOTS=1",
+            path.display()
+        )
+    });
+
+    assert_eq!(actual, expected, "{}", path.display());
+}
+
+/// The shortest decimal that reads back as the same `f32`, rather than its exact
+/// `f64` value. JSON has no NaN or infinity, those are strings.
+fn num(num: f32) -> Value {
+    if num.is_finite() {
+        json!(num.to_string().parse::<f64>().unwrap())
+    } else {
+        json!(num.to_string())
+    }
+}
+
+fn nums(nums: &[f32]) -> Value {
+    Value::Array(nums.iter().map(|&n| num(n)).collect())
+}
+
+fn scene_json(scene: &Scene) -> Value {
+    let face_vertex =
+        |vertex: &FaceVertex| json!([vertex.position, vertex.texcoord, vertex.normal]);
+
+    json!({
+        "material_libraries": scene.material_libraries,
+        "position": nums(&scene.position),
+        "texcoord": nums(&scene.texcoord),
+        "normal": nums(&scene.normal),
+        "color": nums(&scene.color),
+        "faces": scene.faces.iter().map(|face| json!({
+            "vertices": face.vertices.iter().map(face_vertex).collect::<Vec<_>>(),
+            "state": face.state,
+        })).collect::<Vec<_>>(),
+        "states": scene.states.iter().map(|state| json!({
+            "object": state.object,
+            "groups": state.groups,
+            "material": state.material,
+            "smoothing_group": state.smoothing_group,
+        })).collect::<Vec<_>>(),
+    })
+}
+
+fn obj_data_json(obj_data: &ObjData) -> Value {
+    json!({
+        "groups": obj_data.groups.iter().map(|group| json!({
+            "position": nums(&group.position),
+            "texcoord": nums(&group.texcoord),
+            "normal": nums(&group.normal),
+        })).collect::<Vec<_>>(),
+        "free_form": obj_data.free_form.iter().map(|element| json!({
+            "kind": format!("{:?}", element.kind),
+            "statement": element.statement,
+            "line": element.location.line,
+            "merging_group": element.merging_group.map(|group| {
+                json!({ "id": group.id, "resolution": num(group.resolution) })
+            }),
+        })).collect::<Vec<_>>(),
+        "skipped": obj_data.skipped.iter().map(|skipped| json!({
+            "statement": skipped.statement,
+            "line": skipped.location.line,
+            "reason": format!("{:?}", skipped.reason),
+        })).collect::<Vec<_>>(),
+    })
+}
+
+fn color_json(color: &Option<Color>) -> Value {
+    match color {
+        None => Value::Null,
+        Some(Color::Rgb(rgb)) => json!({ "rgb": nums(rgb) }),
+        Some(Color::Xyz(xyz)) => json!({ "xyz": nums(xyz) }),
+        Some(Color::Spectral { file, factor }) => {
+            json!({ "spectral": { "file": file, "factor": num(*factor) } })
+        }
+    }
+}
+
+fn material_json(material: &Material) -> Value {
+    let scalar = |value: Option<f32>| value.map(num);
+
+    json!({
+        "name": material.name,
+        "ambient": color_json(&material.ambient),
+        "diffuse": color_json(&material.diffuse),
+        "specular": color_json(&material.specular),
+        "emissive": color_json(&material.emissive),
+        "transmission_filter": color_json(&material.transmission_filter),
+        "shininess": scalar(material.shininess),
+        "dissolve": material.dissolve.map(|dissolve| {
+            json!({ "factor": num(dissolve.factor), "halo": dissolve.halo })
+        }),
+        "optical_density": scalar(material.optical_density),
+        "sharpness": material.sharpness,
+        "illumination_model": material.illumination_model,
+        "roughness": scalar(material.roughness),
+        "metallic": scalar(material.metallic),
+        "sheen": scalar(material.sheen),
+        "clearcoat_thickness": scalar(material.clearcoat_thickness),
+        "clearcoat_roughness": scalar(material.clearcoat_roughness),
+        "anisotropy": scalar(material.anisotropy),
+        "anisotropy_rotation": scalar(material.anisotropy_rotation),
+        "maps": material.maps.iter().map(map_json).collect::<Vec<_>>(),
+        "unknown": material.unknown,
+    })
+}
+
+fn map_json(map: &TextureMap) -> Value {
+    let options = &map.options;
+    let vector = |values: &Option<Vec<f32>>| values.as_deref().map(nums);
+
+    json!({
+        "kind": map.kind.keyword(),
+        "path": map.path,
+        "options": {
+            "blend_u": options.blend_u,
+            "blend_v": options.blend_v,
+            "bump_multiplier": options.bump_multiplier.map(num),
+            "boost": options.boost.map(num),
+            "color_correction": options.color_correction,
+            "clamp": options.clamp,
+            "channel": options.channel,
+            "range": options.range.map(|(base, gain)| nums(&[base, gain])),
+            "offset": vector(&options.offset),
+            "scale": vector(&options.scale),
+            "turbulence": vector(&options.turbulence),
+            "resolution": options.resolution,
+            "projection": options.projection,
+        },
+    })
+}
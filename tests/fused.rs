@@ -0,0 +1,195 @@
+use rust_obj_parser::obj::{ObjError, ObjErrorKind};
+use rust_obj_parser::parser::{self, fused, sequential, Origin, ParseOptions};
+use rust_obj_parser::triangulate::Triangulation;
+
+fn grid(side: usize) -> String {
+    let mut obj_file = String::new();
+
+    for y in 0..=side {
+        for x in 0..=side {
+            obj_file.push_str(&format!("v {} {} 0\n", x, y));
+        }
+    }
+
+    for y in 0..side {
+        if y % 7 == 0 {
+            obj_file.push_str(&format!("g row{}\n", y));
+        }
+
+        for x in 0..side {
+            let corner = y * (side + 1) + x + 1;
+            obj_file.push_str(&format!(
+                "f {} {} {} {}\n",
+                corner,
+                corner + 1,
+                corner + side + 2,
+                corner + side + 1
+            ));
+        }
+    }
+
+    obj_file
+}
+
+#[test]
+fn matches_three_pass_pipeline() {
+    for obj_file in [std::fs::read_to_string("al.obj").unwrap(), grid(60)] {
+        let expected = parser::parse_obj_threaded(obj_file.clone()).unwrap();
+
+        assert_eq!(fused::parse_obj_fused(obj_file).unwrap(), expected);
+    }
+}
+
+#[test]
+fn matches_options() {
+    let obj_file = std::fs::read_to_string("al.obj").unwrap();
+    let options = ParseOptions {
+        triangulation: Triangulation::EarClipping,
+        origin: Some(Origin::FirstVertex),
+        ..ParseOptions::default()
+    };
+
+    let expected = parser::parse_obj_with_options_as::<f64>(&obj_file, &options).unwrap();
+    let (groups, origin) =
+        fused::parse_obj_fused_as::<f64>(obj_file, options.triangulation, options.origin).unwrap();
+
+    assert_eq!(groups, expected.groups);
+    assert_eq!(origin, expected.origin);
+}
+
+#[test]
+fn resolves_relative_indices() {
+    let obj_file = grid(40);
+    let expected = fused::parse_obj_fused(obj_file.clone()).unwrap();
+
+    // The same faces, each written right after its own four vertices.
+    let mut relative = String::new();
+    let positions: Vec<&str> = obj_file
+        .lines()
+        .filter(|line| line.starts_with("v "))
+        .collect();
+
+    for line in obj_file.lines() {
+        if let Some(face) = line.strip_prefix("f ") {
+            for corner in face.split(' ') {
+                let corner: usize = corner.parse().unwrap();
+                relative.push_str(positions[corner - 1]);
+                relative.push('\n');
+            }
+            relative.push_str("f -4 -3 -2 -1\n");
+        } else if line.starts_with('g') {
+            relative.push_str(line);
+            relative.push('\n');
+        }
+    }
+
+    let sequential = sequential::parse_obj_reader(relative.as_bytes(), Triangulation::Fan).unwrap();
+    assert_eq!(sequential, expected);
+    assert_eq!(
+        parser::parse_obj_threaded(relative.clone()).unwrap(),
+        expected
+    );
+    assert_eq!(fused::parse_obj_fused(relative).unwrap(), expected);
+}
+
+#[test]
+fn reports_errors_like_sequential() {
+    let cases = [
+        (40, "v 1 x 3", ObjErrorKind::InvalidNumber("x".to_string())),
+        (
+            1000,
+            "f 1 2 nope",
+            ObjErrorKind::InvalidIndex("nope".to_string()),
+        ),
+        (1800, "f 1 2 99999", ObjErrorKind::IndexOutOfRange(99999)),
+    ];
+
+    for (at, bad, kind) in cases {
+        let mut lines: Vec<String> = grid(30).lines().map(str::to_string).collect();
+        lines.insert(at, bad.to_string());
+        let obj_file = lines.join("\n");
+
+        let expected = ObjError { line: at + 1, kind };
+
+        let sequential = sequential::parse_obj_reader(obj_file.as_bytes(), Triangulation::Fan)
+            .unwrap_err()
+            .downcast::<ObjError>()
+            .unwrap();
+        assert_eq!(*sequential, expected);
+
+        let threaded = parser::parse_obj_threaded(obj_file.clone())
+            .unwrap_err()
+            .downcast::<ObjError>()
+            .unwrap();
+        assert_eq!(*threaded, expected);
+
+        assert_eq!(fused::parse_obj_fused(obj_file).unwrap_err(), expected);
+    }
+
+    let before_vertices = "v 0 0 0\nf 1 -2 1\n";
+    let expected = ObjError {
+        line: 2,
+        kind: ObjErrorKind::IndexOutOfRange(-2),
+    };
+
+    let sequential = sequential::parse_obj_reader(before_vertices.as_bytes(), Triangulation::Fan)
+        .unwrap_err()
+        .downcast::<ObjError>()
+        .unwrap();
+    assert_eq!(*sequential, expected);
+}
+
+#[test]
+fn reports_errors_like_threaded() {
+    let cases = [
+        (
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt x y\nf 1 2 3\n",
+            ObjError {
+                line: 4,
+                kind: ObjErrorKind::InvalidNumber("x".to_string()),
+            },
+        ),
+        (
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 1\nvn 0 nope 1\nf 1 2 3\n",
+            ObjError {
+                line: 5,
+                kind: ObjErrorKind::InvalidNumber("nope".to_string()),
+            },
+        ),
+        (
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nf -1 -2 -9\n",
+            ObjError {
+                line: 4,
+                kind: ObjErrorKind::IndexOutOfRange(-9),
+            },
+        ),
+        (
+            "v 0 0 0\nf 1 -2 1\n",
+            ObjError {
+                line: 2,
+                kind: ObjErrorKind::IndexOutOfRange(-2),
+            },
+        ),
+    ];
+
+    for (obj_file, expected) in cases {
+        let threaded = parser::parse_obj_threaded(obj_file.to_string())
+            .unwrap_err()
+            .downcast::<ObjError>()
+            .unwrap();
+        assert_eq!(*threaded, expected);
+
+        assert_eq!(
+            fused::parse_obj_fused(obj_file.to_string()).unwrap_err(),
+            expected
+        );
+    }
+}
+
+#[test]
+fn empty_input() {
+    assert_eq!(
+        fused::parse_obj_fused(String::new()).unwrap(),
+        parser::parse_obj_threaded(String::new()).unwrap()
+    );
+}
@@ -0,0 +1,40 @@
+//! Minimised crashes of the fuzz targets in `fuzz/`, run through the same checks.
+
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+
+#[path = "../fuzz/src/lib.rs"]
+mod checks;
+
+fn run(target: &str, check: fn(&[u8])) {
+    let mut inputs = 0;
+
+    for entry in fs::read_dir(Path::new("fuzz/regressions").join(target)).unwrap() {
+        let path = entry.unwrap().path();
+        let data = fs::read(&path).unwrap();
+
+        if panic::catch_unwind(AssertUnwindSafe(|| check(&data))).is_err() {
+            panic!("{} fails the {} check", path.display(), target);
+        }
+
+        inputs += 1;
+    }
+
+    assert!(inputs > 0);
+}
+
+#[test]
+fn obj() {
+    run("obj", checks::check_obj);
+}
+
+#[test]
+fn mtl() {
+    run("mtl", checks::check_mtl);
+}
+
+#[test]
+fn obj_round_trip() {
+    run("obj_round_trip", checks::check_obj_round_trip);
+}
@@ -0,0 +1,223 @@
+//! The opt-in `exports_pass_the_khronos_validator` runs the `gltf_validator`
+//! executable, or the one `GLTF_VALIDATOR` points to, on the exported files. Run
+//! it with `cargo test --test gltf_export -- --ignored`.
+
+use std::env;
+use std::fs;
+use std::process::Command;
+
+use rust_obj_parser::gltf::GltfError;
+use rust_obj_parser::{gltf, mtl, obj, parser};
+use serde_json::Value;
+
+fn check_document(json: &Value, bin: &[u8]) {
+    assert_eq!(json["asset"]["version"], "2.0");
+    assert_eq!(json["buffers"][0]["byteLength"], bin.len());
+
+    let views = json["bufferViews"].as_array().unwrap();
+
+    for view in views {
+        let offset = view["byteOffset"].as_u64().unwrap() as usize;
+        let length = view["byteLength"].as_u64().unwrap() as usize;
+        assert_eq!(offset % 4, 0);
+        assert!(offset + length <= bin.len());
+    }
+
+    for mesh in json["meshes"].as_array().unwrap() {
+        for primitive in mesh["primitives"].as_array().unwrap() {
+            let attributes = primitive["attributes"].as_object().unwrap();
+            let counts: Vec<_> = attributes
+                .values()
+                .map(|accessor| {
+                    json["accessors"][accessor.as_u64().unwrap() as usize]["count"].clone()
+                })
+                .collect();
+            assert!(counts.iter().all(|count| *count == counts[0]));
+
+            let position = &json["accessors"][attributes["POSITION"].as_u64().unwrap() as usize];
+            let view = &views[position["bufferView"].as_u64().unwrap() as usize];
+            let offset = view["byteOffset"].as_u64().unwrap() as usize;
+            let length = view["byteLength"].as_u64().unwrap() as usize;
+            let floats: Vec<f32> = bin[offset..offset + length]
+                .chunks(4)
+                .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                .collect();
+
+            for axis in 0..3 {
+                let values = floats.iter().skip(axis).step_by(3);
+                let min = values.clone().copied().fold(f32::INFINITY, f32::min);
+                let max = values.copied().fold(f32::NEG_INFINITY, f32::max);
+                assert_eq!(position["min"][axis].as_f64().unwrap() as f32, min);
+                assert_eq!(position["max"][axis].as_f64().unwrap() as f32, max);
+            }
+        }
+    }
+}
+
+#[test]
+fn al_obj_exports_one_primitive_per_material() {
+    let scene = obj::parse(&fs::read_to_string("al.obj").unwrap()).unwrap();
+    let materials = mtl::parse("newmtl black\nKd 0 0 0\nNs 10\n").unwrap();
+
+    let gltf = gltf::export(&scene, &materials).unwrap();
+    let json: Value = serde_json::from_str(&gltf.to_json("al.bin")).unwrap();
+
+    check_document(&json, gltf.bin());
+    assert_eq!(json["buffers"][0]["uri"], "al.bin");
+
+    let primitives: usize = json["meshes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|mesh| mesh["primitives"].as_array().unwrap().len())
+        .sum();
+    let ranges: std::collections::HashSet<_> = scene
+        .faces
+        .iter()
+        .map(|face| {
+            let state = &scene.states[face.state];
+            (&state.object, &state.groups, &state.material)
+        })
+        .collect();
+    assert_eq!(primitives, ranges.len());
+
+    let black = json["materials"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|material| material["name"] == "black")
+        .unwrap();
+    assert_eq!(
+        black["pbrMetallicRoughness"]["baseColorFactor"],
+        serde_json::json!([0.0, 0.0, 0.0, 1.0])
+    );
+}
+
+#[test]
+fn glb_embeds_json_and_buffer() {
+    let groups = parser::parse_obj_threaded(fs::read_to_string("al.obj").unwrap()).unwrap();
+    let gltf = gltf::export_groups(&groups).unwrap();
+    let glb = gltf.to_glb();
+
+    let word = |i: usize| u32::from_le_bytes([glb[i], glb[i + 1], glb[i + 2], glb[i + 3]]) as usize;
+
+    assert_eq!(&glb[0..4], b"glTF");
+    assert_eq!(word(4), 2);
+    assert_eq!(word(8), glb.len());
+
+    let json_length = word(12);
+    assert_eq!(&glb[16..20], b"JSON");
+    assert_eq!(json_length % 4, 0);
+
+    let json: Value = serde_json::from_slice(&glb[20..20 + json_length]).unwrap();
+    let bin_start = 20 + json_length;
+    assert_eq!(&glb[bin_start + 4..bin_start + 8], b"BIN\0");
+
+    let bin = &glb[bin_start + 8..bin_start + 8 + word(bin_start)];
+    assert!(json["buffers"][0].get("uri").is_none());
+    check_document(&json, &bin[..gltf.bin().len()]);
+}
+
+#[test]
+fn materials_map_to_pbr() {
+    let material = &mtl::parse(
+        "newmtl glass\nKd 0.2 0.4 0.6\nKe 2 0 0\nNs 98\nd 0.5\nmap_Kd tex/glass color.png\nnorm n.png\n",
+    )
+    .unwrap()[0];
+
+    let pbr = gltf::pbr_from_mtl(material);
+
+    assert_eq!(pbr.base_color, [0.2, 0.4, 0.6, 0.5]);
+    assert_eq!(pbr.metallic, 0.0);
+    assert!((pbr.roughness - 0.141_421_36).abs() < 1e-6);
+    assert_eq!(pbr.emissive, [1.0, 0.0, 0.0]);
+    assert_eq!(
+        pbr.base_color_texture.as_deref(),
+        Some("tex/glass color.png")
+    );
+    assert!(pbr.blend);
+
+    let scene = obj::parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 2\nusemtl glass\nf 1//1 2//1 3//1\n")
+        .unwrap();
+    let gltf = gltf::export(&scene, std::slice::from_ref(material)).unwrap();
+    let json: Value = serde_json::from_str(&gltf.to_json("glass.bin")).unwrap();
+
+    check_document(&json, gltf.bin());
+    assert_eq!(json["images"][0]["uri"], "tex/glass%20color.png");
+    assert_eq!(json["materials"][0]["alphaMode"], "BLEND");
+}
+
+#[test]
+fn non_finite_values_are_rejected() {
+    let cases = [
+        ("v 0 0 0\nv nan 0 0\nv 0 1 0\nf 1 2 3\n", "POSITION"),
+        (
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvt inf 0\ng part\nf 1/1 2/2 3/1\n",
+            "TEXCOORD_0",
+        ),
+    ];
+
+    for (obj_file, attribute) in cases {
+        let scene = obj::parse(obj_file).unwrap();
+
+        assert_eq!(
+            gltf::export(&scene, &[]),
+            Err(GltfError::NonFinite {
+                mesh: scene.states[scene.faces[0].state].groups.join(" "),
+                attribute,
+            })
+        );
+    }
+
+    let groups =
+        parser::parse_obj_threaded("v 0 0 0\nv 1 0 0\nv 0 1 -inf\nf 1 2 3\n".to_string()).unwrap();
+
+    assert_eq!(
+        gltf::export_groups(&groups),
+        Err(GltfError::NonFinite {
+            mesh: "group0".to_string(),
+            attribute: "POSITION",
+        })
+    );
+
+    // Normals are replaced rather than rejected
+    let scene = obj::parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nvn nan 0 1\nf 1//1 2//1 3//1\n").unwrap();
+    assert!(gltf::export(&scene, &[]).is_ok());
+}
+
+#[test]
+#[ignore]
+fn exports_pass_the_khronos_validator() {
+    let validator = env::var("GLTF_VALIDATOR").unwrap_or_else(|_| "gltf_validator".to_string());
+    let dir = env::temp_dir().join(format!("rust_obj_parser-gltf-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let scene = obj::parse(&fs::read_to_string("al.obj").unwrap()).unwrap();
+    let materials = mtl::parse("newmtl black\nKd 0 0 0\nNs 10\nd 0.5\n").unwrap();
+    let groups = parser::parse_obj_threaded(fs::read_to_string("al.obj").unwrap()).unwrap();
+
+    let scene_gltf = gltf::export(&scene, &materials).unwrap();
+    let groups_gltf = gltf::export_groups(&groups).unwrap();
+
+    scene_gltf.write_gltf(dir.join("scene.gltf")).unwrap();
+    scene_gltf.write_glb(dir.join("scene.glb")).unwrap();
+    groups_gltf.write_glb(dir.join("groups.glb")).unwrap();
+
+    for name in ["scene.gltf", "scene.glb", "groups.glb"] {
+        let output = Command::new(&validator)
+            .arg("--stdout")
+            .arg(dir.join(name))
+            .output()
+            .unwrap_or_else(|error| panic!("cannot run {}: {}", validator, error));
+
+        let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+
+        assert_eq!(
+            report["issues"]["numErrors"], 0,
+            "{}: {:#}",
+            name, report["issues"]["messages"]
+        );
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
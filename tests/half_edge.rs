@@ -0,0 +1,281 @@
+use rust_obj_parser::mesh;
+use rust_obj_parser::mesh::half_edge::{EditError, HalfEdgeError, HalfEdgeMesh};
+use rust_obj_parser::obj;
+
+const CUBE: &str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+                    v 0 0 1\nv 1 0 1\nv 1 1 1\nv 0 1 1\n\
+                    f 1 4 3 2\nf 5 6 7 8\nf 1 2 6 5\nf 2 3 7 6\nf 3 4 8 7\nf 4 1 5 8\n";
+
+const OCTAHEDRON: &str = "v 1 0 0\nv -1 0 0\nv 0 1 0\nv 0 -1 0\nv 0 0 1\nv 0 0 -1\n\
+                          f 1 3 5\nf 3 2 5\nf 2 4 5\nf 4 1 5\n\
+                          f 3 1 6\nf 2 3 6\nf 4 2 6\nf 1 4 6\n";
+
+// Two by two quads
+const GRID: &str =
+    "v 0 0 0\nv 1 0 0\nv 2 0 0\nv 0 1 0\nv 1 1 0\nv 2 1 0\nv 0 2 0\nv 1 2 0\nv 2 2 0\n\
+                    f 1 2 5 4\nf 2 3 6 5\nf 4 5 8 7\nf 5 6 9 8\n";
+
+fn build(obj_file: &str) -> HalfEdgeMesh {
+    HalfEdgeMesh::from_scene(&obj::parse(obj_file).unwrap()).unwrap()
+}
+
+fn sorted(iter: impl Iterator<Item = usize>) -> Vec<usize> {
+    let mut items: Vec<_> = iter.collect();
+    items.sort_unstable();
+    items
+}
+
+fn check_closed(mesh: &HalfEdgeMesh) -> mesh::MeshReport {
+    let report = mesh::analyze_scene(&mesh.to_scene());
+
+    assert!(report.is_closed_manifold(), "{:?}", report);
+    assert_eq!(report.degenerate_faces, 0);
+    assert_eq!(report.faces, mesh.face_count());
+    assert_eq!(report.vertices, mesh.vertex_count());
+
+    report
+}
+
+#[test]
+fn adjacency_of_a_closed_mesh() {
+    let scene = obj::parse(CUBE).unwrap();
+    let mesh = HalfEdgeMesh::from_scene(&scene).unwrap();
+
+    assert_eq!(mesh.vertex_count(), 8);
+    assert_eq!(mesh.face_count(), 6);
+    assert_eq!(mesh.edge_count(), 12);
+
+    assert_eq!(sorted(mesh.one_ring(0)), vec![1, 3, 4]);
+    assert_eq!(sorted(mesh.vertex_faces(0)), vec![0, 2, 5]);
+    assert_eq!(sorted(mesh.face_neighbours(0)), vec![2, 3, 4, 5]);
+    assert_eq!(mesh.face_vertices(0).collect::<Vec<_>>(), vec![0, 3, 2, 1]);
+    assert!(mesh
+        .vertices()
+        .all(|vertex| !mesh.is_boundary_vertex(vertex)));
+
+    let half_edge = mesh.find_half_edge(0, 3).unwrap();
+    assert_eq!(mesh.destination(half_edge), 3);
+    assert_eq!(mesh.face(half_edge), Some(0));
+    assert_eq!(mesh.face(mesh.twin(half_edge)), Some(5));
+
+    assert_eq!(mesh.to_scene(), scene);
+}
+
+#[test]
+fn boundary_of_an_open_mesh() {
+    let mesh = build(GRID);
+
+    assert!(!mesh.is_boundary_vertex(4));
+    assert_eq!(sorted(mesh.one_ring(4)), vec![1, 3, 5, 7]);
+    assert_eq!(sorted(mesh.one_ring(0)), vec![1, 3]);
+    assert!(mesh
+        .vertices()
+        .filter(|&vertex| vertex != 4)
+        .all(|vertex| mesh.is_boundary_vertex(vertex)));
+    assert!(mesh.faces().all(|face| mesh.is_boundary_face(face)));
+
+    let inner = mesh.find_half_edge(1, 4).unwrap();
+    let outer = mesh.find_half_edge(0, 1).unwrap();
+    assert!(!mesh.is_boundary_edge(inner));
+    assert!(mesh.is_boundary_edge(outer));
+    assert!(mesh.is_boundary_half_edge(mesh.twin(outer)));
+
+    // Boundary half-edges go around the hole
+    let start = mesh.vertex_half_edge(0).unwrap();
+    let mut boundary = vec![start];
+    while mesh.next(*boundary.last().unwrap()) != start {
+        boundary.push(mesh.next(*boundary.last().unwrap()));
+    }
+    assert_eq!(boundary.len(), 8);
+}
+
+#[test]
+fn non_manifold_input_is_rejected() {
+    let cases = [
+        (
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 -1 0\nv 0 0 1\nf 1 2 3\nf 2 1 4\nf 1 2 5\n",
+            HalfEdgeError::NonManifoldEdge { from: 0, to: 1 },
+        ),
+        (
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\nf 1 2 3\nf 2 3 4\n",
+            HalfEdgeError::NonManifoldEdge { from: 1, to: 2 },
+        ),
+        (
+            "v 0 0 0\nv 1 0 0\nv 1 1 0\nv -1 0 0\nv -1 -1 0\nf 1 2 3\nf 1 4 5\n",
+            HalfEdgeError::NonManifoldVertex { vertex: 0 },
+        ),
+        (
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 2 3\n",
+            HalfEdgeError::DegenerateFace { face: 0 },
+        ),
+    ];
+
+    for (obj_file, error) in cases {
+        let scene = obj::parse(obj_file).unwrap();
+        assert_eq!(HalfEdgeMesh::from_scene(&scene), Err(error));
+    }
+}
+
+#[test]
+fn out_of_range_indices_are_rejected() {
+    let scene = obj::parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nf 1 2 3\nf 1/1 3/1 2/1\n").unwrap();
+
+    let mut position = scene.clone();
+    position.faces[1].vertices[2].position = 3;
+    assert_eq!(
+        HalfEdgeMesh::from_scene(&position),
+        Err(HalfEdgeError::IndexOutOfRange {
+            face: 1,
+            attribute: "position",
+            index: 3,
+        })
+    );
+
+    let mut texcoord = scene.clone();
+    texcoord.faces[1].vertices[0].texcoord = Some(1);
+    assert_eq!(
+        HalfEdgeMesh::from_scene(&texcoord),
+        Err(HalfEdgeError::IndexOutOfRange {
+            face: 1,
+            attribute: "texcoord",
+            index: 1,
+        })
+    );
+
+    let mut normal = scene;
+    normal.faces[0].vertices[1].normal = Some(0);
+    assert_eq!(
+        HalfEdgeMesh::from_scene(&normal),
+        Err(HalfEdgeError::IndexOutOfRange {
+            face: 0,
+            attribute: "normal",
+            index: 0,
+        })
+    );
+}
+
+#[test]
+fn flip() {
+    let mut mesh = build("v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3\nf 1 3 4\n");
+    let diagonal = mesh.find_half_edge(0, 2).unwrap();
+
+    mesh.flip_edge(diagonal).unwrap();
+
+    assert_eq!(mesh.find_half_edge(0, 2), None);
+    assert!(mesh.find_half_edge(1, 3).is_some());
+    for face in mesh.faces() {
+        assert_eq!(mesh.face_vertices(face).count(), 3);
+    }
+
+    let report = mesh::analyze_scene(&mesh.to_scene());
+    assert_eq!(report.inconsistent_winding, 0);
+    assert_eq!(report.surface_area, 1.0);
+    assert_eq!(report.boundary_loops, 1);
+
+    let outer = mesh.find_half_edge(0, 1).unwrap();
+    assert_eq!(mesh.flip_edge(outer), Err(EditError::BoundaryEdge));
+
+    let mut grid = build(GRID);
+    let inner = grid.find_half_edge(1, 4).unwrap();
+    assert_eq!(grid.flip_edge(inner), Err(EditError::NotTriangles));
+
+    let mut octahedron = build(OCTAHEDRON);
+    let edge = octahedron.find_half_edge(0, 2).unwrap();
+    octahedron.flip_edge(edge).unwrap();
+    check_closed(&octahedron);
+}
+
+#[test]
+fn split() {
+    let mut mesh = build(OCTAHEDRON);
+    let volume = check_closed(&mesh).signed_volume;
+
+    let edge = mesh.find_half_edge(0, 2).unwrap();
+    let vertex = mesh.split_edge(edge, 0.5);
+
+    assert_eq!(mesh.position(vertex), [0.5, 0.5, 0.0]);
+    assert_eq!(mesh.vertex_count(), 7);
+    assert_eq!(mesh.face_count(), 10);
+    assert_eq!(sorted(mesh.one_ring(vertex)), vec![0, 2, 4, 5]);
+    assert_eq!(check_closed(&mesh).signed_volume, volume);
+
+    // Texcoords are interpolated per face, shared where the faces share them
+    let mut square = build(
+        "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+         vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\nvt 0.5 0.5\n\
+         f 1/1 2/2 3/3\nf 1/1 3/3 4/4\n",
+    );
+    let diagonal = square.find_half_edge(0, 2).unwrap();
+    let center = square.split_edge(diagonal, 0.25);
+
+    let scene = square.to_scene();
+    assert_eq!(scene.faces.len(), 4);
+    assert_eq!(scene.texcoord.len(), 2 * 6);
+    assert_eq!(&scene.texcoord[10..], &[0.25, 0.25]);
+    assert!(scene
+        .faces
+        .iter()
+        .all(|face| face.vertices.iter().all(|vertex| vertex.texcoord.is_some())));
+    assert!(!square.is_boundary_vertex(center));
+
+    let soup = square.triangle_soup();
+    assert_eq!(soup.position.len(), 4 * 9);
+    assert_eq!(soup.texcoord.len(), 4 * 6);
+
+    // Polygons get an extra corner
+    let mut grid = build(GRID);
+    let edge = grid.find_half_edge(0, 1).unwrap();
+    let vertex = grid.split_edge(edge, 0.5);
+
+    assert!(grid.is_boundary_vertex(vertex));
+    assert_eq!(grid.face_count(), 4);
+    assert_eq!(grid.face_vertices(0).count(), 5);
+}
+
+#[test]
+fn collapse() {
+    let mut mesh = build(OCTAHEDRON);
+    let edge = mesh.find_half_edge(0, 2).unwrap();
+
+    assert_eq!(mesh.collapse_edge(edge), Ok(0));
+    assert_eq!(mesh.vertex_count(), 5);
+    assert_eq!(mesh.face_count(), 6);
+    assert_eq!(mesh.edge_count(), 9);
+    assert!(check_closed(&mesh).signed_volume > 0.0);
+
+    // Collapsing further would flatten it into two back to back triangles
+    let tetrahedron = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 0 1\nf 1 3 2\nf 1 2 4\nf 2 3 4\nf 3 1 4\n";
+    let mut mesh = build(tetrahedron);
+    let unchanged = mesh.clone();
+    let edge = mesh.find_half_edge(0, 1).unwrap();
+
+    assert_eq!(mesh.collapse_edge(edge), Err(EditError::NonManifold));
+    assert_eq!(mesh, unchanged);
+
+    // Quads lose a corner
+    let mut cube = build(CUBE);
+    let edge = cube.find_half_edge(0, 1).unwrap();
+
+    cube.collapse_edge(edge).unwrap();
+    assert_eq!(cube.vertex_count(), 7);
+    assert_eq!(cube.face_count(), 6);
+    assert_eq!(cube.edge_count(), 11);
+    check_closed(&cube);
+
+    // An inner edge between two boundary vertices would pinch the strip
+    let mut strip = build(
+        "v 0 0 0\nv 1 0 0\nv 2 0 0\nv 3 0 0\nv 0 1 0\nv 1 1 0\nv 2 1 0\nv 3 1 0\n\
+         f 1 2 6 5\nf 2 3 7 6\nf 3 4 8 7\n",
+    );
+    let inner = strip.find_half_edge(1, 5).unwrap();
+    assert_eq!(strip.collapse_edge(inner), Err(EditError::NonManifold));
+
+    let mut grid = build(GRID);
+    let outer = grid.find_half_edge(0, 1).unwrap();
+    grid.collapse_edge(outer).unwrap();
+
+    let report = mesh::analyze_scene(&grid.to_scene());
+    assert_eq!(report.boundary_loops, 1);
+    assert_eq!(report.non_manifold_vertices, 0);
+    assert_eq!(report.degenerate_faces, 0);
+}
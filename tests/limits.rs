@@ -0,0 +1,169 @@
+use std::io;
+
+use rust_obj_parser::parser::limits::{Limit, LimitExceeded, Limits};
+use rust_obj_parser::parser::{self, ParseOptions};
+
+const QUADS: &str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+                     f 1 2 3 4\ng second\nf 1 2 3 4\nf 4 3 2 1\n";
+
+fn parse(obj_file: &str, limits: Limits) -> Result<parser::ObjData, Box<dyn std::error::Error>> {
+    let options = ParseOptions {
+        limits,
+        ..ParseOptions::default()
+    };
+
+    parser::parse_obj_with_options(obj_file, &options)
+}
+
+fn exceeded(obj_file: &str, limits: Limits) -> LimitExceeded {
+    *parse(obj_file, limits)
+        .unwrap_err()
+        .downcast::<LimitExceeded>()
+        .unwrap()
+}
+
+#[test]
+fn limits_at_the_input_size_pass() {
+    let expected = parser::parse_obj_threaded(QUADS.to_string()).unwrap();
+
+    // Three faces of two triangles each, three `f32` per corner
+    let exact = Limits {
+        max_input_bytes: QUADS.len(),
+        max_vertices: 4,
+        max_faces: 3,
+        max_vertices_per_face: 4,
+        max_groups: 2,
+        max_output_bytes: 3 * 2 * 3 * 3 * 4,
+        max_include_depth: 0,
+    };
+
+    assert_eq!(parse(QUADS, exact).unwrap().groups, expected);
+
+    let obj_file = std::fs::read_to_string("al.obj").unwrap();
+    let expected = parser::parse_obj_threaded(obj_file.clone()).unwrap();
+
+    assert_eq!(
+        parse(&obj_file, Limits::untrusted()).unwrap().groups,
+        expected
+    );
+}
+
+#[test]
+fn every_limit_is_reported() {
+    let cases = [
+        (
+            Limits {
+                max_input_bytes: 10,
+                ..Limits::default()
+            },
+            Limit::InputBytes,
+            10,
+            QUADS.len(),
+        ),
+        (
+            Limits {
+                max_vertices: 3,
+                ..Limits::default()
+            },
+            Limit::Vertices,
+            3,
+            4,
+        ),
+        (
+            Limits {
+                max_faces: 2,
+                ..Limits::default()
+            },
+            Limit::Faces,
+            2,
+            3,
+        ),
+        (
+            Limits {
+                max_groups: 1,
+                ..Limits::default()
+            },
+            Limit::Groups,
+            1,
+            2,
+        ),
+        (
+            Limits {
+                max_vertices_per_face: 3,
+                ..Limits::default()
+            },
+            Limit::VerticesPerFace,
+            3,
+            4,
+        ),
+    ];
+
+    for (limits, limit, max, found) in cases {
+        assert_eq!(exceeded(QUADS, limits), LimitExceeded { limit, max, found });
+    }
+}
+
+#[test]
+fn output_stops_at_the_limit() {
+    // Every face of a large fan reserves its triangles before writing them
+    let mut obj_file = String::from("v 0 0 0\nv 1 0 0\nv 1 1 0\n");
+    for _ in 0..100_000 {
+        obj_file.push_str("f 1 2 3\n");
+    }
+
+    let limits = Limits {
+        max_output_bytes: 1 << 20,
+        ..Limits::default()
+    };
+
+    let error = exceeded(&obj_file, limits);
+    assert_eq!(error.limit, Limit::OutputBytes);
+    assert_eq!(error.max, 1 << 20);
+    assert!(error.found > 1 << 20);
+}
+
+#[test]
+fn includes_count_towards_the_input() {
+    let included = "v 0 0 0\n".repeat(100);
+    let options = ParseOptions {
+        include_resolver: Some(Box::new(move |_: &str| {
+            Ok::<_, io::Error>(included.clone())
+        })),
+        limits: Limits {
+            max_input_bytes: 500,
+            ..Limits::default()
+        },
+        ..ParseOptions::default()
+    };
+
+    let error = parser::parse_obj_with_options("call part.obj\n", &options)
+        .unwrap_err()
+        .downcast::<LimitExceeded>()
+        .unwrap();
+
+    assert_eq!(error.limit, Limit::InputBytes);
+    assert_eq!(error.found, 800);
+
+    let options = ParseOptions {
+        include_resolver: Some(Box::new(|_: &str| Ok("call self.obj\n".to_string()))),
+        limits: Limits {
+            max_include_depth: 3,
+            ..Limits::default()
+        },
+        ..ParseOptions::default()
+    };
+
+    let error = parser::parse_obj_with_options("call self.obj\n", &options)
+        .unwrap_err()
+        .downcast::<LimitExceeded>()
+        .unwrap();
+
+    assert_eq!(
+        *error,
+        LimitExceeded {
+            limit: Limit::IncludeDepth,
+            max: 3,
+            found: 4,
+        }
+    );
+}
@@ -0,0 +1,191 @@
+use rust_obj_parser::mesh::{self, Bounds};
+use rust_obj_parser::obj;
+use rust_obj_parser::parser;
+
+const CUBE: &str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+                    v 0 0 1\nv 1 0 1\nv 1 1 1\nv 0 1 1\n\
+                    f 1 4 3 2\nf 5 6 7 8\nf 1 2 6 5\nf 2 3 7 6\nf 3 4 8 7\nf 4 1 5 8\n";
+
+fn analyze(obj_file: &str) -> mesh::MeshReport {
+    mesh::analyze_scene(&obj::parse(obj_file).unwrap())
+}
+
+#[test]
+fn closed_cube() {
+    let report = analyze(CUBE);
+
+    assert_eq!(report.vertices, 8);
+    assert_eq!(report.faces, 6);
+    assert_eq!(report.triangles, 12);
+    assert_eq!(report.boundary_edges, 0);
+    assert_eq!(report.boundary_loops, 0);
+    assert_eq!(report.connected_components, 1);
+    assert_eq!(report.surface_area, 6.0);
+    assert_eq!(report.signed_volume, 1.0);
+    assert_eq!(
+        report.bounds,
+        Some(Bounds {
+            min: [0.0; 3],
+            max: [1.0; 3]
+        })
+    );
+    assert!(report.is_closed_manifold());
+
+    let json = report.to_json();
+    assert_eq!(json["closed_manifold"], true);
+    assert_eq!(json["surface_area"], 6.0);
+}
+
+#[test]
+fn soups_are_welded() {
+    let groups = parser::parse_obj_threaded(CUBE.to_string()).unwrap();
+    let report = mesh::analyze(&groups[0]);
+
+    assert_eq!(report.vertices, 8);
+    assert_eq!(report.faces, 12);
+    assert_eq!(report.triangles, 12);
+    assert_eq!(report.signed_volume, 1.0);
+    assert!(report.is_closed_manifold());
+
+    let al = std::fs::read_to_string("al.obj").unwrap();
+    let scene = obj::parse(&al).unwrap();
+    let soup = scene.triangle_soup(&scene.faces);
+    let soup_report = mesh::analyze(&soup);
+    let scene_report = mesh::analyze_scene(&scene);
+
+    assert_eq!(soup_report.boundary_edges, scene_report.boundary_edges);
+    assert_eq!(soup_report.bounds, scene_report.bounds);
+    assert_eq!(soup_report.triangles, scene_report.triangles);
+}
+
+#[test]
+fn open_and_flipped_faces() {
+    // The cube without its top, and with its bottom flipped
+    let open = CUBE.replace("f 5 6 7 8\n", "");
+    let report = analyze(&open);
+
+    assert_eq!(report.boundary_edges, 4);
+    assert_eq!(report.boundary_loops, 1);
+    assert!(!report.is_closed_manifold());
+
+    let flipped = CUBE.replace("f 1 4 3 2", "f 1 2 3 4");
+    let report = analyze(&flipped);
+
+    assert_eq!(report.inconsistent_winding, 4);
+    assert_eq!(report.boundary_edges, 0);
+}
+
+#[test]
+fn non_manifold_edges_and_vertices() {
+    // Three triangles on the edge 1-2
+    let fin = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 -1 0\nv 0 0 1\n\
+               f 1 2 3\nf 2 1 4\nf 1 2 5\n";
+    let report = analyze(fin);
+
+    assert_eq!(report.non_manifold_edges, 1);
+    assert_eq!(report.non_manifold_vertices, 0);
+
+    // Two triangles touching at vertex 1, with an unused vertex
+    let bowtie = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv -1 0 0\nv -1 -1 0\nv 9 9 9\n\
+                  f 1 2 3\nf 1 4 5\n";
+    let report = analyze(bowtie);
+
+    assert_eq!(report.non_manifold_vertices, 1);
+    assert_eq!(report.connected_components, 1);
+    assert_eq!(report.boundary_loops, 2);
+    assert_eq!(report.unused_vertices, 1);
+}
+
+/// A 5 by 5 grid of quads without the ones at `holes`.
+fn grid_with_holes(holes: &[(usize, usize)]) -> String {
+    let mut obj_file = String::new();
+
+    for y in 0..6 {
+        for x in 0..6 {
+            obj_file.push_str(&format!("v {} {} 0\n", x, y));
+        }
+    }
+
+    for y in 0..5 {
+        for x in 0..5 {
+            if !holes.contains(&(x, y)) {
+                let corner = y * 6 + x + 1;
+                obj_file.push_str(&format!(
+                    "f {} {} {} {}\n",
+                    corner,
+                    corner + 1,
+                    corner + 7,
+                    corner + 6
+                ));
+            }
+        }
+    }
+
+    obj_file
+}
+
+#[test]
+fn holes_are_separate_loops() {
+    let report = analyze(&grid_with_holes(&[(1, 1), (3, 3)]));
+
+    assert_eq!(report.boundary_edges, 20 + 8);
+    assert_eq!(report.boundary_loops, 3);
+
+    // Two neighbours make one hole
+    let report = analyze(&grid_with_holes(&[(1, 1), (2, 1)]));
+
+    assert_eq!(report.boundary_edges, 20 + 6);
+    assert_eq!(report.boundary_loops, 2);
+
+    let report = analyze(&grid_with_holes(&[(1, 1), (2, 2)]));
+
+    // The quads left at the shared corner each lead from one hole into the
+    // other, so the faces around it are bounded by a single figure of eight
+    assert_eq!(report.boundary_edges, 20 + 8);
+    assert_eq!(report.boundary_loops, 2);
+    assert_eq!(report.non_manifold_vertices, 1);
+}
+
+#[test]
+fn degenerate_duplicate_and_non_finite() {
+    let obj_file = "v 0 0 0\nv 1 0 0\nv 2 0 0\nv 0 1 0\nv nan 0 0\nv inf 1 1\n\
+                    f 1 2 3\nf 1 1 4\nf 1 2 4\nf 4 2 1\nf 1 2 5\n";
+    let report = analyze(obj_file);
+
+    assert_eq!(report.degenerate_faces, 2);
+    assert_eq!(report.duplicate_faces, 1);
+    assert_eq!(report.non_finite_vertices, 2);
+    assert_eq!(report.connected_components, 1);
+    assert_eq!(report.surface_area, 1.0);
+    assert_eq!(
+        report.bounds,
+        Some(Bounds {
+            min: [0.0; 3],
+            max: [2.0, 1.0, 0.0]
+        })
+    );
+
+    let empty = mesh::analyze(&parser::Group {
+        position: vec![],
+        texcoord: vec![],
+        normal: vec![],
+    });
+
+    assert_eq!(empty.bounds, None);
+    assert_eq!(empty.connected_components, 0);
+}
+
+#[test]
+fn out_of_range_faces_are_invalid() {
+    let mut scene = obj::parse(CUBE).unwrap();
+    scene.faces[1].vertices[2].position = 8;
+    let report = mesh::analyze_scene(&scene);
+
+    assert_eq!(report.faces, 6);
+    assert_eq!(report.invalid_faces, 1);
+    assert_eq!(report.triangles, 10);
+    assert_eq!(report.boundary_edges, 4);
+    assert_eq!(report.surface_area, 5.0);
+    assert!(!report.is_closed_manifold());
+    assert_eq!(report.to_json()["invalid_faces"], 1);
+}
@@ -0,0 +1,87 @@
+use std::path::PathBuf;
+
+use rust_obj_parser::mtl::{
+    self,
+    writer::{self, PathRewrite, WriteOptions},
+    Color, MapKind,
+};
+
+const MTL: &str = "\
+# exported by hand
+newmtl skin
+Ka 0.1 0.2 0.3
+Kd 0.5
+Ks xyz 0.25 0.5 0.75
+Ke spectral glow.rfl 0.5
+Tf 1 1 1
+Ns 96.078431
+Ni 1.45
+d -halo 0.75
+sharpness 60
+illum 2
+Pr 0.4
+Pm 0.1
+Ps 0.2
+Pc 0.3
+Pcr 0.05
+aniso 0.6
+anisor 0.25
+map_Kd -blendu off -clamp on -o 0.5 0.25 -s 2 2 1 textures/skin diffuse.png
+bump -bm 0.3 -imfchan l textures\\skin_bump.png
+refl -type sphere -mm 0 1 -texres 512 env.png
+Km 0.5
+
+newmtl eyes
+Tr 0.25
+map_d -t 0.1 alpha.png
+";
+
+#[test]
+fn every_statement_is_kept() {
+    let materials = mtl::parse(MTL).unwrap();
+
+    assert_eq!(materials.len(), 2);
+
+    let skin = &materials[0];
+    assert_eq!(skin.diffuse, Some(Color::Rgb([0.5; 3])));
+    assert_eq!(skin.maps.len(), 3);
+    assert_eq!(skin.maps[0].path, "textures/skin diffuse.png");
+    assert_eq!(skin.maps[0].options.offset, Some(vec![0.5, 0.25]));
+    assert_eq!(skin.maps[1].kind, MapKind::Bump);
+    assert_eq!(skin.unknown, ["Km 0.5"]);
+    assert_eq!(materials[1].dissolve.unwrap().factor, 0.75);
+
+    let written = writer::to_string(&materials, &WriteOptions::default());
+
+    assert_eq!(mtl::parse(&written).unwrap(), materials);
+}
+
+#[test]
+fn texture_paths_are_rewritten() {
+    let materials = mtl::parse(MTL).unwrap();
+
+    let options = WriteOptions {
+        texture_paths: Some(PathRewrite {
+            source_dir: PathBuf::from("/assets/robot"),
+            output_dir: PathBuf::from("/baked/robot/materials"),
+        }),
+        ..WriteOptions::default()
+    };
+    let rewritten = mtl::parse(&writer::to_string(&materials, &options)).unwrap();
+
+    let paths: Vec<_> = rewritten
+        .iter()
+        .flat_map(|material| &material.maps)
+        .map(|map| map.path.as_str())
+        .collect();
+
+    assert_eq!(
+        paths,
+        [
+            "../../../assets/robot/textures/skin diffuse.png",
+            "../../../assets/robot/textures/skin_bump.png",
+            "../../../assets/robot/env.png",
+            "../../../assets/robot/alpha.png",
+        ]
+    );
+}
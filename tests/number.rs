@@ -0,0 +1,165 @@
+mod common;
+
+use common::XorShift;
+use rust_obj_parser::parser::number;
+
+fn decimal(rng: &mut XorShift) -> String {
+    let sign = ["", "-", "+"][rng.below(3) as usize];
+    let mut num = format!("{}{}", sign, rng.digits(12));
+
+    if rng.below(4) > 0 {
+        num.push('.');
+        num.push_str(&rng.digits(12));
+    }
+
+    if rng.below(4) == 0 {
+        let marker = ["e", "E", "e-", "e+"][rng.below(4) as usize];
+        num.push_str(&format!("{}{}", marker, rng.below(60)));
+    }
+
+    num
+}
+
+fn assert_same_f32(num: &str) {
+    let expected = num.parse::<f32>().ok();
+    let parsed = number::parse_f32(num);
+
+    assert_eq!(
+        parsed.map(f32::to_bits),
+        expected.map(f32::to_bits),
+        "{}",
+        num
+    );
+}
+
+fn assert_same_f64(num: &str) {
+    let expected = num.parse::<f64>().ok();
+    let parsed = number::parse_f64(num);
+
+    assert_eq!(
+        parsed.map(f64::to_bits),
+        expected.map(f64::to_bits),
+        "{}",
+        num
+    );
+}
+
+const EDGE_CASES: &[&str] = &[
+    "",
+    "-",
+    "+",
+    ".",
+    "-.",
+    "0",
+    "-0",
+    "+0",
+    "0.0",
+    "-0.0",
+    "1.",
+    ".5",
+    "-.5",
+    "1e",
+    "1e+",
+    "1e-",
+    "e5",
+    "1e5",
+    "1E5",
+    "1e-5",
+    "1e+5",
+    "0e999999",
+    "1e38",
+    "3.4028235e38",
+    "3.4028236e38",
+    "1e39",
+    "1e-45",
+    "1e-46",
+    "16777216",
+    "16777217",
+    "16777217.0",
+    "9007199254740993",
+    "0.1",
+    "0.2",
+    "0.3",
+    "123456.789",
+    "512345.123",
+    "5412345.678",
+    "1.000000059604644775390625",
+    "inf",
+    "-inf",
+    "infinity",
+    "NaN",
+    "nan",
+    "1_0",
+    "1.0.0",
+    "0x10",
+    " 1",
+    "1 ",
+    "1,5",
+    "99999999999999999999",
+    "0.00000000000000000000000001",
+];
+
+#[test]
+fn floats_match_std() {
+    for num in EDGE_CASES {
+        if num.to_ascii_lowercase().contains("nan") {
+            assert!(number::parse_f32(num).unwrap().is_nan());
+            assert!(number::parse_f64(num).unwrap().is_nan());
+            continue;
+        }
+
+        assert_same_f32(num);
+        assert_same_f64(num);
+    }
+
+    let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+
+    for _ in 0..200_000 {
+        let num = decimal(&mut rng);
+        assert_same_f32(&num);
+        assert_same_f64(&num);
+    }
+}
+
+#[test]
+fn obj_numbers_match_std() {
+    let obj_file = std::fs::read_to_string("al.obj").unwrap();
+
+    for line in obj_file.lines() {
+        let mut parts = line.split_whitespace();
+
+        match parts.next() {
+            Some("v") | Some("vt") | Some("vn") => parts.for_each(|num| {
+                assert_same_f32(num);
+                assert_same_f64(num);
+            }),
+            Some("f") => parts.for_each(|index| {
+                assert_eq!(number::parse_usize(index), index.parse().ok(), "{}", index);
+            }),
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn integers_match_std() {
+    let mut rng = XorShift(0x9e37_79b9_7f4a_7c15);
+    let mut cases: Vec<String> = [
+        "",
+        "+",
+        "-1",
+        "+7",
+        "0",
+        "007",
+        "1.0",
+        "18446744073709551615",
+    ]
+    .iter()
+    .map(|case| case.to_string())
+    .collect();
+    cases.extend((0..10_000).map(|_| rng.digits(21)));
+
+    for case in cases {
+        assert_eq!(number::parse_usize(&case), case.parse().ok(), "{}", case);
+    }
+}
@@ -0,0 +1,162 @@
+mod common;
+
+use std::fs;
+
+use common::XorShift;
+use rust_obj_parser::obj::{
+    self,
+    writer::{self, FloatFormat, IndexMode, WriteOptions},
+    Scene,
+};
+
+fn round_trip(scene: &Scene, options: &WriteOptions) -> Scene {
+    obj::parse(&writer::to_string(scene, options)).unwrap()
+}
+
+fn all_index_modes() -> [WriteOptions; 2] {
+    [
+        WriteOptions::default(),
+        WriteOptions {
+            index_mode: IndexMode::Relative,
+            ..WriteOptions::default()
+        },
+    ]
+}
+
+#[test]
+fn al_obj_round_trips() {
+    let scene = obj::parse(&fs::read_to_string("al.obj").unwrap()).unwrap();
+
+    assert_eq!(scene.position_count(), 3618);
+    assert_eq!(scene.faces.len(), 3442);
+    assert_eq!(scene.material_libraries, ["./vp.mtl"]);
+
+    for options in &all_index_modes() {
+        assert_eq!(round_trip(&scene, options), scene);
+    }
+}
+
+#[test]
+fn index_forms_are_kept() {
+    let scene = obj::parse(
+        "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvn 0 0 1\n\
+         f 1 2 3\nf 1/1 2/1 3/1\nf 1//1 2//1 3//1\nf -3/-1/-1 -2/-1/-1 -1/-1/-1\n",
+    )
+    .unwrap();
+
+    let text = writer::to_string(&scene, &WriteOptions::default());
+
+    assert!(text.contains("f 1 2 3\n"));
+    assert!(text.contains("f 1/1 2/1 3/1\n"));
+    assert!(text.contains("f 1//1 2//1 3//1\n"));
+    assert!(text.contains("f 1/1/1 2/1/1 3/1/1\n"));
+
+    for options in &all_index_modes() {
+        assert_eq!(round_trip(&scene, options), scene);
+    }
+}
+
+#[test]
+fn vertex_colors_round_trip() {
+    let scene = obj::parse("v 0 0 0\nv 1 0 0 0.5 0.25 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+
+    assert_eq!(scene.color, [1.0, 1.0, 1.0, 0.5, 0.25, 0.0, 1.0, 1.0, 1.0]);
+
+    for options in &all_index_modes() {
+        assert_eq!(round_trip(&scene, options), scene);
+    }
+}
+
+#[test]
+fn fixed_precision_keeps_topology() {
+    let scene = obj::parse(&fs::read_to_string("al.obj").unwrap()).unwrap();
+
+    let options = WriteOptions {
+        float_format: FloatFormat::Fixed(2),
+        ..WriteOptions::default()
+    };
+    let written = round_trip(&scene, &options);
+
+    assert_eq!(written.faces, scene.faces);
+    assert_eq!(written.states, scene.states);
+
+    for (a, b) in written.position.iter().zip(&scene.position) {
+        assert!((a - b).abs() <= 0.005 + b.abs() * f32::EPSILON);
+    }
+}
+
+fn fuzzed_obj(rng: &mut XorShift) -> String {
+    let mut text = String::new();
+    let (mut positions, mut texcoords, mut normals) = (0, 0, 0);
+
+    for _ in 0..rng.below(200) {
+        match rng.below(10) {
+            0..=2 => {
+                positions += 1;
+                text += &format!("v {} {} {}\n", rng.float(), rng.float(), rng.float());
+            }
+            3 => {
+                texcoords += 1;
+                text += &format!("vt {} {}\n", rng.float(), rng.float());
+            }
+            4 => {
+                normals += 1;
+                text += &format!("vn {} {} {}\n", rng.float(), rng.float(), rng.float());
+            }
+            5..=7 if positions > 0 => {
+                let form = rng.below(4);
+                text += "f";
+
+                for _ in 0..3 + rng.below(4) {
+                    let mut index = |count: u64| {
+                        let index = rng.below(count) as i64;
+                        if rng.below(2) == 0 {
+                            index + 1
+                        } else {
+                            index - count as i64
+                        }
+                    };
+
+                    text += &format!(" {}", index(positions));
+
+                    match form {
+                        1 if texcoords > 0 => text += &format!("/{}", index(texcoords)),
+                        2 if normals > 0 => text += &format!("//{}", index(normals)),
+                        3 if texcoords > 0 && normals > 0 => {
+                            text += &format!("/{}/{}", index(texcoords), index(normals))
+                        }
+                        _ => {}
+                    }
+                }
+
+                text += "\n";
+            }
+            8 => {
+                text += match rng.below(6) {
+                    0 => "g\n",
+                    1 => "g left arm\n",
+                    2 => "o body\n",
+                    3 => "usemtl skin\n",
+                    4 => "s off\n",
+                    _ => "mtllib a.mtl b.mtl\n",
+                }
+            }
+            _ => text += &format!("s {}\n", rng.below(4)),
+        }
+    }
+
+    text
+}
+
+#[test]
+fn fuzzed_scenes_round_trip() {
+    let mut rng = XorShift(0x9E37_79B9_7F4A_7C15);
+
+    for _ in 0..500 {
+        let scene = obj::parse(&fuzzed_obj(&mut rng)).unwrap();
+
+        for options in &all_index_modes() {
+            assert_eq!(round_trip(&scene, options), scene);
+        }
+    }
+}
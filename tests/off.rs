@@ -0,0 +1,180 @@
+use rust_obj_parser::off::{self, OffError};
+use rust_obj_parser::parser::{self, ParseOptions};
+use rust_obj_parser::triangulate::Triangulation;
+
+const COLORED_CUBE: &str = "COFF
+# a unit cube with integer colours
+8 6 12
+0 0 0 255 0 0 255
+1 0 0 0 255 0 255
+1 1 0 0 0 255 255
+0 1 0 255 255 0 255
+0 0 1 255 0 255 255
+1 0 1 0 255 255 255
+1 1 1 255 255 255 255
+0 1 1 0 0 0 255
+4 0 3 2 1
+4 4 5 6 7
+4 0 1 5 4
+4 1 2 6 5
+4 2 3 7 6
+4 3 0 4 7 0.5 0.5 0.5
+";
+
+/// An L shaped hexagon whose first corner cannot see the whole polygon.
+const CONCAVE: &str = "OFF
+6 1 0
+0 0 0
+2 0 0
+2 1 0
+1 1 0
+1 2 0
+0 2 0
+6 2 3 4 5 0 1
+";
+
+fn area(positions: &[f32]) -> f32 {
+    positions
+        .chunks_exact(9)
+        .map(|t| {
+            let (a, b) = ([t[3] - t[0], t[4] - t[1]], [t[6] - t[0], t[7] - t[1]]);
+            (a[0] * b[1] - a[1] * b[0]) / 2.0
+        })
+        .sum()
+}
+
+#[test]
+fn colored_cube() {
+    let scene = off::read(COLORED_CUBE).unwrap();
+
+    assert_eq!(scene.position_count(), 8);
+    assert_eq!(scene.faces.len(), 6);
+    assert_eq!(&scene.color[3..6], &[0.0, 1.0, 0.0]);
+    assert!(scene.faces.iter().all(|face| face.vertices.len() == 4));
+
+    let groups = off::read_groups(COLORED_CUBE, Triangulation::Fan).unwrap();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].position.len(), 6 * 2 * 9);
+}
+
+#[test]
+fn color_scale_is_decided_per_file() {
+    // The first vertex is near black in a file of 0 to 255 colours
+    let bytes = "COFF 3 1 0\n0 0 0 1 1 1 1\n1 0 0 255 0 0 255\n0 1 0 0 51 0 255\n3 0 1 2\n";
+    let scene = off::read(bytes).unwrap();
+    let near_black = 1.0 / 255.0;
+    assert_eq!(&scene.color[..3], &[near_black; 3]);
+    assert_eq!(&scene.color[3..], &[1.0, 0.0, 0.0, 0.0, 0.2, 0.0]);
+
+    let floats = "COFF 3 1 0\n0 0 0 1 1 1 1\n1 0 0 0.5 0 0 1\n0 1 0 0 0.2 0 1\n3 0 1 2\n";
+    let scene = off::read(floats).unwrap();
+    assert_eq!(scene.color, [1.0, 1.0, 1.0, 0.5, 0.0, 0.0, 0.0, 0.2, 0.0]);
+}
+
+#[test]
+fn variants() {
+    let normals = "NOFF 3 1 0\n0 0 0 0 0 1\n1 0 0 0 0 1\n0 1 0 0 0 1\n3 0 1 2\n";
+    let scene = off::read(normals).unwrap();
+    assert_eq!(scene.normal, [0.0, 0.0, 1.0].repeat(3));
+    assert_eq!(scene.faces[0].vertices[2].normal, Some(2));
+
+    let texcoords = "STOFF\n3 1 0\n0 0 0 0 0\n1 0 0 1 0\n0 1 0 0 1\n3 0 1 2\n";
+    let scene = off::read(texcoords).unwrap();
+    assert_eq!(scene.texcoord, [0.0, 0.0, 1.0, 0.0, 0.0, 1.0]);
+
+    let groups = off::read_groups(texcoords, Triangulation::Fan).unwrap();
+    assert_eq!(groups[0].texcoord.len(), 6);
+
+    let homogeneous = "4OFF\n3 1 0\n0 0 0 2\n2 0 0 2\n0 4 0 2\n3 0 1 2\n";
+    let scene = off::read(homogeneous).unwrap();
+    assert_eq!(
+        scene.position,
+        [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 2.0, 0.0]
+    );
+
+    let all = "STCNOFF\n3 1 0\n\
+        0 0 0 0 0 1 1 0 0 0 0\n\
+        1 0 0 0 0 1 0 1 0 1 0\n\
+        0 1 0 0 0 1 0 0 1 0 1\n\
+        3 0 1 2\n";
+    let scene = off::read(all).unwrap();
+    assert_eq!(scene.normal_count(), 3);
+    assert_eq!(scene.texcoord_count(), 3);
+    assert_eq!(&scene.color[6..], &[0.0, 0.0, 1.0]);
+}
+
+#[test]
+fn round_trip() {
+    for off_file in [COLORED_CUBE, CONCAVE] {
+        let scene = off::read(off_file).unwrap();
+
+        let mut written = vec![];
+        off::write(&scene, &mut written).unwrap();
+        let written = String::from_utf8(written).unwrap();
+
+        assert_eq!(off::read(&written).unwrap(), scene, "{}", written);
+    }
+}
+
+#[test]
+fn obj_scenes_are_written_with_one_index_per_vertex() {
+    let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 1\nf 1//1 2//1 3//1\n";
+    let scene = rust_obj_parser::obj::parse(obj).unwrap();
+
+    let mut written = vec![];
+    off::write(&scene, &mut written).unwrap();
+    let written = String::from_utf8(written).unwrap();
+
+    assert!(written.starts_with("NOFF\n3 1 0\n"), "{}", written);
+    assert_eq!(
+        off::read(&written).unwrap().normal,
+        [0.0, 0.0, 1.0].repeat(3)
+    );
+}
+
+#[test]
+fn ear_clipping_keeps_concave_faces_inside() {
+    let fan = off::read_groups(CONCAVE, Triangulation::Fan).unwrap();
+    let clipped = off::read_groups(CONCAVE, Triangulation::EarClipping).unwrap();
+
+    assert_eq!(clipped[0].position.len(), 4 * 9);
+    assert_eq!(area(&clipped[0].position), 3.0);
+
+    // The fan covers area outside of the polygon with an inverted triangle.
+    let fan_areas: Vec<f32> = fan[0].position.chunks_exact(9).map(area).collect();
+    assert!(fan_areas.iter().any(|&area| area < 0.0));
+}
+
+#[test]
+fn parser_uses_the_same_triangulation() {
+    let obj = "v 0 0 0\nv 2 0 0\nv 2 1 0\nv 1 1 0\nv 1 2 0\nv 0 2 0\nf 3 4 5 6 1 2\n";
+
+    let options = ParseOptions {
+        triangulation: Triangulation::EarClipping,
+        ..ParseOptions::default()
+    };
+    let parsed = parser::parse_obj_with_options(obj, &options).unwrap();
+    let off = off::read_groups(CONCAVE, Triangulation::EarClipping).unwrap();
+
+    assert_eq!(parsed.groups, off);
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        off::read("PLY\n0 0 0\n"),
+        Err(OffError::InvalidHeader("PLY".to_string()))
+    );
+    assert_eq!(
+        off::read("OFF\n3 1 0\n0 0 0\n"),
+        Err(OffError::UnexpectedEof)
+    );
+    assert!(matches!(
+        off::read("OFF\n1 1 0\n0 0 0\n3 0 1 2\n"),
+        Err(OffError::InvalidLine { line: 4, .. })
+    ));
+    assert!(matches!(
+        off::read("COFF\n1 0 0\n0 0 0\n"),
+        Err(OffError::InvalidLine { line: 3, .. })
+    ));
+}
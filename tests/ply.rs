@@ -0,0 +1,177 @@
+use std::fs;
+
+use rust_obj_parser::{obj, ply};
+
+const FORMATS: [ply::Format; 3] = [
+    ply::Format::Ascii,
+    ply::Format::BinaryLittleEndian,
+    ply::Format::BinaryBigEndian,
+];
+
+fn written(scene: &obj::Scene, format: ply::Format) -> Vec<u8> {
+    let mut buffer = vec![];
+    ply::write(scene, &mut buffer, format).unwrap();
+    buffer
+}
+
+const SCAN: &str = "ply
+format ascii 1.0
+comment scanner output
+element vertex 4
+property float x
+property float y
+property float z
+property float nx
+property float ny
+property float nz
+property uchar red
+property uchar green
+property uchar blue
+property float confidence
+property ushort intensity
+element face 2
+property list uchar int vertex_indices
+property uchar flags
+element edge 1
+property int vertex1
+property int vertex2
+end_header
+0 0 0 0 0 1 255 0 0 0.5 1000
+1 0 0 0 0 1 0 255 0 0.75 2000
+1 1 0 0 0 1 0 0 255 1 3000
+0 1 0 0 0 1 51 51 51 0.25 4000
+3 0 1 2 7
+3 0 2 3 7
+0 1
+";
+
+#[test]
+fn custom_properties_are_kept() {
+    let scene = ply::read(SCAN.as_bytes()).unwrap();
+
+    assert_eq!(scene.position_count(), 4);
+    assert_eq!(scene.normal_count(), 4);
+    assert_eq!(&scene.color[..3], &[1.0, 0.0, 0.0]);
+    assert_eq!(scene.color[9], 0.2);
+    assert_eq!(scene.faces.len(), 2);
+    assert_eq!(scene.faces[1].vertices[2].normal, Some(3));
+
+    let names: Vec<_> = scene.attributes.iter().map(|a| a.name.as_str()).collect();
+    assert_eq!(names, ["confidence", "intensity"]);
+    assert_eq!(scene.attributes[1].values, [1000.0, 2000.0, 3000.0, 4000.0]);
+
+    for format in FORMATS {
+        assert_eq!(ply::read(&written(&scene, format)).unwrap(), scene);
+    }
+}
+
+#[test]
+fn al_obj_round_trips() {
+    let scene = obj::parse(&fs::read_to_string("al.obj").unwrap()).unwrap();
+
+    for format in FORMATS {
+        let read = ply::read(&written(&scene, format)).unwrap();
+
+        assert_eq!(read.position, scene.position);
+        assert_eq!(
+            read.faces
+                .iter()
+                .map(|face| &face.vertices)
+                .collect::<Vec<_>>(),
+            scene
+                .faces
+                .iter()
+                .map(|face| &face.vertices)
+                .collect::<Vec<_>>()
+        );
+    }
+}
+
+#[test]
+fn split_indices_become_separate_vertices() {
+    let scene = obj::parse(
+        "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 0 1\nvt 0.5 0.5\n\
+         f 1/1 2/2 3/3\nf 1/4 3/3 2/2\n",
+    )
+    .unwrap();
+
+    let read = ply::read(&written(&scene, ply::Format::Ascii)).unwrap();
+
+    assert_eq!(read.position_count(), 4);
+    assert_eq!(
+        scene.triangle_soup(&scene.faces),
+        read.triangle_soup(&read.faces)
+    );
+}
+
+#[test]
+fn truncated_binary_is_an_error() {
+    let scene = ply::read(SCAN.as_bytes()).unwrap();
+    let binary = written(&scene, ply::Format::BinaryLittleEndian);
+
+    assert_eq!(
+        ply::read(&binary[..binary.len() - 1]),
+        Err(ply::PlyError::UnexpectedEof)
+    );
+}
+
+#[test]
+fn header_counts_are_not_trusted() {
+    let huge = "ply\nformat binary_little_endian 1.0\nelement vertex 4000000000\n\
+                property float x\nproperty float y\nproperty float z\n\
+                property float confidence\nend_header\n";
+
+    assert_eq!(
+        ply::read(huge.as_bytes()),
+        Err(ply::PlyError::UnexpectedEof)
+    );
+}
+
+#[test]
+fn vertices_need_a_position() {
+    let ply_file = "ply\nformat binary_little_endian 1.0\nelement vertex 4000000000\nend_header\n";
+
+    assert_eq!(
+        ply::read(ply_file.as_bytes()),
+        Err(ply::PlyError::MissingPosition("x"))
+    );
+
+    let ply_file = "ply\nformat ascii 1.0\nelement vertex 1\n\
+                    property float x\nproperty float y\nend_header\n0 0\n";
+
+    assert_eq!(
+        ply::read(ply_file.as_bytes()),
+        Err(ply::PlyError::MissingPosition("z"))
+    );
+}
+
+#[test]
+fn elements_without_properties_are_skipped() {
+    let ply_file = "ply\nformat binary_little_endian 1.0\nelement junk 18000000000000000000\n\
+                    element vertex 1\nproperty float x\nproperty float y\nproperty float z\n\
+                    element face 18000000000000000000\nend_header\n";
+    let mut ply_file = ply_file.as_bytes().to_vec();
+
+    for num in [1.0f32, 2.0, 3.0] {
+        ply_file.extend_from_slice(&num.to_le_bytes());
+    }
+
+    let start = std::time::Instant::now();
+    let scene = ply::read(&ply_file).unwrap();
+
+    assert!(start.elapsed() < std::time::Duration::from_secs(1));
+    assert_eq!(scene.position, [1.0, 2.0, 3.0]);
+    assert!(scene.faces.is_empty());
+}
+
+#[test]
+fn list_vertex_properties_are_an_error() {
+    let ply_file = "ply\nformat ascii 1.0\nelement vertex 1\n\
+                    property float x\nproperty float y\nproperty float z\n\
+                    property list uchar float weights\nend_header\n0 0 0 2 0.25 0.75\n";
+
+    assert_eq!(
+        ply::read(ply_file.as_bytes()),
+        Err(ply::PlyError::ListVertexProperty("weights".to_string()))
+    );
+}
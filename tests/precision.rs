@@ -0,0 +1,96 @@
+use std::io::Write;
+
+use rust_obj_parser::parser::{self, sequential, Origin, ParseOptions};
+use rust_obj_parser::triangulate::Triangulation;
+
+const UTM: &str = "v 512345.123 5412345.678 101.25
+v 512345.133 5412345.678 101.25
+v 512345.123 5412345.688 101.25
+v 512345.133 5412345.688 101.26
+f 1 2 4 3
+";
+
+#[test]
+fn f64_output_keeps_centimetres() {
+    let groups = parser::parse_obj_threaded_as::<f64>(UTM.to_string()).unwrap();
+    let position = &groups[0].position;
+
+    assert_eq!(position.len(), 2 * 9);
+    assert_eq!(&position[..3], &[512345.123, 5412345.678, 101.25]);
+    assert_eq!(position[3], 512345.133);
+
+    // f32 cannot tell the first two vertices apart by a centimetre.
+    let narrow = parser::parse_obj_threaded(UTM.to_string()).unwrap();
+    assert_eq!(narrow[0].position[0], narrow[0].position[3]);
+}
+
+#[test]
+fn f64_through_every_parser() {
+    let expected = parser::parse_obj_threaded_as::<f64>(UTM.to_string()).unwrap();
+
+    let sequential =
+        sequential::parse_obj_reader_as::<_, f64>(UTM.as_bytes(), Triangulation::Fan).unwrap();
+    assert_eq!(sequential, expected);
+
+    // Compressed input is decoded into the sequential parser
+    let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+    encoder.write_all(UTM.as_bytes()).unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    assert_eq!(
+        parser::parse_obj_bytes_as::<f64>(compressed.clone()).unwrap(),
+        expected
+    );
+    assert_eq!(
+        parser::parse_obj_bytes_with_options_as::<f64>(&compressed, &ParseOptions::default())
+            .unwrap()
+            .groups,
+        expected
+    );
+}
+
+#[test]
+fn f64_matches_f32_layout() {
+    let obj_file = std::fs::read_to_string("al.obj").unwrap();
+
+    let narrow = parser::parse_obj_threaded(obj_file.clone()).unwrap();
+    let wide = parser::parse_obj_threaded_as::<f64>(obj_file).unwrap();
+
+    assert_eq!(narrow.len(), wide.len());
+
+    for (narrow, wide) in narrow.iter().zip(&wide) {
+        let widened: Vec<f32> = wide.position.iter().map(|&num| num as f32).collect();
+        assert_eq!(narrow.position, widened);
+    }
+}
+
+#[test]
+fn rebase_to_local_origin() {
+    let options = ParseOptions {
+        origin: Some(Origin::Offset([512345.0, 5412345.0, 100.0])),
+        ..ParseOptions::default()
+    };
+    let data = parser::parse_obj_with_options(UTM, &options).unwrap();
+    let position = &data.groups[0].position;
+
+    assert_eq!(data.origin, [512345.0, 5412345.0, 100.0]);
+    assert_eq!(&position[..3], &[0.123, 0.678, 1.25]);
+    assert_eq!(&position[3..6], &[0.133, 0.678, 1.25]);
+}
+
+#[test]
+fn rebase_to_first_vertex() {
+    let options = ParseOptions {
+        origin: Some(Origin::FirstVertex),
+        ..ParseOptions::default()
+    };
+    let data = parser::parse_obj_with_options_as::<f64>(UTM, &options).unwrap();
+    let position = &data.groups[0].position;
+
+    assert_eq!(data.origin, [512345.123, 5412345.678, 101.25]);
+    assert_eq!(&position[..3], &[0.0; 3]);
+    assert!((position[3] - 0.01).abs() < 1e-9);
+
+    let defaults = parser::parse_obj_with_options(UTM, &ParseOptions::default()).unwrap();
+    assert_eq!(defaults.origin, [0.0; 3]);
+}
@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+
+use rust_obj_parser::parser::limits::{Limit, Limits};
+use rust_obj_parser::parser::preprocess::{
+    self, FsResolver, IncludeResolver, PreprocessError, DEFAULT_MAX_INCLUDE_DEPTH,
+};
+
+fn files(files: &[(&str, &str)]) -> impl IncludeResolver {
+    let files: HashMap<String, String> = files
+        .iter()
+        .map(|&(name, contents)| (name.to_string(), contents.to_string()))
+        .collect();
+
+    move |filename: &str| {
+        files
+            .get(filename)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, filename.to_string()))
+    }
+}
+
+fn expand(obj_file: &str, resolver: &dyn IncludeResolver) -> Result<String, PreprocessError> {
+    preprocess::preprocess(obj_file, Some(resolver), DEFAULT_MAX_INCLUDE_DEPTH)
+        .map(|preprocessed| preprocessed.source)
+}
+
+#[test]
+fn nested_calls_substitute_their_own_arguments() {
+    let resolver = files(&[
+        ("outer.obj", "v $1 $2 0\ncall inner.obj $2 $1\nv $2 $1 0"),
+        ("inner.obj", "v $1 $1 $2\n"),
+    ]);
+
+    let source = expand("v 0 0 0\ncall outer.obj 1 2\nf 1 2 3\n", &resolver).unwrap();
+
+    assert_eq!(source, "v 0 0 0\nv 1 2 0\nv 2 2 1\nv 2 1 0\nf 1 2 3\n");
+}
+
+#[test]
+fn missing_arguments_are_left_untouched() {
+    let resolver = files(&[("part.obj", "v $1 $3 $10\n# $ and $x stay\n")]);
+
+    let source = expand("call part.obj 4 5\n", &resolver).unwrap();
+
+    assert_eq!(source, "v 4 $3 $10\n# $ and $x stay\n");
+}
+
+#[test]
+fn arguments_cannot_grow_past_the_input_limit() {
+    // 200 kB of references to a 10 kB argument would expand to 1 GB
+    let included = "$1".repeat(100_000);
+    let resolver = files(&[("part.obj", &included)]);
+    let obj_file = format!("call part.obj {}\n", "9".repeat(10_000));
+    let limits = Limits {
+        max_input_bytes: 1 << 20,
+        ..Limits::default()
+    };
+
+    match preprocess::preprocess_limited(&obj_file, Some(&resolver), &limits) {
+        Err(PreprocessError::Limit(error)) => {
+            assert_eq!(error.limit, Limit::InputBytes);
+            assert_eq!(error.max, 1 << 20);
+            assert!(error.found > 1 << 20 && error.found <= (1 << 20) + 10_000);
+        }
+        other => panic!("{:?}", other.map(|preprocessed| preprocessed.source.len())),
+    }
+}
+
+#[test]
+fn unresolvable_calls_fail() {
+    let resolver = files(&[("outer.obj", "v 0 0 0\ncall missing.obj 1\n")]);
+
+    match expand("call outer.obj\n", &resolver) {
+        Err(PreprocessError::Resolve { filename, source }) => {
+            assert_eq!(filename, "missing.obj");
+            assert_eq!(source.kind(), io::ErrorKind::NotFound);
+        }
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn fs_resolver_stays_below_its_root() {
+    let dir =
+        std::env::temp_dir().join(format!("rust_obj_parser-preprocess-{}", std::process::id()));
+    fs::create_dir_all(dir.join("parts")).unwrap();
+    fs::write(dir.join("parts/part.obj"), "v $1 0 0\n").unwrap();
+    fs::write(dir.join("secret.obj"), "v 9 9 9\n").unwrap();
+
+    let resolver = FsResolver::new(dir.join("parts"));
+
+    assert_eq!(
+        expand("call ./part.obj 3\n", &resolver).unwrap(),
+        "v 3 0 0\n"
+    );
+
+    let outside = dir.join("secret.obj");
+    for filename in [
+        "../secret.obj",
+        "sub/../../secret.obj",
+        outside.to_str().unwrap(),
+    ] {
+        match expand(&format!("call {}\n", filename), &resolver) {
+            Err(PreprocessError::Resolve { source, .. }) => {
+                assert_eq!(source.kind(), io::ErrorKind::InvalidInput, "{}", filename);
+            }
+            other => panic!("{}: {:?}", filename, other),
+        }
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
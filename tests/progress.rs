@@ -0,0 +1,121 @@
+use std::sync::Arc;
+use std::thread;
+
+use rust_obj_parser::parser::progress::{CancellationToken, Cancelled, Progress};
+use rust_obj_parser::parser::{self, ParseOptions};
+
+fn grid(side: usize) -> String {
+    let mut obj_file = String::new();
+
+    for y in 0..=side {
+        for x in 0..=side {
+            obj_file.push_str(&format!("v {} {} 0\nvt 0 0\n", x, y));
+        }
+    }
+
+    for y in 0..side {
+        for x in 0..side {
+            let corner = y * (side + 1) + x + 1;
+            obj_file.push_str(&format!(
+                "f {} {} {}\nf {} {} {}\n",
+                corner,
+                corner + 1,
+                corner + side + 2,
+                corner,
+                corner + side + 2,
+                corner + side + 1
+            ));
+        }
+    }
+
+    obj_file
+}
+
+fn bytes_and_lines<'a>(lines: impl Iterator<Item = &'a str>) -> (usize, usize) {
+    lines.fold((0, 0), |(bytes, count), line| {
+        (bytes + line.len(), count + 1)
+    })
+}
+
+#[test]
+fn counts_every_stage() {
+    for obj_file in [std::fs::read_to_string("al.obj").unwrap(), grid(150)] {
+        let progress = Arc::new(Progress::new());
+        let options = ParseOptions {
+            progress: Some(Arc::clone(&progress)),
+            ..ParseOptions::default()
+        };
+
+        let data = parser::parse_obj_with_options(&obj_file, &options).unwrap();
+        assert_eq!(
+            data.groups,
+            parser::parse_obj_threaded(obj_file.clone()).unwrap()
+        );
+
+        let lines = || obj_file.split_inclusive('\n');
+        let vertex = bytes_and_lines(lines().filter(|line| {
+            line.starts_with("v ") || line.starts_with("vt ") || line.starts_with("vn ")
+        }));
+        let index =
+            bytes_and_lines(lines().filter(|line| line.starts_with('f') || line.starts_with('g')));
+
+        assert_eq!(progress.extract.bytes(), obj_file.len());
+        assert_eq!(progress.extract.lines(), obj_file.lines().count());
+        assert_eq!((progress.vertex.bytes(), progress.vertex.lines()), vertex);
+        assert_eq!((progress.index.bytes(), progress.index.lines()), index);
+    }
+}
+
+#[test]
+fn cancelled_parses_return_cancelled() {
+    let token = CancellationToken::new();
+    token.cancel();
+
+    let options = ParseOptions {
+        cancellation: Some(token.clone()),
+        ..ParseOptions::default()
+    };
+
+    let error = parser::parse_obj_with_options(&grid(20), &options).unwrap_err();
+    assert_eq!(error.downcast_ref::<Cancelled>(), Some(&Cancelled));
+
+    // An untouched token does not stop anything.
+    let options = ParseOptions {
+        cancellation: Some(CancellationToken::new()),
+        ..ParseOptions::default()
+    };
+    assert!(parser::parse_obj_with_options(&grid(20), &options).is_ok());
+}
+
+#[test]
+fn cancel_from_another_thread() {
+    let obj_file = grid(400);
+    let progress = Arc::new(Progress::new());
+    let token = CancellationToken::new();
+
+    let watcher = {
+        let progress = Arc::clone(&progress);
+        let token = token.clone();
+        thread::spawn(move || {
+            while progress.extract.lines() == 0 {
+                thread::yield_now();
+            }
+            token.cancel();
+        })
+    };
+
+    let options = ParseOptions {
+        progress: Some(Arc::clone(&progress)),
+        cancellation: Some(token),
+        ..ParseOptions::default()
+    };
+
+    let result = parser::parse_obj_with_options(&obj_file, &options);
+    watcher.join().unwrap();
+
+    // The watcher cancels as soon as the first lines are counted, which for a file
+    // of this size is long before the last stage starts.
+    let error = result.unwrap_err();
+    assert!(error.is::<Cancelled>());
+    assert!(progress.index.lines() < obj_file.lines().count() / 2);
+}
@@ -0,0 +1,29 @@
+#![cfg(feature = "rayon")]
+
+use rust_obj_parser::parser::{self, fused, sequential};
+use rust_obj_parser::triangulate::Triangulation;
+
+#[test]
+fn runs_on_the_callers_pool() {
+    let obj_file = std::fs::read_to_string("al.obj").unwrap();
+    let expected = sequential::parse_obj_reader(obj_file.as_bytes(), Triangulation::Fan).unwrap();
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(3)
+        .thread_name(|index| format!("caller-{}", index))
+        .build()
+        .unwrap();
+
+    let (groups, timings) =
+        pool.install(|| parser::parse_obj_threaded_timed(obj_file.clone()).unwrap());
+
+    assert_eq!(groups, expected);
+
+    for stage in [&timings.extract, &timings.vertex, &timings.index] {
+        assert_eq!(stage.len(), 3);
+        assert_eq!(stage.iter().map(|timing| timing.chunks).sum::<usize>(), 64);
+    }
+
+    let groups = pool.install(|| fused::parse_obj_fused(obj_file).unwrap());
+    assert_eq!(groups, expected);
+}
@@ -0,0 +1,129 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use rust_obj_parser::parser::thread_pool::ThreadPool;
+use rust_obj_parser::parser::{self, sequential};
+use rust_obj_parser::triangulate::Triangulation;
+
+#[test]
+fn every_chunk_runs_once() {
+    let pool = ThreadPool::new(4);
+    let runs: Arc<Vec<AtomicUsize>> = Arc::new((0..37).map(|_| AtomicUsize::new(0)).collect());
+
+    let counted = Arc::clone(&runs);
+    let timings = pool.run_chunks(37, move |chunk| {
+        counted[chunk].fetch_add(1, Ordering::SeqCst);
+    });
+
+    assert!(runs.iter().all(|runs| runs.load(Ordering::SeqCst) == 1));
+    assert_eq!(timings.len(), 4);
+    assert_eq!(
+        timings.iter().map(|timing| timing.chunks).sum::<usize>(),
+        37
+    );
+    assert_eq!(
+        timings
+            .iter()
+            .map(|timing| timing.worker)
+            .collect::<Vec<_>>(),
+        [0, 1, 2, 3]
+    );
+
+    // The pool is reusable, including with fewer chunks than workers.
+    let timings = pool.run_chunks(2, |_| {});
+    assert_eq!(timings.iter().map(|timing| timing.chunks).sum::<usize>(), 2);
+}
+
+#[test]
+fn idle_workers_steal() {
+    let pool = ThreadPool::new(4);
+
+    // All the slow chunks are in the first worker's run.
+    let timings = pool.run_chunks(16, |chunk| {
+        if chunk < 4 {
+            thread::sleep(Duration::from_millis(50));
+        }
+    });
+
+    assert!(timings.iter().map(|timing| timing.stolen).sum::<usize>() > 0);
+    assert!(timings[0].chunks < 4);
+}
+
+#[test]
+fn timed_parse_matches_sequential() {
+    let obj_file = std::fs::read_to_string("al.obj").unwrap();
+
+    let (groups, timings) = parser::parse_obj_threaded_timed(obj_file.clone()).unwrap();
+    let expected = sequential::parse_obj_reader(obj_file.as_bytes(), Triangulation::Fan).unwrap();
+
+    assert_eq!(groups, expected);
+
+    for stage in [&timings.extract, &timings.vertex, &timings.index] {
+        #[cfg(not(feature = "rayon"))]
+        assert_eq!(stage.len(), 4);
+        assert_eq!(stage.iter().map(|timing| timing.chunks).sum::<usize>(), 64);
+        assert!(stage.iter().all(|timing| timing.busy <= timing.elapsed));
+    }
+}
+
+#[test]
+fn scope_borrows_and_keeps_order() {
+    let pool = ThreadPool::new(4);
+    let words: Vec<String> = ["zero", "one", "two", "three"]
+        .iter()
+        .map(|word| word.to_string())
+        .collect();
+    let lengths = pool.scope(|id| {
+        thread::sleep(Duration::from_millis(10 * (4 - id) as u64));
+        words[id].len()
+    });
+
+    assert_eq!(lengths, [4, 3, 3, 5]);
+
+    // run_chunks also borrows
+    let runs: Vec<AtomicUsize> = (0..9).map(|_| AtomicUsize::new(0)).collect();
+    pool.run_chunks(9, |chunk| {
+        runs[chunk].fetch_add(1, Ordering::SeqCst);
+    });
+    assert!(runs.iter().all(|runs| runs.load(Ordering::SeqCst) == 1));
+}
+
+#[test]
+fn worker_panics_are_reported() {
+    let pool = ThreadPool::new(4);
+
+    let panicked = pool
+        .try_scope(|id| {
+            if id % 2 == 1 {
+                panic!("worker {} failed", id);
+            }
+            id
+        })
+        .unwrap_err();
+
+    assert_eq!(panicked.worker, 1);
+    assert_eq!(panicked.message(), Some("worker 1 failed"));
+    assert_eq!(panicked.to_string(), "worker 1 panicked: worker 1 failed");
+
+    // Every worker is still alive.
+    assert_eq!(pool.scope(|id| id * 2), [0, 2, 4, 6]);
+}
+
+#[test]
+fn scope_reraises_panics() {
+    let pool = ThreadPool::new(4);
+
+    let payload = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        pool.run_chunks(16, |chunk| {
+            if chunk == 11 {
+                std::panic::panic_any(chunk);
+            }
+        })
+    }))
+    .unwrap_err();
+
+    assert_eq!(payload.downcast_ref::<usize>(), Some(&11));
+    assert_eq!(pool.run_chunks(16, |_| {}).len(), 4);
+}
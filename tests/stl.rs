@@ -0,0 +1,79 @@
+use std::fs;
+
+use rust_obj_parser::{obj, parser, stl};
+
+fn al_obj() -> parser::Groups {
+    parser::parse_obj_threaded(fs::read_to_string("al.obj").unwrap()).unwrap()
+}
+
+fn written(groups: &[parser::VertexData], format: stl::Format) -> Vec<u8> {
+    let mut buffer = vec![];
+    stl::write(groups, &mut buffer, format).unwrap();
+    buffer
+}
+
+#[test]
+fn binary_and_ascii_round_trip() {
+    let groups = al_obj();
+    let positions: Vec<f32> = groups
+        .iter()
+        .flat_map(|group| group.position.clone())
+        .collect();
+
+    for format in [stl::Format::Binary, stl::Format::Ascii] {
+        let stl_file = written(&groups, format);
+        assert_eq!(stl::detect_format(&stl_file), format);
+
+        let read = stl::read(&stl_file).unwrap();
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].position, positions);
+        assert_eq!(read[0].normal.len(), positions.len());
+    }
+}
+
+#[test]
+fn binary_with_solid_header_is_detected() {
+    let mut stl_file = written(&al_obj(), stl::Format::Binary);
+    stl_file[..6].copy_from_slice(b"solid ");
+
+    assert_eq!(stl::detect_format(&stl_file), stl::Format::Binary);
+    assert!(stl::read(&stl_file[..stl_file.len() - 1]).is_err());
+}
+
+#[test]
+fn welding_recovers_shared_vertices() {
+    let scene = obj::parse(&fs::read_to_string("al.obj").unwrap()).unwrap();
+    let soup = scene.triangle_soup(&scene.faces);
+
+    let welded =
+        stl::read_indexed(&written(std::slice::from_ref(&soup), stl::Format::Binary)).unwrap();
+
+    let used: std::collections::HashSet<_> = scene
+        .faces
+        .iter()
+        .flat_map(|face| face.vertices.iter().map(|vertex| vertex.position))
+        .collect();
+
+    assert!(welded.position_count() <= used.len());
+    assert_eq!(welded.faces.len(), soup.position.len() / 9);
+    assert_eq!(welded.triangle_soup(&welded.faces).position, soup.position);
+}
+
+#[test]
+fn ascii_solids_become_groups() {
+    let stl_file = "solid a\n\
+        facet normal 0 0 0\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nvertex 0 1 0\nendloop\nendfacet\n\
+        endsolid a\n\
+        solid b\n\
+        facet normal 0 0 1\nouter loop\nvertex 0 0 1\nvertex 1 0 1\nvertex 0 1 1\nendloop\nendfacet\n\
+        endsolid b\n";
+
+    let groups = stl::read(stl_file.as_bytes()).unwrap();
+
+    assert_eq!(groups.len(), 2);
+    assert_eq!(groups[0].normal, [0.0, 0.0, 1.0].repeat(3));
+
+    let scene = stl::read_indexed(stl_file.as_bytes()).unwrap();
+    assert_eq!(scene.states[1].object.as_deref(), Some("b"));
+    assert_eq!(scene.position_count(), 6);
+}
@@ -0,0 +1,418 @@
+use bevy_mikktspace::Geometry;
+use rust_obj_parser::mesh::tangent::{self, TangentError};
+use rust_obj_parser::obj;
+use rust_obj_parser::parser::Group;
+
+fn soup(obj_file: &str) -> Group {
+    let scene = obj::parse(obj_file).unwrap();
+    scene.triangle_soup(&scene.faces)
+}
+
+fn assert_close(tangent: &[f32], expected: [f32; 4]) {
+    for (found, expected) in tangent.iter().zip(&expected) {
+        assert!(
+            (found - expected).abs() < 1e-6,
+            "{:?} != {:?}",
+            tangent,
+            expected
+        );
+    }
+}
+
+#[test]
+fn planar_and_mirrored() {
+    // Two quads, the right one with its UVs mirrored onto the left one
+    let group = soup(
+        "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 2 0 0\nv 2 1 0\n\
+         vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\nvn 0 0 1\n\
+         f 1/1/1 2/2/1 3/3/1 4/4/1\nf 2/2/1 5/1/1 6/4/1 3/3/1\n",
+    );
+    let tangents = tangent::generate(&group).unwrap();
+
+    assert_eq!(tangents.len(), 12 * 4);
+    for (corner, tangent) in tangents.chunks_exact(4).enumerate() {
+        let expected = if corner < 6 {
+            [1.0, 0.0, 0.0, 1.0]
+        } else {
+            [-1.0, 0.0, 0.0, -1.0]
+        };
+
+        assert_eq!(tangent, expected);
+    }
+
+    // The shared edge has the same position, texcoord and normal on both sides,
+    // but is split by the tangent
+    let mesh = tangent::generate_indexed(&group).unwrap();
+
+    assert_eq!(mesh.position.len(), 8 * 3);
+    assert_eq!(mesh.texcoord.len(), 8 * 2);
+    assert_eq!(mesh.normal.len(), 8 * 3);
+    assert_eq!(mesh.tangent.len(), 8 * 4);
+    assert_eq!(mesh.indices, vec![0, 1, 2, 0, 2, 3, 4, 5, 6, 4, 6, 7]);
+    assert_eq!(&mesh.position[3..6], &mesh.position[12..15]);
+    assert_eq!(&mesh.tangent[4..8], &[1.0, 0.0, 0.0, 1.0]);
+    assert_eq!(&mesh.tangent[16..20], &[-1.0, 0.0, 0.0, -1.0]);
+}
+
+#[test]
+fn shared_vertices_average_by_angle() {
+    // Around the origin, one triangle has the tangent x and the other x + y, both
+    // with a right angle there
+    let shared = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv -1 0 0\n\
+                  vt 0 0\nvt 1 0\nvt 0 1\nvt -1 1\nvn 0 0 1\n\
+                  f 1/1/1 2/2/1 3/3/1\nf 1/1/1 3/3/1 4/4/1\n";
+    let tangents = tangent::generate(&soup(shared)).unwrap();
+
+    let (sin, cos) = std::f32::consts::FRAC_PI_8.sin_cos();
+    let half = std::f32::consts::FRAC_1_SQRT_2;
+
+    assert_close(&tangents[..4], [cos, sin, 0.0, 1.0]);
+    assert_close(&tangents[12..16], [cos, sin, 0.0, 1.0]);
+    // They also share the vertex at 0 1 0, with 45 degrees each
+    assert_close(&tangents[8..12], [cos, sin, 0.0, 1.0]);
+    assert_close(&tangents[16..20], [cos, sin, 0.0, 1.0]);
+    // Corners used by one triangle keep its tangent
+    assert_close(&tangents[4..8], [1.0, 0.0, 0.0, 1.0]);
+    assert_close(&tangents[20..24], [half, half, 0.0, 1.0]);
+
+    // A UV seam on the shared edge keeps the triangles apart
+    let seam = shared
+        .replace("vt -1 1\n", "vt -1 1\nvt 0 2\n")
+        .replace("f 1/1/1 3/3/1 4/4/1", "f 1/1/1 3/5/1 4/4/1");
+    let tangents = tangent::generate(&soup(&seam)).unwrap();
+
+    assert_close(&tangents[..4], [1.0, 0.0, 0.0, 1.0]);
+    let fifth = 1.0 / 5.0f32.sqrt();
+    assert_close(&tangents[12..16], [2.0 * fifth, fifth, 0.0, 1.0]);
+}
+
+#[test]
+fn tangents_follow_normals_and_texcoords() {
+    // A cube with a UV square per face, flat normals and one bent normal
+    let cube = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 0 0 1\nv 1 0 1\nv 1 1 1\nv 0 1 1\n\
+                vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\n\
+                vn 0 0 -1\nvn 0 0 1\nvn 0 -1 0\nvn 1 0 0\nvn 0 1 0\nvn -1 0 0\nvn 0.6 0 0.8\n\
+                f 1/1/1 4/2/1 3/3/1 2/4/1\nf 5/1/2 6/2/2 7/3/2 8/4/7\n\
+                f 1/1/3 2/2/3 6/3/3 5/4/3\nf 2/1/4 3/2/4 7/3/4 6/4/4\n\
+                f 3/1/5 4/2/5 8/3/5 7/4/5\nf 4/1/6 1/2/6 5/3/6 8/4/6\n";
+    let group = soup(cube);
+    let tangents = tangent::generate(&group).unwrap();
+
+    // Every face's tangent runs along its first edge
+    for (face, expected) in [
+        [0.0, 1.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0],
+        [-1.0, 0.0, 0.0],
+        [0.0, -1.0, 0.0],
+    ]
+    .iter()
+    .enumerate()
+    {
+        assert_close(
+            &tangents[face * 24..face * 24 + 4],
+            [expected[0], expected[1], expected[2], 1.0],
+        );
+    }
+
+    // Projected onto the plane of the bent normal
+    assert_close(&tangents[11 * 4..12 * 4], [0.8, 0.0, -0.6, 1.0]);
+
+    for (tangent, normal) in tangents.chunks_exact(4).zip(group.normal.chunks_exact(3)) {
+        let dot: f32 = tangent.iter().zip(normal).map(|(t, n)| t * n).sum();
+        let length = tangent[..3].iter().map(|t| t * t).sum::<f32>().sqrt();
+
+        assert!(dot.abs() < 1e-6);
+        assert!((length - 1.0).abs() < 1e-6);
+        assert_eq!(tangent[3].abs(), 1.0);
+    }
+
+    // 24 corners, none shared between faces
+    let mesh = tangent::generate_indexed(&group).unwrap();
+    assert_eq!(mesh.indices.len(), 36);
+    assert_eq!(mesh.position.len(), 24 * 3);
+}
+
+#[test]
+fn degenerate_and_missing_streams() {
+    // The last triangle has no area and takes the space of the first at its corners
+    let obj_file = "v 0 0 0\nv 1 0 0\nv 0 1 0\n\
+                    vt 0 0\nvt 0 1\nvt 1 0\nvn 0 0 1\n\
+                    f 1/1/1 2/2/1 3/3/1\nf 1/1/1 2/2/1 2/2/1\n";
+    let tangents = tangent::generate(&soup(obj_file)).unwrap();
+
+    // The texcoords are mirrored
+    assert_eq!(&tangents[..4], &[0.0, 1.0, 0.0, -1.0]);
+    assert_eq!(&tangents[12..16], &tangents[..4]);
+    assert_eq!(&tangents[16..20], &tangents[4..8]);
+    assert_eq!(&tangents[20..], &tangents[4..8]);
+
+    // Without texcoords to follow, the default space
+    let flat = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvn 0 0 1\nf 1/1/1 2/1/1 3/1/1\n";
+    let tangents = tangent::generate(&soup(flat)).unwrap();
+    assert_eq!(&tangents[..4], &[1.0, 0.0, 0.0, -1.0]);
+
+    let group = soup("v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 1\nf 1//1 2//1 3//1\n");
+    assert_eq!(
+        tangent::generate(&group),
+        Err(TangentError::MissingTexcoords)
+    );
+
+    let group = soup("v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nf 1/1 2/1 3/1\n");
+    assert_eq!(tangent::generate(&group), Err(TangentError::MissingNormals));
+}
+
+/// A triangle soup for the port of `mikktspace.c`.
+struct Reference<'a> {
+    group: &'a Group,
+    tangent: Vec<f32>,
+}
+
+impl Geometry for Reference<'_> {
+    fn num_faces(&self) -> usize {
+        self.group.position.len() / 9
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        let corner = (face * 3 + vert) * 3;
+        let position = &self.group.position[corner..corner + 3];
+        [position[0], position[1], position[2]]
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        let corner = (face * 3 + vert) * 3;
+        let normal = &self.group.normal[corner..corner + 3];
+        [normal[0], normal[1], normal[2]]
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        let corner = (face * 3 + vert) * 2;
+        [self.group.texcoord[corner], self.group.texcoord[corner + 1]]
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        let corner = (face * 3 + vert) * 4;
+        self.tangent[corner..corner + 4].copy_from_slice(&tangent);
+    }
+}
+
+fn assert_matches_reference(obj_file: &str) {
+    let group = soup(obj_file);
+    let mut reference = Reference {
+        group: &group,
+        tangent: vec![0.0; group.position.len() / 3 * 4],
+    };
+
+    assert!(bevy_mikktspace::generate_tangents(&mut reference));
+
+    let tangents = tangent::generate(&group).unwrap();
+    assert_eq!(tangents.len(), reference.tangent.len());
+
+    for (corner, (found, expected)) in tangents
+        .chunks_exact(4)
+        .zip(reference.tangent.chunks_exact(4))
+        .enumerate()
+    {
+        assert_eq!(found[3], expected[3], "handedness of corner {}", corner);
+
+        for (found_num, expected_num) in found.iter().zip(expected) {
+            assert!(
+                (found_num - expected_num).abs() < 1e-6,
+                "corner {}: {:?} != {:?}",
+                corner,
+                found,
+                expected
+            );
+        }
+    }
+}
+
+/// One `v`, `vt` and `vn` per point, each face using the same index for all three.
+fn textured(points: &[([f32; 3], [f32; 2], [f32; 3])], faces: &[Vec<usize>]) -> String {
+    let mut obj_file = String::new();
+
+    for (position, _, _) in points {
+        obj_file.push_str(&format!(
+            "v {} {} {}\n",
+            position[0], position[1], position[2]
+        ));
+    }
+
+    for (_, texcoord, _) in points {
+        obj_file.push_str(&format!("vt {} {}\n", texcoord[0], texcoord[1]));
+    }
+
+    for (_, _, normal) in points {
+        obj_file.push_str(&format!("vn {} {} {}\n", normal[0], normal[1], normal[2]));
+    }
+
+    for face in faces {
+        obj_file.push('f');
+
+        for index in face {
+            obj_file.push_str(&format!(" {0}/{0}/{0}", index + 1));
+        }
+
+        obj_file.push('\n');
+    }
+
+    obj_file
+}
+
+/// Quads between the rows of a `columns` wide grid of points.
+fn grid_faces(rows: usize, columns: usize) -> Vec<Vec<usize>> {
+    let mut faces = vec![];
+
+    for row in 0..rows - 1 {
+        for column in 0..columns - 1 {
+            let corner = row * columns + column;
+            faces.push(vec![
+                corner,
+                corner + 1,
+                corner + columns + 1,
+                corner + columns,
+            ]);
+        }
+    }
+
+    faces
+}
+
+#[test]
+fn sphere_matches_reference() {
+    // The texcoords wrap around with a seam at u = 0, and every quad at a pole has
+    // two corners on it, so one of its triangles is degenerate
+    let (rings, segments) = (9, 16);
+    let mut points = vec![];
+
+    for ring in 0..=rings {
+        for segment in 0..=segments {
+            let u = segment as f32 / segments as f32;
+            let v = ring as f32 / rings as f32;
+            let (sin_theta, cos_theta) = (v * std::f32::consts::PI).sin_cos();
+            let (sin_phi, cos_phi) = (u * 2.0 * std::f32::consts::PI).sin_cos();
+            let normal = [sin_theta * cos_phi, cos_theta, sin_theta * sin_phi];
+
+            points.push((normal, [u, 1.0 - v], normal));
+        }
+    }
+
+    assert_matches_reference(&textured(&points, &grid_faces(rings + 1, segments + 1)));
+}
+
+#[test]
+fn mirrored_terrain_matches_reference() {
+    // The left half mirrors the texcoords of the right one, the middle column is
+    // shared by both
+    let mut points = vec![];
+
+    for row in 0..=6 {
+        for column in -5..=5 {
+            let (x, y) = (column as f32 * 0.5, row as f32 * 0.5);
+            let height = (x * 1.3).sin() * (y * 0.9).cos() * 0.4;
+            let dx = 1.3 * (x * 1.3).cos() * (y * 0.9).cos() * 0.4;
+            let dy = -0.9 * (x * 1.3).sin() * (y * 0.9).sin() * 0.4;
+            let length = (dx * dx + dy * dy + 1.0).sqrt();
+
+            points.push((
+                [x, y, height],
+                [x.abs() / 2.5, y / 3.0],
+                [-dx / length, -dy / length, 1.0 / length],
+            ));
+        }
+    }
+
+    let mut faces = grid_faces(7, 11);
+
+    // A triangle with repeated corners, and one with no area in UV space
+    faces.push(vec![20, 20, 31]);
+    points.push(([0.0, 0.0, 2.0], [0.5, 0.5], [0.0, 0.0, 1.0]));
+    let flat = points.len() - 1;
+    points.push(([1.0, 0.0, 2.0], [0.5, 0.5], [0.0, 0.0, 1.0]));
+    points.push(([0.0, 1.0, 2.0], [0.5, 0.5], [0.0, 0.0, 1.0]));
+    faces.push(vec![flat, flat + 1, flat + 2]);
+
+    assert_matches_reference(&textured(&points, &faces));
+}
+
+#[test]
+fn cube_matches_reference() {
+    // Flat faces with a bent normal, from `tangents_follow_normals_and_texcoords`
+    let cube = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 0 0 1\nv 1 0 1\nv 1 1 1\nv 0 1 1\n\
+                vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\n\
+                vn 0 0 -1\nvn 0 0 1\nvn 0 -1 0\nvn 1 0 0\nvn 0 1 0\nvn -1 0 0\nvn 0.6 0 0.8\n\
+                f 1/1/1 4/2/1 3/3/1 2/4/1\nf 5/1/2 6/2/2 7/3/2 8/4/7\n\
+                f 1/1/3 2/2/3 6/3/3 5/4/3\nf 2/1/4 3/2/4 7/3/4 6/4/4\n\
+                f 3/1/5 4/2/5 8/3/5 7/4/5\nf 4/1/6 1/2/6 5/3/6 8/4/6\n";
+
+    assert_matches_reference(cube);
+}
+
+/// A flat fan of `segments` triangles around the origin, with the texcoords of
+/// the square of each point, so that opposite triangles have opposite tangents.
+fn fan(segments: usize) -> String {
+    let half = segments / 2;
+    let mut points = vec![([0.0, 0.0, 0.0], [0.5, 0.5], [0.0, 0.0, 1.0])];
+
+    for segment in 0..segments {
+        let angle = (segment % half) as f32 / segments as f32 * 2.0 * std::f32::consts::PI;
+        let (sin, cos) = angle.sin_cos();
+        // Opposite points are negated exactly
+        let sign = if segment < half { 1.0 } else { -1.0 };
+        let (sin_2, cos_2) = (2.0 * angle).sin_cos();
+
+        points.push((
+            [sign * cos, sign * sin, 0.0],
+            [0.5 + 0.5 * cos_2, 0.5 + 0.5 * sin_2],
+            [0.0, 0.0, 1.0],
+        ));
+    }
+
+    let faces: Vec<_> = (0..segments)
+        .map(|segment| vec![0, segment + 1, (segment + 1) % segments + 1])
+        .collect();
+
+    textured(&points, &faces)
+}
+
+#[test]
+fn fan_with_opposite_tangents_matches_reference() {
+    assert_matches_reference(&fan(8));
+    assert_matches_reference(&fan(30));
+}
+
+#[test]
+fn large_fans_are_fast() {
+    // A disc with planar texcoords, every triangle sharing the centre. Averaging
+    // the tangents there was once quadratic in the number of triangles
+    let segments = 20_000;
+    let mut points = vec![([0.0, 0.0, 0.0], [0.5, 0.5], [0.0, 0.0, 1.0])];
+
+    for segment in 0..segments {
+        let angle = segment as f32 / segments as f32 * 2.0 * std::f32::consts::PI;
+        let (sin, cos) = angle.sin_cos();
+
+        points.push((
+            [cos, sin, 0.0],
+            [0.5 + 0.5 * cos, 0.5 + 0.5 * sin],
+            [0.0, 0.0, 1.0],
+        ));
+    }
+
+    let faces: Vec<_> = (0..segments)
+        .map(|segment| vec![0, segment + 1, (segment + 1) % segments + 1])
+        .collect();
+
+    let group = soup(&textured(&points, &faces));
+    let start = std::time::Instant::now();
+    let tangents = tangent::generate(&group).unwrap();
+
+    assert!(start.elapsed() < std::time::Duration::from_secs(2));
+    assert_eq!(tangents.len(), segments * 3 * 4);
+    assert_close(&tangents[..4], [1.0, 0.0, 0.0, 1.0]);
+}
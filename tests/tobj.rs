@@ -0,0 +1,282 @@
+//! Compares the attribute buffers of the `tests/corpus` files with what the `tobj`
+//! crate loads from them. It is opt-in, run it with
+//! `cargo test --test tobj -- --ignored`. A file only one side rejects, or where
+//! they disagree, has to be listed in `DEVIATIONS`, or `POSITION_DEVIATIONS` for
+//! the threaded and fused parsers, and a listed file has to still deviate, so the
+//! lists stay a record of where the parsers differ.
+
+use std::fs;
+use std::io::BufReader;
+use std::panic;
+use std::path::{Path, PathBuf};
+
+use rust_obj_parser::parser::{self, fused, Groups};
+use rust_obj_parser::{mtl, obj};
+
+/// Files on which this crate and `tobj` knowingly disagree, and why.
+const DEVIATIONS: &[(&str, &str)] = &[
+    (
+        "obj/groups_and_state.obj",
+        "tobj rejects `usemtl` without a name",
+    ),
+    (
+        "obj/index_forms.obj",
+        "tobj gives corners without a texcoord or normal the first one",
+    ),
+    ("obj/points_and_lines.obj", "tobj reads `l` as a face"),
+    (
+        "obj/positions.obj",
+        "tobj rejects positions with fewer than three coordinates",
+    ),
+    (
+        "obj/two_corner_face.obj",
+        "tobj keeps faces of two vertices",
+    ),
+    (
+        "mtl/colors.mtl",
+        "tobj panics on a one value `Kd` and rejects `Ks xyz`",
+    ),
+    (
+        "mtl/invalid_on_off.mtl",
+        "tobj keeps texture options in the path",
+    ),
+    ("mtl/maps.mtl", "tobj keeps texture options in the path"),
+    (
+        "mtl/missing_argument.mtl",
+        "tobj reads the options of a map without a path as its path",
+    ),
+    ("mtl/multiple.mtl", "tobj rejects `newmtl` without a name"),
+    ("mtl/scalars.mtl", "tobj rejects `d -halo` and ignores `Tr`"),
+    (
+        "mtl/statement_outside_material.mtl",
+        "tobj drops statements before the first `newmtl`",
+    ),
+];
+
+/// Where the positions of the threaded and fused parsers differ from `tobj`.
+const POSITION_DEVIATIONS: &[(&str, &str)] = &[
+    (
+        "obj/comments_and_whitespace.obj",
+        "only `v` followed by a space is a vertex line",
+    ),
+    (
+        "obj/extra_index_part.obj",
+        "only the position of a face vertex is read, tobj rejects a fourth part",
+    ),
+    (
+        "obj/groups_and_state.obj",
+        "tobj rejects `usemtl` without a name",
+    ),
+    ("obj/points_and_lines.obj", "tobj reads `l` as a face"),
+    (
+        "obj/positions.obj",
+        "tobj rejects positions with fewer than three coordinates",
+    ),
+];
+
+#[test]
+#[ignore]
+fn obj_corpus_matches_tobj() {
+    let mut deviating = vec![];
+
+    for path in corpus("obj") {
+        let obj_file = fs::read_to_string(&path).unwrap();
+
+        let ours = obj::parse(&obj_file).map(|scene| {
+            let soup = scene.triangle_soup(&scene.faces);
+            [soup.position, soup.texcoord, soup.normal]
+        });
+
+        if ours.ok() != tobj_soup(&path) {
+            deviating.push(name(&path));
+        }
+    }
+
+    check_deviations(DEVIATIONS, "obj", deviating);
+}
+
+/// The threaded and fused parsers only give positions, one soup per `g` group.
+#[test]
+#[ignore]
+fn obj_corpus_positions_match_tobj() {
+    let mut deviating = vec![];
+
+    for path in corpus("obj") {
+        let obj_file = fs::read_to_string(&path).unwrap();
+        let theirs = tobj_soup(&path).map(|[position, _, _]| position);
+
+        let threaded = parser::parse_obj_threaded(obj_file.clone()).ok();
+        let fused = fused::parse_obj_fused(obj_file).ok();
+
+        if threaded.map(positions) != theirs || fused.map(positions) != theirs {
+            deviating.push(name(&path));
+        }
+    }
+
+    check_deviations(POSITION_DEVIATIONS, "obj", deviating);
+}
+
+#[test]
+#[ignore]
+fn mtl_corpus_matches_tobj() {
+    let mut deviating = vec![];
+
+    for path in corpus("mtl") {
+        let mtl_file = fs::read_to_string(&path).unwrap();
+
+        let ours = mtl::parse(&mtl_file)
+            .ok()
+            .map(|materials| materials.iter().map(basics).collect::<Vec<_>>());
+
+        // tobj panics on some malformed colours
+        let theirs = panic::catch_unwind(|| {
+            let mut reader = BufReader::new(fs::File::open(&path).unwrap());
+            tobj::load_mtl_buf(&mut reader).ok()
+        })
+        .ok()
+        .flatten()
+        .map(|(materials, _)| materials.iter().map(tobj_basics).collect::<Vec<_>>());
+
+        if ours != theirs {
+            deviating.push(name(&path));
+        }
+    }
+
+    check_deviations(DEVIATIONS, "mtl", deviating);
+}
+
+fn corpus(extension: &str) -> Vec<PathBuf> {
+    let mut paths: Vec<_> = fs::read_dir(Path::new("tests/corpus").join(extension))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == extension))
+        .collect();
+
+    paths.sort();
+    paths
+}
+
+fn name(path: &Path) -> String {
+    path.strip_prefix("tests/corpus")
+        .unwrap()
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+fn positions(groups: Groups) -> Vec<f32> {
+    groups
+        .into_iter()
+        .flat_map(|group| group.position)
+        .collect()
+}
+
+fn check_deviations(deviations: &[(&str, &str)], extension: &str, deviating: Vec<String>) {
+    let expected: Vec<_> = deviations
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .filter(|name| name.starts_with(extension))
+        .collect();
+
+    for name in &deviating {
+        assert!(expected.contains(name), "{} deviates from tobj", name);
+    }
+
+    for name in &expected {
+        assert!(deviating.contains(name), "{} no longer deviates", name);
+    }
+}
+
+/// The triangles of every model, in file order, expanded like
+/// `Scene::triangle_soup`: texcoords and normals only if every vertex has them.
+/// `None` if tobj rejects the file.
+fn tobj_soup(path: &Path) -> Option<[Vec<f32>; 3]> {
+    let options = tobj::LoadOptions {
+        single_index: false,
+        triangulate: true,
+        ignore_points: true,
+        ignore_lines: true,
+    };
+
+    let mut reader = BufReader::new(fs::File::open(path).unwrap());
+    let (models, _) = tobj::load_obj_buf(&mut reader, &options, |_| {
+        Err(tobj::LoadError::OpenFileFailed)
+    })
+    .ok()?;
+
+    let meshes = || models.iter().map(|model| &model.mesh);
+    let has_texcoord = meshes().all(|mesh| mesh.texcoord_indices.len() == mesh.indices.len());
+    let has_normal = meshes().all(|mesh| mesh.normal_indices.len() == mesh.indices.len());
+
+    let mut soup = [vec![], vec![], vec![]];
+
+    for mesh in meshes() {
+        for (corner, &index) in mesh.indices.iter().enumerate() {
+            let index = index as usize * 3;
+            soup[0].extend_from_slice(&mesh.positions[index..index + 3]);
+
+            if has_texcoord {
+                let index = mesh.texcoord_indices[corner] as usize * 2;
+                soup[1].extend_from_slice(&mesh.texcoords[index..index + 2]);
+            }
+
+            if has_normal {
+                let index = mesh.normal_indices[corner] as usize * 3;
+                soup[2].extend_from_slice(&mesh.normals[index..index + 3]);
+            }
+        }
+    }
+
+    Some(soup)
+}
+
+type Basics = (
+    String,
+    [Option<[f32; 3]>; 3],
+    [Option<f32>; 3],
+    Option<u32>,
+    Option<String>,
+);
+
+/// What both models of a material have in common.
+fn basics(material: &mtl::Material) -> Basics {
+    let rgb = |color: &Option<mtl::Color>| match color {
+        Some(mtl::Color::Rgb(rgb)) => Some(*rgb),
+        _ => None,
+    };
+
+    let diffuse_texture = material
+        .maps
+        .iter()
+        .find(|map| map.kind == mtl::MapKind::Diffuse)
+        .map(|map| map.path.clone());
+
+    (
+        material.name.clone(),
+        [
+            rgb(&material.ambient),
+            rgb(&material.diffuse),
+            rgb(&material.specular),
+        ],
+        [
+            material.shininess,
+            material.dissolve.map(|dissolve| dissolve.factor),
+            material.optical_density,
+        ],
+        material.illumination_model,
+        diffuse_texture,
+    )
+}
+
+fn tobj_basics(material: &tobj::Material) -> Basics {
+    (
+        material.name.clone(),
+        [material.ambient, material.diffuse, material.specular],
+        [
+            material.shininess,
+            material.dissolve,
+            material.optical_density,
+        ],
+        material.illumination_model.map(u32::from),
+        material.diffuse_texture.clone(),
+    )
+}